@@ -1,15 +1,146 @@
 use crate::git;
 use crate::menu;
+use crate::operations::OperationRegistry;
 use crate::types::{
-    BranchInfo, CommitDiff, CommitInfo, CreateWorktreeOptions, PruneResult, WorkingDiff, Worktree,
+    AmResult, BisectState, BlameLine, BranchComparison, BranchInfo, CommitDiff, CommitGraph,
+    CommitInfo, CommitOneline, ConflictEntry, ConflictVersions, CreateWorktreeOptions,
+    CreateWorktreeResult, DiffHunk, DiffLine, DiffScope, DiffStats, DiffTree, FileBytes,
+    FileCountDelta, FileDiff, FileMtime, FileNumstat, GcResult, HeadInfo, IgnoreResult,
+    IntegrityReport, MarkResolvedResult, OperationProgress, PruneResult, PushTarget, RefEntry,
+    ReflogEntry, RepoProbe, RepoScale, RepoSummary, ResolveResult, SplitRow, StreamedFileDiff,
+    SubmoduleStatus, UntrackedMode, UntrackedSummary, WorkingDiff, Worktree, WorktreeCommits,
     WorktreeStatus,
 };
 use crate::watcher;
 use tauri::async_runtime::spawn_blocking;
+use tauri::{Emitter, State};
 
 #[tauri::command]
-pub async fn list_worktrees(repo_path: String) -> Result<Vec<Worktree>, String> {
-    spawn_blocking(move || git::get_all_worktrees(&repo_path))
+pub async fn list_worktrees(
+    repo_path: String,
+    max_parallelism: Option<usize>,
+) -> Result<Vec<Worktree>, String> {
+    spawn_blocking(move || git::get_all_worktrees(&repo_path, max_parallelism))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_recent_worktrees(
+    repo_path: String,
+    limit: usize,
+) -> Result<Vec<Worktree>, String> {
+    spawn_blocking(move || git::get_recent_worktrees(&repo_path, limit))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn list_worktrees_by_attention(repo_path: String) -> Result<Vec<Worktree>, String> {
+    spawn_blocking(move || git::list_worktrees_by_attention(&repo_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_repo_summary(repo_path: String) -> Result<RepoSummary, String> {
+    spawn_blocking(move || git::get_repo_summary(&repo_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn is_git_repository(path: String) -> Result<RepoProbe, String> {
+    spawn_blocking(move || git::is_git_repository(&path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn init_repository(
+    path: String,
+    initial_branch: Option<String>,
+    bare: bool,
+) -> Result<RepoProbe, String> {
+    spawn_blocking(move || git::init_repository(&path, initial_branch.as_deref(), bare))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Clone with progress reported as "operation-progress" events tagged with operation_id
+#[tauri::command]
+pub async fn clone_repository(
+    app: tauri::AppHandle,
+    operations: State<'_, OperationRegistry>,
+    operation_id: String,
+    url: String,
+    dest: String,
+    bare: bool,
+) -> Result<RepoProbe, String> {
+    let cancel = operations.register(&operation_id);
+    let progress_id = operation_id.clone();
+    let result = spawn_blocking(move || {
+        git::clone_repository(&url, &dest, bare, &cancel, |phase, percent| {
+            let _ = app.emit(
+                "operation-progress",
+                OperationProgress {
+                    operation_id: progress_id.clone(),
+                    phase,
+                    percent,
+                },
+            );
+        })
+    })
+    .await
+    .map_err(|e| e.to_string());
+    operations.finish(&operation_id);
+    result?
+}
+
+/// Run "git gc" with progress reported as "operation-progress" events tagged with operation_id
+#[tauri::command]
+pub async fn run_gc(
+    app: tauri::AppHandle,
+    operations: State<'_, OperationRegistry>,
+    operation_id: String,
+    repo_path: String,
+    aggressive: bool,
+) -> Result<GcResult, String> {
+    let cancel = operations.register(&operation_id);
+    let progress_id = operation_id.clone();
+    let result = spawn_blocking(move || {
+        git::run_gc(&repo_path, aggressive, &cancel, |phase, percent| {
+            let _ = app.emit(
+                "operation-progress",
+                OperationProgress {
+                    operation_id: progress_id.clone(),
+                    phase,
+                    percent,
+                },
+            );
+        })
+    })
+    .await
+    .map_err(|e| e.to_string());
+    operations.finish(&operation_id);
+    result?
+}
+
+#[tauri::command]
+pub fn cancel_operation(operations: State<'_, OperationRegistry>, operation_id: String) -> bool {
+    operations.cancel(&operation_id)
+}
+
+#[tauri::command]
+pub async fn probe_repo_scale(repo_path: String) -> Result<RepoScale, String> {
+    spawn_blocking(move || git::probe_repo_scale(&repo_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn check_integrity(repo_path: String) -> Result<IntegrityReport, String> {
+    spawn_blocking(move || git::check_integrity(&repo_path))
         .await
         .map_err(|e| e.to_string())?
 }
@@ -24,8 +155,30 @@ pub async fn get_commit_history(
     worktree_path: String,
     limit: usize,
     offset: usize,
+    no_merges: bool,
+    first_parent: bool,
 ) -> Result<Vec<CommitInfo>, String> {
-    spawn_blocking(move || git::get_commit_history(&worktree_path, limit, offset))
+    spawn_blocking(move || {
+        git::get_commit_history(&worktree_path, limit, offset, no_merges, first_parent)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_commit_oneline(
+    worktree_path: String,
+    limit: usize,
+    offset: usize,
+) -> Result<Vec<CommitOneline>, String> {
+    spawn_blocking(move || git::get_commit_oneline(&worktree_path, limit, offset))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_commit_graph(worktree_path: String, limit: usize) -> Result<CommitGraph, String> {
+    spawn_blocking(move || git::get_commit_graph(&worktree_path, limit))
         .await
         .map_err(|e| e.to_string())?
 }
@@ -41,15 +194,448 @@ pub async fn get_commit_diff(
 }
 
 #[tauri::command]
-pub async fn get_working_diff(worktree_path: String) -> Result<WorkingDiff, String> {
-    spawn_blocking(move || git::get_working_diff(&worktree_path))
+pub async fn get_revert_preview(
+    worktree_path: String,
+    commit_sha: String,
+) -> Result<CommitDiff, String> {
+    spawn_blocking(move || git::get_revert_preview(&worktree_path, &commit_sha))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Stream a commit's diff as "commit-diff-file" events; resolves with the file count
+#[tauri::command]
+pub async fn stream_commit_diff(
+    app: tauri::AppHandle,
+    worktree_path: String,
+    commit_sha: String,
+) -> Result<u32, String> {
+    spawn_blocking(move || {
+        git::stream_commit_diff(&worktree_path, &commit_sha, |file| {
+            let _ = app.emit(
+                "commit-diff-file",
+                StreamedFileDiff {
+                    commit_sha: commit_sha.clone(),
+                    file,
+                },
+            );
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_commit_file_diff(
+    worktree_path: String,
+    commit_sha: String,
+    file_path: String,
+) -> Result<FileDiff, String> {
+    spawn_blocking(move || git::get_commit_file_diff(&worktree_path, &commit_sha, &file_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_commit_preview(worktree_path: String) -> Result<CommitDiff, String> {
+    spawn_blocking(move || git::get_commit_preview(&worktree_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_staged_file_diff(
+    worktree_path: String,
+    file_path: String,
+) -> Result<FileDiff, String> {
+    spawn_blocking(move || git::get_staged_file_diff(&worktree_path, &file_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn blame_file(
+    worktree_path: String,
+    file_path: String,
+    rev: Option<String>,
+) -> Result<Vec<BlameLine>, String> {
+    spawn_blocking(move || git::blame_file(&worktree_path, &file_path, rev.as_deref()))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn diff_file_against_rev(
+    worktree_path: String,
+    file_path: String,
+    rev: String,
+) -> Result<FileDiff, String> {
+    spawn_blocking(move || git::diff_file_against_rev(&worktree_path, &file_path, &rev))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_diff_context(
+    worktree_path: String,
+    rev: String,
+    file_path: String,
+    start_line: u32,
+    line_count: u32,
+) -> Result<Vec<DiffLine>, String> {
+    spawn_blocking(move || {
+        git::get_diff_context(&worktree_path, &rev, &file_path, start_line, line_count)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn get_split_diff(hunk: DiffHunk) -> Vec<SplitRow> {
+    git::to_split_diff(&hunk)
+}
+
+#[tauri::command]
+pub async fn get_blob(worktree_path: String, sha: String) -> Result<String, String> {
+    spawn_blocking(move || git::get_blob(&worktree_path, &sha))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn read_worktree_file(
+    worktree_path: String,
+    file_path: String,
+    max_bytes: u64,
+) -> Result<FileBytes, String> {
+    spawn_blocking(move || git::read_worktree_file(&worktree_path, &file_path, max_bytes))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn branch_stash_count(worktree_path: String) -> Result<u32, String> {
+    spawn_blocking(move || git::branch_stash_count(&worktree_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_stash_diff(
+    worktree_path: String,
+    index: u32,
+    include_untracked: bool,
+) -> Result<CommitDiff, String> {
+    spawn_blocking(move || git::get_stash_diff(&worktree_path, index, include_untracked))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_working_diff_tree(worktree_path: String) -> Result<DiffTree, String> {
+    spawn_blocking(move || git::get_working_diff_tree(&worktree_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_working_diff(
+    worktree_path: String,
+    scope: Option<DiffScope>,
+    detect_renames: Option<bool>,
+) -> Result<WorkingDiff, String> {
+    spawn_blocking(move || {
+        git::get_working_diff(
+            &worktree_path,
+            scope.unwrap_or_default(),
+            detect_renames.unwrap_or(false),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_range_numstat(
+    worktree_path: String,
+    base: String,
+    head: String,
+) -> Result<Vec<FileNumstat>, String> {
+    spawn_blocking(move || git::get_range_numstat(&worktree_path, &base, &head))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn diff_name_only(worktree_path: String, base: String) -> Result<Vec<String>, String> {
+    spawn_blocking(move || git::diff_name_only(&worktree_path, &base))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_remote_head(repo_path: String, remote: String) -> Result<Option<String>, String> {
+    spawn_blocking(move || git::get_remote_head(&repo_path, &remote))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn file_count_delta(
+    worktree_path: String,
+    base: String,
+    head: String,
+) -> Result<FileCountDelta, String> {
+    spawn_blocking(move || git::file_count_delta(&worktree_path, &base, &head))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// With an operation_id, cancel_operation(operation_id) kills the in-flight scan
+#[tauri::command]
+pub async fn get_worktree_status(
+    operations: State<'_, OperationRegistry>,
+    worktree_path: String,
+    untracked_mode: Option<UntrackedMode>,
+    operation_id: Option<String>,
+) -> Result<WorktreeStatus, String> {
+    let untracked_mode = untracked_mode.unwrap_or_default();
+    let Some(operation_id) = operation_id else {
+        return spawn_blocking(move || {
+            git::get_worktree_status_by_path(&worktree_path, untracked_mode, None)
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+    };
+
+    let cancel = operations.register(&operation_id);
+    let result = spawn_blocking(move || {
+        git::get_worktree_status_by_path(&worktree_path, untracked_mode, Some(&cancel))
+    })
+    .await
+    .map_err(|e| e.to_string());
+    operations.finish(&operation_id);
+    result?
+}
+
+#[tauri::command]
+pub async fn is_worktree_dirty(worktree_path: String) -> Result<bool, String> {
+    spawn_blocking(move || git::is_worktree_dirty(&worktree_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn is_checkout_of(worktree_path: String, rev: String) -> Result<bool, String> {
+    spawn_blocking(move || git::is_checkout_of(&worktree_path, &rev))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_last_modified_file(worktree_path: String) -> Result<Option<FileMtime>, String> {
+    spawn_blocking(move || git::get_last_modified_file(&worktree_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn untracked_summary(worktree_path: String) -> Result<UntrackedSummary, String> {
+    spawn_blocking(move || git::untracked_summary(&worktree_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn check_ignore(
+    worktree_path: String,
+    paths: Vec<String>,
+) -> Result<Vec<IgnoreResult>, String> {
+    spawn_blocking(move || git::check_ignore(&worktree_path, paths))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_worktree_label(worktree_path: String) -> Result<Option<String>, String> {
+    spawn_blocking(move || git::get_worktree_label(&worktree_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn set_worktree_label(worktree_path: String, label: String) -> Result<(), String> {
+    spawn_blocking(move || git::set_worktree_label(&worktree_path, &label))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_head_info(worktree_path: String) -> Result<HeadInfo, String> {
+    spawn_blocking(move || git::get_head_info(&worktree_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_current_branch(worktree_path: String) -> Result<Option<String>, String> {
+    spawn_blocking(move || git::get_current_branch(&worktree_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn detach_head(worktree_path: String) -> Result<HeadInfo, String> {
+    spawn_blocking(move || git::detach_head(&worktree_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn reattach_head(worktree_path: String, branch: String) -> Result<HeadInfo, String> {
+    spawn_blocking(move || git::reattach_head(&worktree_path, &branch))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn rename_current_branch(
+    worktree_path: String,
+    new_name: String,
+) -> Result<HeadInfo, String> {
+    spawn_blocking(move || git::rename_current_branch(&worktree_path, &new_name))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_push_target(worktree_path: String) -> Result<Option<PushTarget>, String> {
+    spawn_blocking(move || git::get_push_target(&worktree_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn set_upstream(worktree_path: String, upstream: String) -> Result<(), String> {
+    spawn_blocking(move || git::set_upstream(&worktree_path, &upstream))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn unset_upstream(worktree_path: String) -> Result<(), String> {
+    spawn_blocking(move || git::unset_upstream(&worktree_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_outgoing_stat(worktree_path: String) -> Result<DiffStats, String> {
+    spawn_blocking(move || git::get_outgoing_stat(&worktree_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_worktree_config(worktree_path: String) -> Result<Vec<(String, String)>, String> {
+    spawn_blocking(move || git::get_worktree_config(&worktree_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_sparse_patterns(worktree_path: String) -> Result<Vec<String>, String> {
+    spawn_blocking(move || git::get_sparse_patterns(&worktree_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_submodule_status(worktree_path: String) -> Result<Vec<SubmoduleStatus>, String> {
+    spawn_blocking(move || git::get_submodule_status(&worktree_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Squash the last `count` commits into one with `message`
+#[tauri::command]
+pub async fn squash_last(
+    worktree_path: String,
+    count: u32,
+    message: String,
+) -> Result<CommitInfo, String> {
+    spawn_blocking(move || git::squash_last(&worktree_path, count, message))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Commit all tracked changes; untracked files are not included (git commit -a)
+#[tauri::command]
+pub async fn commit_all(worktree_path: String, message: String) -> Result<CommitInfo, String> {
+    spawn_blocking(move || git::commit_all(&worktree_path, message))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn uncommit_keep_changes(worktree_path: String) -> Result<WorktreeStatus, String> {
+    spawn_blocking(move || git::uncommit_keep_changes(&worktree_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn restore_file_from_commit(
+    worktree_path: String,
+    commit_sha: String,
+    file_path: String,
+) -> Result<(), String> {
+    spawn_blocking(move || git::restore_file_from_commit(&worktree_path, &commit_sha, &file_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn list_conflicts(worktree_path: String) -> Result<Vec<ConflictEntry>, String> {
+    spawn_blocking(move || git::list_conflicts(&worktree_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_conflict_versions(
+    worktree_path: String,
+    file_path: String,
+) -> Result<ConflictVersions, String> {
+    spawn_blocking(move || git::get_conflict_versions(&worktree_path, &file_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn mark_resolved(
+    worktree_path: String,
+    paths: Vec<String>,
+) -> Result<MarkResolvedResult, String> {
+    spawn_blocking(move || git::mark_resolved(&worktree_path, paths))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn set_assume_unchanged(
+    worktree_path: String,
+    paths: Vec<String>,
+    enabled: bool,
+) -> Result<(), String> {
+    spawn_blocking(move || git::set_assume_unchanged(&worktree_path, paths, enabled))
         .await
         .map_err(|e| e.to_string())?
 }
 
 #[tauri::command]
-pub async fn get_worktree_status(worktree_path: String) -> Result<WorktreeStatus, String> {
-    spawn_blocking(move || git::get_worktree_status_by_path(&worktree_path))
+pub async fn list_assume_unchanged(worktree_path: String) -> Result<Vec<String>, String> {
+    spawn_blocking(move || git::list_assume_unchanged(&worktree_path))
         .await
         .map_err(|e| e.to_string())?
 }
@@ -58,12 +644,37 @@ pub async fn get_worktree_status(worktree_path: String) -> Result<WorktreeStatus
 pub async fn create_worktree(
     repo_path: String,
     options: CreateWorktreeOptions,
-) -> Result<Worktree, String> {
+) -> Result<CreateWorktreeResult, String> {
     spawn_blocking(move || git::create_worktree(&repo_path, options))
         .await
         .map_err(|e| e.to_string())?
 }
 
+#[tauri::command]
+pub async fn create_worktree_from_remote(
+    repo_path: String,
+    remote_branch: String,
+    path: String,
+) -> Result<Worktree, String> {
+    spawn_blocking(move || git::create_worktree_from_remote(&repo_path, &remote_branch, &path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn rename_worktree_and_branch(
+    repo_path: String,
+    worktree_path: String,
+    new_dir: String,
+    new_branch: String,
+) -> Result<Worktree, String> {
+    spawn_blocking(move || {
+        git::rename_worktree_and_branch(&repo_path, &worktree_path, &new_dir, &new_branch)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
 #[tauri::command]
 pub async fn delete_worktree(
     repo_path: String,
@@ -75,6 +686,13 @@ pub async fn delete_worktree(
         .map_err(|e| e.to_string())?
 }
 
+#[tauri::command]
+pub async fn find_missing_worktrees(repo_path: String) -> Result<Vec<String>, String> {
+    spawn_blocking(move || git::find_missing_worktrees(&repo_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
 #[tauri::command]
 pub async fn prune_worktrees(repo_path: String) -> Result<PruneResult, String> {
     spawn_blocking(move || git::prune_worktrees(&repo_path))
@@ -89,6 +707,269 @@ pub async fn list_branches(repo_path: String) -> Result<Vec<BranchInfo>, String>
         .map_err(|e| e.to_string())?
 }
 
+#[tauri::command]
+pub async fn branch_remote_presence(
+    repo_path: String,
+    branch: String,
+) -> Result<Vec<String>, String> {
+    spawn_blocking(move || git::branch_remote_presence(&repo_path, &branch))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn branch_checkout_conflict(
+    repo_path: String,
+    branch: String,
+) -> Result<Option<String>, String> {
+    spawn_blocking(move || git::branch_checkout_conflict(&repo_path, &branch))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn list_all_refs(repo_path: String) -> Result<Vec<RefEntry>, String> {
+    spawn_blocking(move || git::list_all_refs(&repo_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn resolve_tag(repo_path: String, tag: String) -> Result<String, String> {
+    spawn_blocking(move || git::resolve_tag(&repo_path, &tag))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn resolve_commit(worktree_path: String, rev: String) -> Result<ResolveResult, String> {
+    spawn_blocking(move || git::resolve_commit(&worktree_path, &rev))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn commits_ahead_of_default(
+    repo_path: String,
+    worktree_path: String,
+) -> Result<u32, String> {
+    spawn_blocking(move || git::commits_ahead_of_default(&repo_path, &worktree_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_branch_commits(
+    worktree_path: String,
+    base: String,
+    limit: usize,
+    no_merges: bool,
+) -> Result<Vec<CommitInfo>, String> {
+    spawn_blocking(move || git::get_branch_commits(&worktree_path, &base, limit, no_merges))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_all_in_flight_commits(
+    repo_path: String,
+    limit_per_branch: usize,
+) -> Result<Vec<WorktreeCommits>, String> {
+    spawn_blocking(move || git::get_all_in_flight_commits(&repo_path, limit_per_branch))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn merge_base(repo_path: String, refs: Vec<String>) -> Result<Option<String>, String> {
+    spawn_blocking(move || git::merge_base(&repo_path, &refs))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn is_branch_merged(
+    repo_path: String,
+    branch: String,
+    into: String,
+) -> Result<bool, String> {
+    spawn_blocking(move || git::is_branch_merged(&repo_path, &branch, &into))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn can_fast_forward(worktree_path: String, source: String) -> Result<bool, String> {
+    spawn_blocking(move || git::can_fast_forward(&worktree_path, &source))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn merge_fast_forward(worktree_path: String, source: String) -> Result<HeadInfo, String> {
+    spawn_blocking(move || git::merge_fast_forward(&worktree_path, &source))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn branches_containing(
+    repo_path: String,
+    commit_sha: String,
+) -> Result<Vec<String>, String> {
+    spawn_blocking(move || git::branches_containing(&repo_path, &commit_sha))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn compare_branches(
+    repo_path: String,
+    a: String,
+    b: String,
+    limit: usize,
+) -> Result<BranchComparison, String> {
+    spawn_blocking(move || git::compare_branches(&repo_path, &a, &b, limit))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn fetch_pull_request(
+    repo_path: String,
+    remote: String,
+    number: u32,
+    ref_pattern: Option<String>,
+) -> Result<String, String> {
+    spawn_blocking(move || {
+        git::fetch_pull_request(&repo_path, &remote, number, ref_pattern.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn format_patch(
+    worktree_path: String,
+    commit_sha: String,
+    base: Option<String>,
+) -> Result<String, String> {
+    spawn_blocking(move || git::format_patch(&worktree_path, &commit_sha, base.as_deref()))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn stage_lines(
+    worktree_path: String,
+    file_path: String,
+    ranges: Vec<(u32, u32)>,
+) -> Result<(), String> {
+    spawn_blocking(move || git::stage_lines(&worktree_path, &file_path, ranges))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn export_working_patch(
+    worktree_path: String,
+    include_untracked: bool,
+) -> Result<String, String> {
+    spawn_blocking(move || git::export_working_patch(&worktree_path, include_untracked))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn transfer_changes(
+    from_worktree: String,
+    to_worktree: String,
+    include_untracked: bool,
+    discard_source: bool,
+) -> Result<(), String> {
+    spawn_blocking(move || {
+        git::transfer_changes(
+            &from_worktree,
+            &to_worktree,
+            include_untracked,
+            discard_source,
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn apply_mailbox(
+    worktree_path: String,
+    patch_text: String,
+    three_way: bool,
+) -> Result<AmResult, String> {
+    spawn_blocking(move || git::apply_mailbox(&worktree_path, patch_text, three_way))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn am_abort(worktree_path: String) -> Result<(), String> {
+    spawn_blocking(move || git::am_abort(&worktree_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn recent_branches(worktree_path: String, limit: usize) -> Result<Vec<String>, String> {
+    spawn_blocking(move || git::recent_branches(&worktree_path, limit))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_reflog(worktree_path: String, limit: usize) -> Result<Vec<ReflogEntry>, String> {
+    spawn_blocking(move || git::get_reflog(&worktree_path, limit))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_branch_reflog(
+    repo_path: String,
+    branch: String,
+    limit: usize,
+) -> Result<Vec<ReflogEntry>, String> {
+    spawn_blocking(move || git::get_branch_reflog(&repo_path, &branch, limit))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn bisect_start(worktree_path: String) -> Result<BisectState, String> {
+    spawn_blocking(move || git::bisect_start(&worktree_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn bisect_good(worktree_path: String, sha: String) -> Result<BisectState, String> {
+    spawn_blocking(move || git::bisect_good(&worktree_path, &sha))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn bisect_bad(worktree_path: String, sha: String) -> Result<BisectState, String> {
+    spawn_blocking(move || git::bisect_bad(&worktree_path, &sha))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn bisect_reset(worktree_path: String) -> Result<(), String> {
+    spawn_blocking(move || git::bisect_reset(&worktree_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
 #[tauri::command]
 pub async fn open_in_terminal(path: String, terminal: String) -> Result<(), String> {
     use std::process::Command;
@@ -1,27 +1,248 @@
 use crate::types::{
-    BranchInfo, CommitDiff, CommitInfo, CreateWorktreeOptions, DiffHunk, DiffLine, DiffStats,
-    FileDiff, FileStatus, HeadInfo, PruneResult, UpstreamInfo, Worktree, WorkingDiff,
-    WorktreeStatus,
+    AmResult, BisectState, BlameLine, BranchComparison, BranchInfo, ChmodChange, CommitDiff,
+    CommitGraph, CommitInfo, CommitOneline, ConflictEntry, ConflictVersions, CreateWorktreeOptions,
+    CreateWorktreeResult, DiffHunk, DiffLine, DiffScope, DiffStats, DiffTree, FileBytes,
+    FileCountDelta, FileDiff, FileMtime, FileNumstat, FileStatus, GcResult, GraphEdge, GraphRow,
+    HeadInfo, IgnoreResult, IntegrityReport, MarkResolvedResult, PruneResult, PushTarget, RefEntry,
+    RefKind, ReflogEntry, RepoProbe, RepoScale, RepoSummary, ResolveResult, SplitRow,
+    SubmoduleState, SubmoduleStatus, UntrackedMode, UntrackedSummary, UpstreamInfo, WorkingDiff,
+    Worktree, WorktreeCommits, WorktreeStatus,
 };
+use base64::prelude::*;
 use rayon::prelude::*;
-use std::path::PathBuf;
-use std::process::Command;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Give spawned git a clean, deterministic environment
+/// - GIT_DIR / GIT_WORK_TREE from the user's shell would override the -C path
+/// - GIT_PAGER=cat and GIT_TERMINAL_PROMPT=0 stop git from blocking on a pager or credentials
+/// - LC_ALL=C keeps output in English so parsers don't depend on the user's locale;
+///   LANGUAGE is dropped too since gettext can prefer it over LC_ALL for messages
+fn isolate_git_env(cmd: &mut Command) -> &mut Command {
+    cmd.env_remove("GIT_DIR")
+        .env_remove("GIT_WORK_TREE")
+        .env_remove("LANGUAGE")
+        .env("GIT_PAGER", "cat")
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("LC_ALL", "C")
+}
+
+/// Build a git Command for the specified directory with an isolated environment
+fn git_command(path: &str, args: &[&str]) -> Command {
+    let mut cmd = Command::new("git");
+    isolate_git_env(&mut cmd).arg("-C").arg(path).args(args);
+    cmd
+}
+
+/// A failed git invocation, classified so callers can decide whether to retry
+#[derive(Debug, PartialEq)]
+enum GitError {
+    /// Another git process holds index.lock; transient for read operations
+    Locked(String),
+    /// A network hiccup (DNS, dropped connection) that may succeed on retry
+    Network(String),
+    Failed(String),
+}
+
+impl From<GitError> for String {
+    fn from(err: GitError) -> String {
+        match err {
+            GitError::Locked(msg) | GitError::Network(msg) | GitError::Failed(msg) => msg,
+        }
+    }
+}
+
+/// Attempts made by read operations that hit index.lock contention
+const READ_LOCK_ATTEMPTS: u32 = 3;
+/// Delay before the first retry; doubles on each subsequent attempt
+const READ_LOCK_BACKOFF: Duration = Duration::from_millis(50);
 
 /// Run a git command in the specified directory and return stdout as String
 fn run_git(path: &str, args: &[&str]) -> Result<String, String> {
-    let output = Command::new("git")
-        .arg("-C")
-        .arg(path)
-        .args(args)
+    run_git_capture(path, args).map(|(stdout, _)| stdout)
+}
+
+/// Run a read-only git command, retrying briefly if another process holds index.lock
+/// Write operations use run_git directly so real contention isn't masked
+fn run_git_read(path: &str, args: &[&str]) -> Result<String, String> {
+    retry_when_locked(READ_LOCK_ATTEMPTS, READ_LOCK_BACKOFF, || {
+        run_git_classified(path, args).map(|(stdout, _)| stdout)
+    })
+}
+
+/// Attempts made by remote operations that hit a transient network failure
+const NETWORK_ATTEMPTS: u32 = 3;
+/// Delay before the first network retry; doubles on each subsequent attempt
+const NETWORK_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Run a git command that talks to a remote, retrying transient network failures
+/// Terminal errors (authentication, rejected pushes, missing refs) fail immediately
+fn run_git_network(path: &str, args: &[&str]) -> Result<String, String> {
+    retry_when_transient(NETWORK_ATTEMPTS, NETWORK_BACKOFF, || {
+        run_git_classified(path, args).map(|(stdout, _)| stdout)
+    })
+}
+
+/// Call `f` up to `attempts` times while it fails with GitError::Locked
+/// Extracted for testability (tests pass a shim that fails then succeeds)
+fn retry_when_locked<T, F>(attempts: u32, backoff: Duration, f: F) -> Result<T, String>
+where
+    F: FnMut() -> Result<T, GitError>,
+{
+    retry_with_backoff(attempts, backoff, |e| matches!(e, GitError::Locked(_)), f)
+}
+
+/// Call `f` up to `attempts` times while it fails with GitError::Network
+/// Extracted for testability
+fn retry_when_transient<T, F>(attempts: u32, backoff: Duration, f: F) -> Result<T, String>
+where
+    F: FnMut() -> Result<T, GitError>,
+{
+    retry_with_backoff(attempts, backoff, |e| matches!(e, GitError::Network(_)), f)
+}
+
+/// Call `f` up to `attempts` times, sleeping with exponential backoff between attempts,
+/// for as long as it fails with an error `retryable` accepts
+fn retry_with_backoff<T, F, R>(
+    attempts: u32,
+    backoff: Duration,
+    retryable: R,
+    mut f: F,
+) -> Result<T, String>
+where
+    F: FnMut() -> Result<T, GitError>,
+    R: Fn(&GitError) -> bool,
+{
+    let mut delay = backoff;
+    for attempt in 1..=attempts {
+        match f() {
+            Err(ref e) if attempt < attempts && retryable(e) => {
+                std::thread::sleep(delay);
+                delay *= 2;
+            }
+            result => return result.map_err(String::from),
+        }
+    }
+    Err("git was not run (zero attempts)".to_string())
+}
+
+/// Run a git command and return (stdout, stderr) for commands that report on stderr
+fn run_git_capture(path: &str, args: &[&str]) -> Result<(String, String), String> {
+    run_git_classified(path, args).map_err(String::from)
+}
+
+/// Run a git command, classifying failures into GitError
+fn run_git_classified(path: &str, args: &[&str]) -> Result<(String, String), GitError> {
+    let output = git_command(path, args)
         .output()
-        .map_err(|e| format!("Failed to run git {}: {}", args.join(" "), e))?;
+        .map_err(|e| GitError::Failed(format!("Failed to run git {}: {}", args.join(" "), e)))?;
+    classify_output(args, output)
+}
+
+/// Run a read-only git command like run_git_read, killing it once `cancel` is set
+/// Returns None when cancelled; index.lock contention is retried as usual
+fn run_git_read_cancellable(
+    path: &str,
+    args: &[&str],
+    cancel: &AtomicBool,
+) -> Result<Option<String>, String> {
+    retry_when_locked(READ_LOCK_ATTEMPTS, READ_LOCK_BACKOFF, || {
+        let Some(output) =
+            output_cancellable(&mut git_command(path, args), cancel).map_err(|e| {
+                GitError::Failed(format!("Failed to run git {}: {}", args.join(" "), e))
+            })?
+        else {
+            return Ok(None);
+        };
+        classify_output(args, output).map(|(stdout, _)| Some(stdout))
+    })
+}
 
+/// Turn a finished git process into (stdout, stderr), classifying failures into GitError
+fn classify_output(args: &[&str], output: Output) -> Result<(String, String), GitError> {
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
     if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("git {} failed: {}", args.join(" "), stderr));
+        let message = format!("git {} failed: {}", args.join(" "), stderr);
+        return Err(if is_index_lock_error(&stderr) {
+            GitError::Locked(message)
+        } else if is_transient_network_error(&stderr) {
+            GitError::Network(message)
+        } else {
+            GitError::Failed(message)
+        });
     }
 
-    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    Ok((String::from_utf8_lossy(&output.stdout).to_string(), stderr))
+}
+
+/// Whether git failed because another process holds the index lock, e.g.
+/// "fatal: Unable to create '/repo/.git/index.lock': File exists."
+/// Extracted for testability
+fn is_index_lock_error(stderr: &str) -> bool {
+    stderr.contains("index.lock")
+}
+
+/// stderr fragments of network failures worth retrying (output is in the C locale)
+const TRANSIENT_NETWORK_PATTERNS: &[&str] = &[
+    "Could not resolve host",
+    "Temporary failure in name resolution",
+    "Connection reset",
+    "Connection timed out",
+    "Operation timed out",
+    "Failed to connect to",
+    "The remote end hung up unexpectedly",
+    "early EOF",
+    "RPC failed",
+];
+
+/// stderr fragments that mean retrying can't help, even alongside a transient pattern
+/// (e.g. ssh auth failures also report "the remote end hung up")
+const TERMINAL_NETWORK_PATTERNS: &[&str] = &[
+    "Authentication failed",
+    "Permission denied",
+    "could not read Username",
+    "Repository not found",
+    "[rejected]",
+    "non-fast-forward",
+    "couldn't find remote ref",
+];
+
+/// Whether a remote operation failed for a reason that may go away on retry
+/// Extracted for testability
+fn is_transient_network_error(stderr: &str) -> bool {
+    TRANSIENT_NETWORK_PATTERNS
+        .iter()
+        .any(|p| stderr.contains(p))
+        && !TERMINAL_NETWORK_PATTERNS.iter().any(|p| stderr.contains(p))
+}
+
+/// Run a git command with `input` piped to stdin and return the raw process output
+/// A non-zero exit is not treated as an error so callers can inspect partial results
+fn run_git_with_stdin(path: &str, args: &[&str], input: &str) -> Result<Output, String> {
+    let mut child = git_command(path, args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run git {}: {}", args.join(" "), e))?;
+
+    // Write stdin from a separate thread so a full stdout pipe can't deadlock us
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| format!("Failed to open stdin for git {}", args.join(" ")))?;
+    let input = input.to_string();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run git {}: {}", args.join(" "), e))?;
+    let _ = writer.join();
+
+    Ok(output)
 }
 
 /// Get upstream tracking info for the current branch
@@ -61,953 +282,10127 @@ fn parse_ahead_behind(output: &str) -> (u32, u32) {
     }
 }
 
-pub fn get_all_worktrees(repo_path: &str) -> Result<Vec<Worktree>, String> {
-    // Use git worktree list --porcelain to get all worktrees
-    let output = run_git(repo_path, &["worktree", "list", "--porcelain"])?;
+/// Check whether a path is inside a git repository
+/// A directory that simply isn't a repo yields is_repo = false rather than an error
+pub fn is_git_repository(path: &str) -> Result<RepoProbe, String> {
+    if !Path::new(path).is_dir() {
+        return Err(format!("Not a directory: {}", path));
+    }
 
-    // Parse porcelain output to extract paths
-    // Format:
-    // worktree /path/to/main
-    // HEAD abc1234...
-    // branch refs/heads/main
-    //
-    // worktree /path/to/linked
-    // HEAD def5678...
-    // branch refs/heads/feature
+    // "true" inside a working tree, "false" inside a .git dir or bare repo, error otherwise
+    let inside_work_tree = match run_git(path, &["rev-parse", "--is-inside-work-tree"]) {
+        Ok(output) => output.trim() == "true",
+        Err(_) => {
+            return Ok(RepoProbe {
+                is_repo: false,
+                toplevel: None,
+                is_worktree: false,
+            })
+        }
+    };
 
-    let mut worktree_paths: Vec<PathBuf> = Vec::new();
+    let toplevel = if inside_work_tree {
+        run_git(path, &["rev-parse", "--show-toplevel"])
+            .ok()
+            .map(|s| s.trim().to_string())
+    } else {
+        None
+    };
 
-    for line in output.lines() {
-        if let Some(path) = line.strip_prefix("worktree ") {
-            worktree_paths.push(PathBuf::from(path));
-        }
+    Ok(RepoProbe {
+        is_repo: true,
+        toplevel,
+        is_worktree: inside_work_tree,
+    })
+}
+
+/// Create a new repository at `path` (created if missing) and probe it
+/// A non-bare repository's path becomes its main worktree
+pub fn init_repository(
+    path: &str,
+    initial_branch: Option<&str>,
+    bare: bool,
+) -> Result<RepoProbe, String> {
+    let args = build_init_args(path, initial_branch, bare);
+    let arg_refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    // The target may not exist yet, so run from the current directory
+    run_git(".", &arg_refs)?;
+
+    is_git_repository(path)
+}
+
+/// Build the argument list for "git init"
+/// Extracted for testability
+fn build_init_args(path: &str, initial_branch: Option<&str>, bare: bool) -> Vec<String> {
+    let mut args = vec!["init".to_string(), "-q".to_string()];
+    if let Some(branch) = initial_branch {
+        args.push(format!("--initial-branch={}", branch));
     }
+    if bare {
+        args.push("--bare".to_string());
+    }
+    args.push("--".to_string());
+    args.push(path.to_string());
+    args
+}
 
-    // Process all worktrees in parallel using rayon
-    let mut worktrees: Vec<Worktree> = worktree_paths
-        .par_iter()
-        .enumerate()
-        .filter_map(|(idx, path)| {
-            let is_main = idx == 0; // First worktree is the main one
-            build_worktree_info(path, is_main).ok()
-        })
-        .collect();
+/// Clone `url` into `dest`, reporting (phase, percent) progress from git's stderr
+/// Setting `cancel` kills the clone (even while git is silent, e.g. a stalled
+/// connection) and removes the partially cloned directory
+pub fn clone_repository<F>(
+    url: &str,
+    dest: &str,
+    bare: bool,
+    cancel: &AtomicBool,
+    mut on_progress: F,
+) -> Result<RepoProbe, String>
+where
+    F: FnMut(String, u32),
+{
+    let dest_existed = Path::new(dest).exists();
+    let args = build_clone_args(url, dest, bare);
+    let arg_refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+
+    // The destination doesn't exist yet, so run from the current directory
+    let mut child = git_command(".", &arg_refs)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run git clone: {}", e))?;
+
+    // Read stderr on its own thread so the cancel flag is still polled while git is quiet
+    let (progress_tx, progress_rx) = std::sync::mpsc::channel();
+    let stderr = child.stderr.take();
+    let reader = std::thread::spawn(move || {
+        let mut stderr_text = String::new();
+        let Some(stderr) = stderr else {
+            return stderr_text;
+        };
+        // Progress lines are redrawn with '\r', so split on both '\r' and '\n'
+        let mut reader = BufReader::new(stderr);
+        let mut segment = Vec::new();
+        loop {
+            segment.clear();
+            match reader.read_until(b'\r', &mut segment) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            for line in String::from_utf8_lossy(&segment).split(['\r', '\n']) {
+                if let Some(progress) = parse_progress_line(line) {
+                    let _ = progress_tx.send(progress);
+                } else if !line.trim().is_empty() {
+                    stderr_text.push_str(line);
+                    stderr_text.push('\n');
+                }
+            }
+        }
+        stderr_text
+    });
 
-    // Sort by last commit timestamp (most recent first)
-    worktrees.sort_by(|a, b| b.last_commit_timestamp.cmp(&a.last_commit_timestamp));
+    let status = wait_cancellable(&mut child, cancel, || {
+        if let Ok((phase, percent)) = progress_rx.recv_timeout(CANCEL_POLL_INTERVAL) {
+            on_progress(phase, percent);
+        }
+    })
+    .map_err(|e| format!("Failed to run git clone: {}", e))?;
+    let stderr_text = reader.join().unwrap_or_default();
 
-    Ok(worktrees)
+    let Some(status) = status else {
+        if !dest_existed {
+            let _ = std::fs::remove_dir_all(dest);
+        }
+        return Err("Clone cancelled".to_string());
+    };
+    for (phase, percent) in progress_rx.try_iter() {
+        on_progress(phase, percent);
+    }
+    if !status.success() {
+        return Err(format!("git clone failed: {}", stderr_text.trim()));
+    }
+
+    is_git_repository(dest)
 }
 
-/// Get status for a single worktree path (for lazy loading)
-pub fn get_worktree_status_by_path(worktree_path: &str) -> Result<WorktreeStatus, String> {
-    get_worktree_status(worktree_path)
+/// Build the argument list for "git clone"
+/// Extracted for testability
+fn build_clone_args(url: &str, dest: &str, bare: bool) -> Vec<String> {
+    let mut args = vec!["clone".to_string(), "--progress".to_string()];
+    if bare {
+        args.push("--bare".to_string());
+    }
+    args.push("--".to_string());
+    args.push(url.to_string());
+    args.push(dest.to_string());
+    args
 }
 
-fn build_worktree_info(path: &PathBuf, is_main: bool) -> Result<Worktree, String> {
-    let path_str = path.to_string_lossy();
+/// Run "git gc" to repack and prune the object store, reporting the object directory
+/// size before and after. git only prints gc progress to a terminal, so `on_progress`
+/// receives just a "Collecting garbage" phase at 0% (start) and 100% (success)
+/// Setting `cancel` kills gc; git leaves the repository consistent when interrupted
+pub fn run_gc<F>(
+    repo_path: &str,
+    aggressive: bool,
+    cancel: &AtomicBool,
+    mut on_progress: F,
+) -> Result<GcResult, String>
+where
+    F: FnMut(String, u32),
+{
+    let (_, common_dir) = get_git_dirs(repo_path)?;
+    let objects_dir = common_dir.join("objects");
+    let before_size = dir_size(&objects_dir);
+
+    let args = build_gc_args(aggressive);
+    on_progress(GC_PHASE.to_string(), 0);
+    let output = output_cancellable(&mut git_command(repo_path, &args), cancel)
+        .map_err(|e| format!("Failed to run git gc: {}", e))?
+        .ok_or_else(|| "Garbage collection cancelled".to_string())?;
+
+    let stderr_text = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        return Err(format!("git gc failed: {}", stderr_text.trim()));
+    }
+    on_progress(GC_PHASE.to_string(), 100);
 
-    // Get short SHA
-    let short_sha = run_git(&path_str, &["rev-parse", "--short", "HEAD"])?
-        .trim()
-        .to_string();
+    Ok(GcResult {
+        before_size,
+        after_size: dir_size(&objects_dir),
+        message: stderr_text.trim().to_string(),
+    })
+}
 
-    // Get branch name (returns "HEAD" if detached)
-    let branch_output = run_git(&path_str, &["rev-parse", "--abbrev-ref", "HEAD"])?;
-    let branch_name = branch_output.trim();
-    let branch = if branch_name == "HEAD" {
-        None // Detached HEAD
-    } else {
-        Some(branch_name.to_string())
+/// Phase label reported around a gc run
+const GC_PHASE: &str = "Collecting garbage";
+
+/// Build the argument list for "git gc"
+/// Extracted for testability
+fn build_gc_args(aggressive: bool) -> Vec<&'static str> {
+    let mut args = vec!["gc"];
+    if aggressive {
+        args.push("--aggressive");
+    }
+    args
+}
+
+/// Total size in bytes of the files under `dir`; unreadable entries count as 0
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
     };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| match entry.file_type() {
+            Ok(kind) if kind.is_dir() => dir_size(&entry.path()),
+            Ok(_) => entry.metadata().map(|m| m.len()).unwrap_or(0),
+            Err(_) => 0,
+        })
+        .sum()
+}
 
-    // Get commit message summary
-    let commit_message = run_git(&path_str, &["log", "-1", "--format=%s"])?
-        .trim()
-        .to_string();
+/// Parse a git progress line such as "Receiving objects:  42% (420/1000), 1.2 MiB"
+/// into (phase, percent); "remote: " prefixes are dropped
+/// Extracted for testability
+fn parse_progress_line(line: &str) -> Option<(String, u32)> {
+    let line = line.trim();
+    let line = line.strip_prefix("remote: ").unwrap_or(line);
+    let (phase, rest) = line.split_once(": ")?;
+    let (percent, _) = rest.trim_start().split_once('%')?;
+    Some((phase.to_string(), percent.parse().ok()?))
+}
 
-    // Get commit timestamp
-    let timestamp_str = run_git(&path_str, &["log", "-1", "--format=%ct"])?;
-    let timestamp = timestamp_str.trim().parse::<i64>().unwrap_or(0);
+/// Worktree count above which a full scan is considered large
+const LARGE_WORKTREE_COUNT: u32 = 50;
+/// Commit count above which a full scan is considered large
+const LARGE_COMMIT_COUNT: u64 = 100_000;
 
-    // Defer status scanning - return None initially for faster load
-    // Frontend will fetch status lazily
-    let status = None;
+/// Cheaply estimate repo size so the UI can warn before a slow get_all_worktrees
+pub fn probe_repo_scale(repo_path: &str) -> Result<RepoScale, String> {
+    let output = run_git(repo_path, &["worktree", "list", "--porcelain"])?;
+    let worktree_count = count_porcelain_worktrees(&output);
 
-    // Get upstream tracking info if we have a branch (not detached)
-    let upstream = if branch.is_some() {
-        get_upstream_info(&path_str)
-    } else {
-        None
-    };
+    // Unborn HEAD has no commits to count
+    let approx_commit_count = run_git(repo_path, &["rev-list", "--count", "HEAD"])
+        .ok()
+        .and_then(|s| s.trim().parse().ok());
 
-    Ok(Worktree {
-        path: path.clone(),
-        name: path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown")
-            .to_string(),
-        is_main,
-        head: HeadInfo {
-            branch,
-            commit_sha: short_sha,
-            commit_message,
-            upstream,
-        },
-        status,
-        last_commit_timestamp: timestamp,
+    Ok(RepoScale {
+        worktree_count,
+        approx_commit_count,
+        large: is_large_repo(worktree_count, approx_commit_count),
     })
 }
 
-fn get_worktree_status(worktree_path: &str) -> Result<WorktreeStatus, String> {
-    let output = run_git(worktree_path, &["status", "--porcelain"])?;
-    Ok(parse_status_porcelain(&output))
+/// Count "worktree <path>" records in "git worktree list --porcelain" output
+/// Extracted for testability
+fn count_porcelain_worktrees(output: &str) -> u32 {
+    output
+        .lines()
+        .filter(|line| line.starts_with("worktree "))
+        .count() as u32
 }
 
-// Get commit history for a worktree
-pub fn get_commit_history(
-    worktree_path: &str,
-    limit: usize,
-    offset: usize,
-) -> Result<Vec<CommitInfo>, String> {
-    // Use record separator (%x1e) between commits and unit separator (%x1f) between fields
-    // Format: hash, short_hash, author_name, author_email, timestamp, summary, body
-    let format = "%H%x1f%h%x1f%an%x1f%ae%x1f%ct%x1f%s%x1f%B%x1e";
+/// Heuristic for whether a repo is big enough to warrant a "this may take a moment" hint
+/// Extracted for testability
+fn is_large_repo(worktree_count: u32, approx_commit_count: Option<u64>) -> bool {
+    worktree_count > LARGE_WORKTREE_COUNT
+        || approx_commit_count.is_some_and(|count| count > LARGE_COMMIT_COUNT)
+}
 
-    let output = run_git(
-        worktree_path,
-        &[
-            "log",
-            &format!("--format={}", format),
-            &format!("--skip={}", offset),
-            &format!("-n{}", limit),
-        ],
-    )?;
+/// Check the object store for corruption with "git fsck"
+/// fsck reads every object, so this is slow on large repos and only runs when asked
+pub fn check_integrity(repo_path: &str) -> Result<IntegrityReport, String> {
+    let args = ["fsck", "--no-progress"];
+    let output = git_command(repo_path, &args)
+        .output()
+        .map_err(|e| format!("Failed to run git {}: {}", args.join(" "), e))?;
 
-    Ok(parse_commit_log(&output))
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    // 128 means fsck itself couldn't run (e.g. not a repository), not that it found problems
+    if output.status.code() == Some(128) && stderr.contains("fatal:") {
+        return Err(format!("git {} failed: {}", args.join(" "), stderr));
+    }
+
+    let combined = format!("{}\n{}", String::from_utf8_lossy(&output.stdout), stderr);
+    Ok(parse_fsck_output(&combined))
 }
 
-// Get diff for a specific commit
-pub fn get_commit_diff(worktree_path: &str, commit_sha: &str) -> Result<CommitDiff, String> {
-    // Get commit info using git log
-    let format = "%H%x1f%h%x1f%an%x1f%ae%x1f%ct%x1f%s%x1f%B";
-    let commit_output = run_git(
-        worktree_path,
-        &["log", "-1", &format!("--format={}", format), commit_sha],
-    )?;
+/// Split "git fsck" output into harmless dangling objects and real problems
+/// Dangling entries keep "<type> <sha>"; indented lines continue the previous problem
+/// (e.g. "broken link from tree <sha>" / "to blob <sha>")
+/// Extracted for testability
+fn parse_fsck_output(output: &str) -> IntegrityReport {
+    let mut dangling = Vec::new();
+    let mut errors: Vec<String> = Vec::new();
 
-    let fields: Vec<&str> = commit_output.trim().split('\x1f').collect();
-    if fields.len() < 6 {
-        return Err(format!("Failed to parse commit info for {}", commit_sha));
+    for line in output.lines() {
+        if line.trim().is_empty() || line.starts_with("Checking ") || line.starts_with("notice:") {
+            continue;
+        }
+        if let Some(object) = line.strip_prefix("dangling ") {
+            dangling.push(object.trim().to_string());
+        } else if let Some(last) = errors
+            .last_mut()
+            .filter(|_| line.starts_with(char::is_whitespace))
+        {
+            last.push(' ');
+            last.push_str(line.trim());
+        } else {
+            errors.push(line.trim().to_string());
+        }
     }
 
-    let commit_info = CommitInfo {
-        hash: fields[0].to_string(),
-        short_hash: fields[1].to_string(),
-        author_name: fields[2].to_string(),
-        author_email: fields[3].to_string(),
-        timestamp: fields[4].parse::<i64>().unwrap_or(0),
-        summary: fields[5].to_string(),
-        message: fields.get(6).unwrap_or(&"").trim().to_string(),
-    };
-
-    // Get diff using git show
-    let diff_output = run_git(
-        worktree_path,
-        &["show", commit_sha, "--format=", "-U3", "-M"],
-    )?;
+    IntegrityReport {
+        ok: errors.is_empty(),
+        dangling,
+        errors,
+    }
+}
 
-    let files = parse_git_diff_output(&diff_output);
+/// List all worktrees, building their info in parallel
+/// `max_parallelism` caps concurrent git invocations (useful on slow or network storage);
+/// None uses rayon's global pool
+pub fn get_all_worktrees(
+    repo_path: &str,
+    max_parallelism: Option<usize>,
+) -> Result<Vec<Worktree>, String> {
+    // Use git worktree list --porcelain to get all worktrees
+    let output = run_git(repo_path, &["worktree", "list", "--porcelain"])?;
 
-    // Calculate stats
-    let mut total_insertions = 0u32;
-    let mut total_deletions = 0u32;
+    let worktree_paths = parse_worktree_list_paths(&output);
 
-    for file in &files {
-        for hunk in &file.hunks {
-            for line in &hunk.lines {
-                match line.kind {
-                    '+' => total_insertions += 1,
-                    '-' => total_deletions += 1,
-                    _ => {}
-                }
-            }
-        }
-    }
+    // Process all worktrees in parallel using rayon
+    let mut worktrees: Vec<Worktree> = with_parallelism(max_parallelism, || {
+        worktree_paths
+            .par_iter()
+            .filter_map(|path| {
+                // Porcelain order isn't a reliable signal; only the main worktree's git dir
+                // is the common dir itself (linked ones live under <common>/worktrees/)
+                let is_main = get_git_dirs(&path.to_string_lossy())
+                    .map(|(git_dir, common_dir)| is_main_worktree(&git_dir, &common_dir))
+                    .unwrap_or(false);
+                build_worktree_info(path, is_main).ok()
+            })
+            .collect()
+    })?;
 
-    let files_changed = files.len() as u32;
+    // Sort by last commit timestamp (most recent first)
+    worktrees.sort_by(|a, b| b.last_commit_timestamp.cmp(&a.last_commit_timestamp));
 
-    Ok(CommitDiff {
-        commit: commit_info,
-        files,
-        stats: DiffStats {
-            files_changed,
-            insertions: total_insertions,
-            deletions: total_deletions,
-        },
-    })
+    Ok(worktrees)
 }
 
-// Get uncommitted working directory changes using git CLI
-pub fn get_working_diff(worktree_path: &str) -> Result<WorkingDiff, String> {
-    // Get staged changes: git diff --cached
-    let staged_diff_text = run_git(worktree_path, &["diff", "--cached", "-U3"])?;
-    let staged_files = parse_git_diff_output(&staged_diff_text);
-
-    // Get unstaged changes: git diff
-    let unstaged_diff_text = run_git(worktree_path, &["diff", "-U3"])?;
-    let mut unstaged_files = parse_git_diff_output(&unstaged_diff_text);
-
-    // Get untracked files: git ls-files --others --exclude-standard
-    let untracked_text = run_git(worktree_path, &["ls-files", "--others", "--exclude-standard"])?;
-    for line in untracked_text.lines() {
-        if !line.is_empty() {
-            unstaged_files.push(FileDiff {
-                path: line.to_string(),
-                status: FileStatus::Added,
-                old_path: None,
-                hunks: Vec::new(), // Untracked files don't have hunks
-                binary: false,
-            });
-        }
-    }
+/// Parse worktree paths from "git worktree list --porcelain" output
+/// Format:
+/// worktree /path/to/main
+/// HEAD abc1234...
+/// branch refs/heads/main
+///
+/// worktree /path/to/linked
+/// HEAD def5678...
+/// branch refs/heads/feature
+/// Extracted for testability
+fn parse_worktree_list_paths(output: &str) -> Vec<PathBuf> {
+    output
+        .lines()
+        .filter_map(|line| line.strip_prefix("worktree "))
+        .map(PathBuf::from)
+        .collect()
+}
 
-    // Calculate total stats
-    let mut total_insertions = 0u32;
-    let mut total_deletions = 0u32;
+/// A worktree is the main one when its git dir is the common dir
+/// (a linked worktree's git dir is <common>/worktrees/<name>)
+fn is_main_worktree(git_dir: &Path, common_dir: &Path) -> bool {
+    git_dir == common_dir
+}
 
-    for file in staged_files.iter().chain(unstaged_files.iter()) {
-        for hunk in &file.hunks {
-            for line in &hunk.lines {
-                match line.kind {
-                    '+' => total_insertions += 1,
-                    '-' => total_deletions += 1,
-                    _ => {}
-                }
-            }
-        }
-    }
+/// List worktrees by when they were last worked in (most recent first), capped at `limit`
+/// Activity is the newest HEAD reflog entry in the worktree's gitdir, falling back to
+/// the directory mtime, unlike get_all_worktrees which sorts by last commit timestamp
+pub fn get_recent_worktrees(repo_path: &str, limit: usize) -> Result<Vec<Worktree>, String> {
+    let with_activity = get_all_worktrees(repo_path, None)?
+        .into_iter()
+        .map(|wt| {
+            let activity = last_activity_timestamp(&wt.path);
+            (wt, activity)
+        })
+        .collect();
 
-    let files_changed = (staged_files.len() + unstaged_files.len()) as u32;
+    Ok(order_by_activity(with_activity, limit))
+}
 
-    Ok(WorkingDiff {
-        staged_files,
-        unstaged_files,
-        stats: DiffStats {
-            files_changed,
-            insertions: total_insertions,
-            deletions: total_deletions,
-        },
+/// When a worktree was last used: its newest HEAD reflog entry, else the directory mtime
+fn last_activity_timestamp(worktree_path: &Path) -> Option<i64> {
+    let reflog_time = get_git_dirs(&worktree_path.to_string_lossy())
+        .ok()
+        .and_then(|(git_dir, _)| std::fs::read_to_string(git_dir.join("logs").join("HEAD")).ok())
+        .and_then(|content| parse_last_reflog_timestamp(&content));
+
+    reflog_time.or_else(|| {
+        let modified = std::fs::metadata(worktree_path).ok()?.modified().ok()?;
+        let secs = modified
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()?
+            .as_secs();
+        Some(secs as i64)
     })
 }
 
-/// Parse git diff output into Vec<FileDiff>
-fn parse_git_diff_output(diff_text: &str) -> Vec<FileDiff> {
-    let mut files: Vec<FileDiff> = Vec::new();
-    let mut current_file: Option<FileDiff> = None;
-    let mut current_hunk: Option<DiffHunk> = None;
+/// Timestamp of the last entry in a raw reflog file
+/// Each line is "<old> <new> <name> <email> <unix time> <tz>\t<message>"
+/// Extracted for testability
+fn parse_last_reflog_timestamp(content: &str) -> Option<i64> {
+    let line = content.lines().rev().find(|line| !line.is_empty())?;
+    let (identity, _message) = line.split_once('\t').unwrap_or((line, ""));
+    let mut fields = identity.rsplit(' ');
+    let _tz = fields.next()?;
+    fields.next()?.parse().ok()
+}
 
-    for line in diff_text.lines() {
-        // New file header: diff --git a/path b/path
-        if line.starts_with("diff --git ") {
-            // Save previous file
-            if let Some(mut file) = current_file.take() {
-                if let Some(hunk) = current_hunk.take() {
-                    file.hunks.push(hunk);
-                }
-                files.push(file);
-            }
+/// Sort items by activity timestamp (newest first, unknown last) and keep `limit`
+/// Extracted for testability
+fn order_by_activity<T>(mut items: Vec<(T, Option<i64>)>, limit: usize) -> Vec<T> {
+    items.sort_by_key(|(_, activity)| std::cmp::Reverse(*activity));
+    items
+        .into_iter()
+        .take(limit)
+        .map(|(item, _)| item)
+        .collect()
+}
 
-            // Extract path from "diff --git a/path b/path"
-            let parts: Vec<&str> = line.split(" b/").collect();
-            let path = if parts.len() >= 2 {
-                parts[1].to_string()
-            } else {
-                // Fallback: extract from a/ part
-                line.split(" a/")
-                    .nth(1)
-                    .and_then(|s| s.split(' ').next())
-                    .unwrap_or("")
-                    .to_string()
-            };
+/// List worktrees for a "needs attention" view: conflicted first, then dirty, then clean
+/// Statuses are fetched eagerly (in parallel) rather than lazy-loaded, since the order
+/// depends on them; ties are broken by last commit timestamp (most recent first)
+pub fn list_worktrees_by_attention(repo_path: &str) -> Result<Vec<Worktree>, String> {
+    let mut worktrees = get_worktrees_with_status(repo_path)?;
+    sort_by_attention(&mut worktrees);
+    Ok(worktrees)
+}
 
-            current_file = Some(FileDiff {
-                path,
-                status: FileStatus::Modified, // Will be updated below
-                old_path: None,
-                hunks: Vec::new(),
-                binary: false,
-            });
-            continue;
-        }
+/// Aggregate status across all worktrees for an app-level summary badge
+pub fn get_repo_summary(repo_path: &str) -> Result<RepoSummary, String> {
+    Ok(summarize_worktrees(&get_worktrees_with_status(repo_path)?))
+}
 
-        // Check for binary file
-        if line.starts_with("Binary files") {
-            if let Some(ref mut file) = current_file {
-                file.binary = true;
-            }
-            continue;
-        }
+/// List worktrees with their statuses filled in, scanning them in parallel
+fn get_worktrees_with_status(repo_path: &str) -> Result<Vec<Worktree>, String> {
+    let mut worktrees = get_all_worktrees(repo_path, None)?;
+    worktrees.par_iter_mut().for_each(|wt| {
+        wt.status =
+            get_worktree_status(&wt.path.to_string_lossy(), UntrackedMode::default(), None).ok();
+    });
+    Ok(worktrees)
+}
 
-        // New file indicator
-        if line.starts_with("new file mode") {
-            if let Some(ref mut file) = current_file {
-                file.status = FileStatus::Added;
+/// Count dirty, conflicted, ahead, and behind worktrees
+/// A worktree whose status couldn't be read counts toward the total only
+/// Extracted for testability
+fn summarize_worktrees(worktrees: &[Worktree]) -> RepoSummary {
+    let mut summary = RepoSummary {
+        total_worktrees: worktrees.len() as u32,
+        dirty: 0,
+        conflicted: 0,
+        ahead: 0,
+        behind: 0,
+    };
+
+    for wt in worktrees {
+        if let Some(status) = &wt.status {
+            if !status.is_clean {
+                summary.dirty += 1;
+            }
+            if status.conflicted > 0 {
+                summary.conflicted += 1;
             }
-            continue;
         }
-
-        // Deleted file indicator
-        if line.starts_with("deleted file mode") {
-            if let Some(ref mut file) = current_file {
-                file.status = FileStatus::Deleted;
+        if let Some(upstream) = &wt.head.upstream {
+            if upstream.ahead > 0 {
+                summary.ahead += 1;
+            }
+            if upstream.behind > 0 {
+                summary.behind += 1;
             }
-            continue;
         }
+    }
 
-        // Rename detection: rename from / rename to
-        if line.starts_with("rename from ") {
-            if let Some(ref mut file) = current_file {
-                file.old_path = Some(line.trim_start_matches("rename from ").to_string());
-                file.status = FileStatus::Renamed;
-            }
-            continue;
+    summary
+}
+
+/// Sort worktrees by attention priority, then by last commit timestamp (newest first)
+/// Extracted for testability
+fn sort_by_attention(worktrees: &mut [Worktree]) {
+    worktrees.sort_by_key(|wt| {
+        (
+            attention_priority(wt.status.as_ref()),
+            std::cmp::Reverse(wt.last_commit_timestamp),
+        )
+    });
+}
+
+/// 0 = conflicted, 1 = dirty (staged, modified, or untracked), 2 = clean, 3 = status unknown
+fn attention_priority(status: Option<&WorktreeStatus>) -> u8 {
+    match status {
+        Some(s) if s.conflicted > 0 => 0,
+        Some(s) if !s.is_clean || s.staged + s.modified + s.untracked > 0 => 1,
+        Some(_) => 2,
+        None => 3,
+    }
+}
+
+/// Run `f` on rayon's global pool, or on a dedicated pool limited to `max_parallelism` threads
+fn with_parallelism<R, F>(max_parallelism: Option<usize>, f: F) -> Result<R, String>
+where
+    R: Send,
+    F: FnOnce() -> R + Send,
+{
+    match max_parallelism {
+        Some(threads) if threads > 0 => {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .map_err(|e| format!("Failed to build thread pool: {}", e))?;
+            Ok(pool.install(f))
         }
+        _ => Ok(f()),
+    }
+}
 
-        // Hunk header: @@ -old_start,old_lines +new_start,new_lines @@
-        if line.starts_with("@@ ") {
-            // Save previous hunk
-            if let Some(ref mut file) = current_file {
-                if let Some(hunk) = current_hunk.take() {
-                    file.hunks.push(hunk);
-                }
-            }
+/// Get status for a single worktree path (for lazy loading)
+/// Setting `cancel` kills the in-flight scan
+pub fn get_worktree_status_by_path(
+    worktree_path: &str,
+    untracked_mode: UntrackedMode,
+    cancel: Option<&AtomicBool>,
+) -> Result<WorktreeStatus, String> {
+    get_worktree_status(worktree_path, untracked_mode, cancel)
+}
 
-            // Parse hunk header
-            if let Some((old_start, old_lines, new_start, new_lines)) = parse_hunk_header(line) {
-                current_hunk = Some(DiffHunk {
-                    old_start,
-                    old_lines,
-                    new_start,
-                    new_lines,
-                    header: line.to_string(),
-                    lines: Vec::new(),
-                });
-            }
-            continue;
+/// Summarize untracked files without enumerating large untracked directories
+/// Directories with no tracked files are collapsed to one "dir/" entry by git
+pub fn untracked_summary(worktree_path: &str) -> Result<UntrackedSummary, String> {
+    let output = run_git_read(
+        worktree_path,
+        &["status", "--porcelain", "-z", "--untracked-files=normal"],
+    )?;
+    Ok(parse_untracked_summary(&output))
+}
+
+/// Collect "?? <path>" entries from NUL-separated porcelain status output
+/// `approximate` is set when any entry is a collapsed directory
+/// Extracted for testability
+fn parse_untracked_summary(output: &str) -> UntrackedSummary {
+    let top_level_entries: Vec<String> = output
+        .split('\0')
+        .filter_map(|entry| entry.strip_prefix("?? "))
+        .map(|path| path.to_string())
+        .collect();
+    let approximate = top_level_entries.iter().any(|path| path.ends_with('/'));
+
+    UntrackedSummary {
+        top_level_entries,
+        approximate,
+    }
+}
+
+/// Cap on files stat'ed by get_last_modified_file so huge trees stay responsive
+const LAST_MODIFIED_SCAN_LIMIT: usize = 20_000;
+
+/// The most recently modified tracked file in a worktree, for "resume work"
+/// Approximate: only the first LAST_MODIFIED_SCAN_LIMIT files in index order are stat'ed,
+/// and files deleted from the working tree are skipped
+pub fn get_last_modified_file(worktree_path: &str) -> Result<Option<FileMtime>, String> {
+    let output = run_git_read(worktree_path, &["ls-files", "-z"])?;
+    let root = Path::new(worktree_path);
+
+    let with_mtimes = output
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .take(LAST_MODIFIED_SCAN_LIMIT)
+        .filter_map(|path| {
+            let modified = std::fs::metadata(root.join(path)).ok()?.modified().ok()?;
+            let secs = modified
+                .duration_since(std::time::UNIX_EPOCH)
+                .ok()?
+                .as_secs();
+            Some((path.to_string(), secs as i64))
+        });
+
+    Ok(newest_file(with_mtimes))
+}
+
+/// Pick the entry with the greatest mtime; ties go to the first seen
+/// Extracted for testability
+fn newest_file(files: impl Iterator<Item = (String, i64)>) -> Option<FileMtime> {
+    files
+        .fold(
+            None,
+            |best: Option<(String, i64)>, (path, mtime)| match best {
+                Some((_, best_mtime)) if best_mtime >= mtime => best,
+                _ => Some((path, mtime)),
+            },
+        )
+        .map(|(path, mtime)| FileMtime { path, mtime })
+}
+
+/// Check which paths .gitignore (or info/exclude, core.excludesFile) would ignore
+/// Results are returned in the same order as `paths`
+pub fn check_ignore(worktree_path: &str, paths: Vec<String>) -> Result<Vec<IgnoreResult>, String> {
+    if paths.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // -n reports non-matching paths too, so every input gets exactly one output line
+    let args = ["check-ignore", "-v", "-n", "--stdin"];
+    let input: String = paths.iter().map(|p| format!("{}\n", p)).collect();
+    let output = run_git_with_stdin(worktree_path, &args, &input)?;
+
+    // Exit 1 just means nothing was ignored; 128 is a real failure
+    if output.status.code() == Some(128) {
+        return Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let mut results = parse_check_ignore_output(&String::from_utf8_lossy(&output.stdout));
+    // git C-quotes unusual paths in its output; report the caller's spelling instead
+    if results.len() == paths.len() {
+        for (result, path) in results.iter_mut().zip(paths) {
+            result.path = path;
         }
+    }
+    Ok(results)
+}
 
-        // Diff lines: +, -, or space (context)
-        if let Some(ref mut hunk) = current_hunk {
-            let first_char = line.chars().next();
-            match first_char {
-                Some('+') => {
-                    hunk.lines.push(DiffLine {
-                        kind: '+',
-                        content: line[1..].to_string(),
-                    });
-                }
-                Some('-') => {
-                    hunk.lines.push(DiffLine {
-                        kind: '-',
-                        content: line[1..].to_string(),
-                    });
-                }
-                Some(' ') => {
-                    hunk.lines.push(DiffLine {
-                        kind: ' ',
-                        content: line[1..].to_string(),
-                    });
-                }
-                _ => {}
+/// Parse "git check-ignore -v -n" lines ("<source>:<line>:<pattern>\t<path>")
+/// Non-matching paths have empty fields ("::\t<path>"); a matching negated pattern
+/// ("!keep.log") means the path is explicitly not ignored
+/// Extracted for testability
+fn parse_check_ignore_output(output: &str) -> Vec<IgnoreResult> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (info, path) = line.split_once('\t')?;
+            let (source, pattern) = split_ignore_source(info);
+            let ignored = pattern.is_some_and(|p| !p.starts_with('!'));
+            Some(IgnoreResult {
+                path: path.to_string(),
+                ignored,
+                source: source.map(|s| s.to_string()),
+                pattern: pattern.map(|p| p.to_string()),
+            })
+        })
+        .collect()
+}
+
+/// Split "<source>:<line>:<pattern>" at the line number, since the source path and
+/// the pattern may both contain ':'
+fn split_ignore_source(info: &str) -> (Option<&str>, Option<&str>) {
+    for (idx, _) in info.match_indices(':') {
+        let rest = &info[idx + 1..];
+        if let Some((line_no, pattern)) = rest.split_once(':') {
+            if !line_no.is_empty() && line_no.chars().all(|c| c.is_ascii_digit()) {
+                return (Some(&info[..idx]), Some(pattern));
             }
         }
     }
+    (None, None)
+}
+
+/// Report whether a worktree has tracked changes, without counting them
+/// Untracked files are ignored; git is stopped as soon as the first change line arrives
+pub fn is_worktree_dirty(worktree_path: &str) -> Result<bool, String> {
+    let args = ["status", "--porcelain", "--untracked-files=no"];
+    let mut child = git_command(worktree_path, &args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run git {}: {}", args.join(" "), e))?;
+
+    let mut first_line = String::new();
+    if let Some(stdout) = child.stdout.take() {
+        BufReader::new(stdout)
+            .read_line(&mut first_line)
+            .map_err(|e| format!("Failed to read git status output: {}", e))?;
+    }
 
-    // Save last file
-    if let Some(mut file) = current_file {
-        if let Some(hunk) = current_hunk {
-            file.hunks.push(hunk);
-        }
-        files.push(file);
+    if porcelain_has_changes(&first_line) {
+        // No need to let git finish scanning the rest of the tree
+        let _ = child.kill();
+        let _ = child.wait();
+        return Ok(true);
     }
 
-    files
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run git {}: {}", args.join(" "), e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(false)
 }
 
-/// Parse hunk header like "@@ -1,5 +1,7 @@" into (old_start, old_lines, new_start, new_lines)
-fn parse_hunk_header(line: &str) -> Option<(u32, u32, u32, u32)> {
-    // Format: @@ -old_start,old_lines +new_start,new_lines @@
-    let line = line.trim_start_matches("@@ ");
-    let parts: Vec<&str> = line.split(" @@").next()?.split(' ').collect();
-
-    if parts.len() < 2 {
-        return None;
+/// Whether a worktree is a clean checkout of `rev`: HEAD is that commit and there are
+/// no tracked changes (untracked files are ignored, as in is_worktree_dirty)
+pub fn is_checkout_of(worktree_path: &str, rev: &str) -> Result<bool, String> {
+    if rev.is_empty() || rev.starts_with('-') {
+        return Err(format!("Invalid revision: {}", rev));
     }
 
-    let old_part = parts[0].trim_start_matches('-');
-    let new_part = parts[1].trim_start_matches('+');
+    let target = run_git(
+        worktree_path,
+        &[
+            "rev-parse",
+            "--verify",
+            "-q",
+            &format!("{}^{{commit}}", rev),
+        ],
+    )
+    .map_err(|_| format!("No commit matches {}", rev))?;
+    let head = match run_git(worktree_path, &["rev-parse", "--verify", "-q", "HEAD"]) {
+        Ok(head) => head,
+        // Unborn HEAD isn't a checkout of anything
+        Err(_) => return Ok(false),
+    };
 
-    let (old_start, old_lines) = parse_range(old_part)?;
-    let (new_start, new_lines) = parse_range(new_part)?;
+    if head.trim() != target.trim() {
+        return Ok(false);
+    }
+    Ok(!is_worktree_dirty(worktree_path)?)
+}
 
-    Some((old_start, old_lines, new_start, new_lines))
+/// Whether a line of "git status --porcelain" output describes a change
+/// Extracted for testability
+fn porcelain_has_changes(line: &str) -> bool {
+    !line.trim().is_empty()
 }
 
-/// Parse "start,lines" or just "start" (implies lines=1)
-fn parse_range(s: &str) -> Option<(u32, u32)> {
-    let parts: Vec<&str> = s.split(',').collect();
-    let start = parts[0].parse().ok()?;
-    let lines = if parts.len() > 1 {
-        parts[1].parse().ok()?
+/// Get HEAD info for a single worktree path (cheap refresh after a commit or checkout)
+pub fn get_head_info(worktree_path: &str) -> Result<HeadInfo, String> {
+    // A freshly initialized repo (or orphan branch) has a HEAD pointing at a branch
+    // with no commits yet; report it with empty commit fields instead of failing
+    if let Some(branch) = get_unborn_branch(worktree_path) {
+        return Ok(HeadInfo {
+            branch: Some(branch),
+            commit_sha: String::new(),
+            commit_message: String::new(),
+            upstream: None,
+            unborn: true,
+        });
+    }
+
+    // Get short SHA
+    let short_sha = run_git(worktree_path, &["rev-parse", "--short", "HEAD"])?
+        .trim()
+        .to_string();
+
+    // Get branch name (returns "HEAD" if detached)
+    let branch_output = run_git(worktree_path, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+    let branch_name = branch_output.trim();
+    let branch = if branch_name == "HEAD" {
+        None // Detached HEAD
     } else {
-        1
+        Some(branch_name.to_string())
     };
-    Some((start, lines))
+
+    // Get commit message summary
+    let commit_message = run_git(worktree_path, &["log", "-1", "--format=%s"])?
+        .trim()
+        .to_string();
+
+    // Get upstream tracking info if we have a branch (not detached)
+    let upstream = if branch.is_some() {
+        get_upstream_info(worktree_path)
+    } else {
+        None
+    };
+
+    Ok(HeadInfo {
+        branch,
+        commit_sha: short_sha,
+        commit_message,
+        upstream,
+        unborn: false,
+    })
 }
 
-/// Create a new worktree
-pub fn create_worktree(repo_path: &str, options: CreateWorktreeOptions) -> Result<Worktree, String> {
-    let mut args = vec!["worktree", "add"];
+/// Get the checked-out branch name, or None when HEAD is detached
+/// Cheaper than get_head_info when only the branch is needed; works on unborn branches too
+pub fn get_current_branch(worktree_path: &str) -> Result<Option<String>, String> {
+    let args = ["symbolic-ref", "--short", "-q", "HEAD"];
+    let output = git_command(worktree_path, &args)
+        .output()
+        .map_err(|e| format!("Failed to run git {}: {}", args.join(" "), e))?;
 
-    // Build temporary strings to hold the branch flag
-    let branch_flag;
-    if let Some(ref branch) = options.new_branch {
-        branch_flag = format!("-b");
-        args.push(&branch_flag);
-        args.push(branch);
+    // With -q, a detached HEAD exits 1 quietly; anything else is a real failure
+    match output.status.code() {
+        Some(0) => Ok(Some(
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        )),
+        Some(1) => Ok(None),
+        _ => Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        )),
+    }
+}
+
+/// Detach a worktree's HEAD at its current commit
+pub fn detach_head(worktree_path: &str) -> Result<HeadInfo, String> {
+    if run_git(worktree_path, &["symbolic-ref", "-q", "HEAD"]).is_err() {
+        return Err("HEAD is already detached".to_string());
     }
 
-    if options.detach {
-        args.push("--detach");
+    let args = build_detach_args();
+    run_git(worktree_path, &args)?;
+    get_head_info(worktree_path)
+}
+
+/// Check out `branch` again in a worktree (typically after detach_head)
+/// Only local branches are accepted; a sha, tag, or remote ref would detach HEAD again
+pub fn reattach_head(worktree_path: &str, branch: &str) -> Result<HeadInfo, String> {
+    if branch.is_empty() || branch.starts_with('-') {
+        return Err(format!("Invalid branch name: {}", branch));
+    }
+    let full_ref = format!("refs/heads/{}", branch);
+    if run_git(worktree_path, &["rev-parse", "--verify", "-q", &full_ref]).is_err() {
+        return Err(format!("Branch {} does not exist", branch));
+    }
+
+    let args = build_reattach_args(branch);
+    run_git(worktree_path, &args)?;
+    get_head_info(worktree_path)
+}
+
+/// Build the argument list for detaching HEAD
+/// Extracted for testability
+fn build_detach_args() -> [&'static str; 3] {
+    ["checkout", "-q", "--detach"]
+}
+
+/// Build the argument list for checking out a branch
+/// The trailing "--" keeps a branch named like a path from being treated as one
+/// Extracted for testability
+fn build_reattach_args(branch: &str) -> [&str; 4] {
+    ["checkout", "-q", branch, "--"]
+}
+
+/// Rename the branch checked out in a worktree ("git branch -m <new_name>")
+pub fn rename_current_branch(worktree_path: &str, new_name: &str) -> Result<HeadInfo, String> {
+    if new_name.is_empty() || new_name.starts_with('-') {
+        return Err(format!("Invalid branch name: {}", new_name));
+    }
+    if get_current_branch(worktree_path)?.is_none() {
+        return Err("HEAD is detached; there is no branch to rename".to_string());
+    }
+
+    let args = build_rename_current_branch_args(new_name);
+    run_git(worktree_path, &args)?;
+    get_head_info(worktree_path)
+}
+
+/// Build the argument list for renaming the current branch
+/// With a single name, "branch -m" renames whatever branch HEAD points at
+/// Extracted for testability
+fn build_rename_current_branch_args(new_name: &str) -> [&str; 3] {
+    ["branch", "-m", new_name]
+}
+
+/// Return the branch name if HEAD is unborn (symbolic ref to a branch with no commits)
+fn get_unborn_branch(worktree_path: &str) -> Option<String> {
+    if run_git(worktree_path, &["rev-parse", "--verify", "-q", "HEAD"]).is_ok() {
+        return None;
+    }
+
+    run_git(worktree_path, &["symbolic-ref", "--short", "-q", "HEAD"])
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// Resolve where a push from this worktree's branch would go
+/// Returns None for a detached HEAD or when the resolved remote doesn't exist
+pub fn get_push_target(worktree_path: &str) -> Result<Option<PushTarget>, String> {
+    let Some(branch) = run_git(worktree_path, &["symbolic-ref", "--short", "-q", "HEAD"])
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+    else {
+        return Ok(None);
+    };
+
+    let config = |key: String| {
+        run_git(worktree_path, &["config", "--get", &key])
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+    let remote = resolve_push_remote(
+        config(format!("branch.{}.pushRemote", branch)),
+        config("remote.pushDefault".to_string()),
+        config(format!("branch.{}.remote", branch)),
+    );
+
+    // "git remote get-url" fails for an unknown remote (e.g. no "origin" fallback)
+    let Ok(url) = run_git(worktree_path, &["remote", "get-url", "--push", &remote]) else {
+        return Ok(None);
+    };
+
+    Ok(Some(PushTarget {
+        remote,
+        url: url.trim().to_string(),
+        branch,
+    }))
+}
+
+/// Pick the push remote following git's precedence:
+/// branch.<name>.pushRemote, then remote.pushDefault, then branch.<name>.remote, then "origin"
+/// Extracted for testability
+fn resolve_push_remote(
+    branch_push_remote: Option<String>,
+    push_default: Option<String>,
+    branch_remote: Option<String>,
+) -> String {
+    branch_push_remote
+        .or(push_default)
+        .or(branch_remote)
+        .unwrap_or_else(|| "origin".to_string())
+}
+
+/// Set the current branch's upstream ("git branch --set-upstream-to=<upstream>")
+/// The upstream must be an existing ref, e.g. "origin/main"
+pub fn set_upstream(worktree_path: &str, upstream: &str) -> Result<(), String> {
+    let exists = !upstream.starts_with('-')
+        && run_git(
+            worktree_path,
+            &[
+                "rev-parse",
+                "--verify",
+                "-q",
+                "--symbolic-full-name",
+                upstream,
+            ],
+        )
+        .is_ok_and(|full_name| full_name.trim().starts_with("refs/"));
+    if !exists {
+        return Err(format!("Upstream ref {} does not exist", upstream));
+    }
+
+    let args = build_set_upstream_args(upstream);
+    let arg_refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    run_git(worktree_path, &arg_refs)?;
+    Ok(())
+}
+
+/// Remove the current branch's upstream configuration
+pub fn unset_upstream(worktree_path: &str) -> Result<(), String> {
+    run_git(worktree_path, &["branch", "--unset-upstream"])?;
+    Ok(())
+}
+
+/// Summarize what a push would send: the diff from the upstream to HEAD
+pub fn get_outgoing_stat(worktree_path: &str) -> Result<DiffStats, String> {
+    if run_git(worktree_path, &["rev-parse", "--abbrev-ref", "@{upstream}"]).is_err() {
+        return Err("The current branch has no upstream".to_string());
+    }
+
+    // Three dots diff from the merge base, so commits only on the upstream aren't
+    // counted as (reverted) outgoing changes when the branches have diverged
+    let output = run_git(
+        worktree_path,
+        &["diff", "--shortstat", "@{upstream}...HEAD"],
+    )?;
+    Ok(parse_shortstat(&output))
+}
+
+/// Parse "git diff --shortstat" output, e.g.
+/// " 3 files changed, 10 insertions(+), 2 deletions(-)"
+/// Empty output (no differences) yields zeros
+/// Extracted for testability
+fn parse_shortstat(output: &str) -> DiffStats {
+    let mut stats = DiffStats {
+        files_changed: 0,
+        insertions: 0,
+        deletions: 0,
+    };
+
+    for part in output.trim().split(", ") {
+        let Some((count, label)) = part.split_once(' ') else {
+            continue;
+        };
+        let Ok(count) = count.parse::<u32>() else {
+            continue;
+        };
+        if label.starts_with("file") {
+            stats.files_changed = count;
+        } else if label.starts_with("insertion") {
+            stats.insertions = count;
+        } else if label.starts_with("deletion") {
+            stats.deletions = count;
+        }
+    }
+
+    stats
+}
+
+/// Build the argument list for setting the current branch's upstream
+/// Extracted for testability
+fn build_set_upstream_args(upstream: &str) -> Vec<String> {
+    vec![
+        "branch".to_string(),
+        format!("--set-upstream-to={}", upstream),
+    ]
+}
+
+/// List per-worktree config overrides ("git config --worktree")
+/// Returns an empty list when extensions.worktreeConfig isn't enabled
+pub fn get_worktree_config(worktree_path: &str) -> Result<Vec<(String, String)>, String> {
+    // Without the extension, --worktree either errors (multiple worktrees) or silently
+    // falls back to the shared repo config, neither of which are worktree overrides
+    let enabled = run_git(
+        worktree_path,
+        &["config", "--bool", "--get", "extensions.worktreeConfig"],
+    )
+    .map(|s| s.trim() == "true")
+    .unwrap_or(false);
+    if !enabled {
+        return Ok(Vec::new());
+    }
+
+    match run_git(worktree_path, &["config", "--worktree", "--list"]) {
+        Ok(output) => Ok(parse_config_list(&output)),
+        // Enabled but nothing set yet: config.worktree doesn't exist
+        Err(e) if is_worktree_config_unavailable(&e) => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Whether a "git config --worktree" error just means there are no worktree overrides
+/// Extracted for testability
+fn is_worktree_config_unavailable(error: &str) -> bool {
+    error.contains("unable to read config file")
+        || error.contains("extension worktreeConfig is enabled")
+}
+
+/// Parse "git config --list" output into key/value pairs
+/// A key set without a value (implicit boolean true) has no "=" and yields an empty value
+/// Extracted for testability
+fn parse_config_list(output: &str) -> Vec<(String, String)> {
+    output
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.split_once('=') {
+            Some((key, value)) => (key.to_string(), value.to_string()),
+            None => (line.to_string(), String::new()),
+        })
+        .collect()
+}
+
+/// Resolve a worktree's private git dir and the shared common dir as absolute paths
+/// For the main worktree both are "<repo>/.git"; linked worktrees keep HEAD in
+/// "<repo>/.git/worktrees/<name>" while refs live in the common dir
+pub fn get_git_dirs(worktree_path: &str) -> Result<(PathBuf, PathBuf), String> {
+    let output = run_git(
+        worktree_path,
+        &[
+            "rev-parse",
+            "--path-format=absolute",
+            "--git-dir",
+            "--git-common-dir",
+        ],
+    )?;
+
+    let mut lines = output.lines().map(|l| PathBuf::from(l.trim()));
+    match (lines.next(), lines.next()) {
+        (Some(git_dir), Some(common_dir)) => Ok((git_dir, common_dir)),
+        _ => Err(format!("Failed to resolve git dirs for {}", worktree_path)),
+    }
+}
+
+fn build_worktree_info(path: &PathBuf, is_main: bool) -> Result<Worktree, String> {
+    let path_str = path.to_string_lossy();
+
+    let head = get_head_info(&path_str)?;
+
+    // Get commit timestamp (an unborn HEAD has no commit to date)
+    let timestamp = if head.unborn {
+        0
+    } else {
+        let timestamp_str = run_git(&path_str, &["log", "-1", "--format=%ct"])?;
+        timestamp_str.trim().parse::<i64>().unwrap_or(0)
+    };
+
+    // Defer status scanning - return None initially for faster load
+    // Frontend will fetch status lazily
+    let status = None;
+
+    let sparse = is_sparse_checkout(&path_str);
+    let label = get_worktree_label(&path_str).ok().flatten();
+
+    Ok(Worktree {
+        path: path.clone(),
+        name: path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown")
+            .to_string(),
+        is_main,
+        head,
+        status,
+        last_commit_timestamp: timestamp,
+        sparse,
+        label,
+    })
+}
+
+/// Config key holding a worktree's user-chosen label
+const WORKTREE_LABEL_KEY: &str = "woodeye.label";
+
+/// Read a worktree's custom label, or None when unset
+pub fn get_worktree_label(worktree_path: &str) -> Result<Option<String>, String> {
+    let config_file = worktree_config_file(worktree_path)?;
+    let args = [
+        "config",
+        "--file",
+        &config_file,
+        "--get",
+        WORKTREE_LABEL_KEY,
+    ];
+    let output = git_command(worktree_path, &args)
+        .output()
+        .map_err(|e| format!("Failed to run git {}: {}", args.join(" "), e))?;
+
+    // Exit 1 means the key (or the whole file) doesn't exist
+    match output.status.code() {
+        Some(0) => {
+            let label = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            Ok(Some(label).filter(|l| !l.is_empty()))
+        }
+        Some(1) => Ok(None),
+        _ => Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        )),
+    }
+}
+
+/// Set a worktree's custom label; an empty label removes it
+pub fn set_worktree_label(worktree_path: &str, label: &str) -> Result<(), String> {
+    let config_file = worktree_config_file(worktree_path)?;
+    let label = label.trim();
+    if label.is_empty() {
+        if get_worktree_label(worktree_path)?.is_some() {
+            run_git(
+                worktree_path,
+                &[
+                    "config",
+                    "--file",
+                    &config_file,
+                    "--unset",
+                    WORKTREE_LABEL_KEY,
+                ],
+            )?;
+        }
+        return Ok(());
+    }
+
+    run_git(
+        worktree_path,
+        &["config", "--file", &config_file, WORKTREE_LABEL_KEY, label],
+    )?;
+    Ok(())
+}
+
+/// The worktree's own config.worktree, addressed by path
+/// "git config --worktree" refuses to run in repos with several worktrees unless
+/// extensions.worktreeConfig is enabled; naming the file avoids changing repo format
+fn worktree_config_file(worktree_path: &str) -> Result<String, String> {
+    let (git_dir, _) = get_git_dirs(worktree_path)?;
+    Ok(git_dir
+        .join("config.worktree")
+        .to_string_lossy()
+        .to_string())
+}
+
+/// Whether a worktree uses sparse-checkout (so missing files are expected)
+fn is_sparse_checkout(worktree_path: &str) -> bool {
+    // Without a pattern file git doesn't apply sparse-checkout, so the common case
+    // (no file) is answered with a single git call and the config is never read
+    let has_pattern_file = sparse_checkout_file(worktree_path).is_some_and(|file| file.exists());
+    if !has_pattern_file {
+        return false;
+    }
+
+    let config = run_git(
+        worktree_path,
+        &["config", "--bool", "--get", "core.sparseCheckout"],
+    )
+    .ok()
+    .map(|s| s.trim().to_string());
+
+    detect_sparse(config.as_deref(), has_pattern_file)
+}
+
+/// Decide sparseness from core.sparseCheckout and the presence of a pattern file
+/// An explicit "false" wins: "git sparse-checkout disable" leaves the file behind
+/// Extracted for testability
+fn detect_sparse(config_value: Option<&str>, has_pattern_file: bool) -> bool {
+    match config_value {
+        Some(value) => value == "true",
+        None => has_pattern_file,
+    }
+}
+
+/// Path of the worktree's sparse-checkout pattern file (in its private git dir)
+fn sparse_checkout_file(worktree_path: &str) -> Option<PathBuf> {
+    let output = run_git(
+        worktree_path,
+        &["rev-parse", "--git-path", "info/sparse-checkout"],
+    )
+    .ok()?;
+    let path = PathBuf::from(output.trim());
+    // --git-path may be relative to the worktree
+    Some(if path.is_absolute() {
+        path
+    } else {
+        Path::new(worktree_path).join(path)
+    })
+}
+
+/// Read the active sparse-checkout patterns for a worktree
+/// Returns an empty list when the worktree isn't sparse
+pub fn get_sparse_patterns(worktree_path: &str) -> Result<Vec<String>, String> {
+    let Some(file) = sparse_checkout_file(worktree_path) else {
+        return Ok(Vec::new());
+    };
+    match std::fs::read_to_string(&file) {
+        Ok(content) => Ok(parse_sparse_patterns(&content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(format!("Failed to read {}: {}", file.display(), e)),
+    }
+}
+
+/// Parse a sparse-checkout file, skipping blank lines and comments
+/// Extracted for testability
+fn parse_sparse_patterns(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(|line| line.trim_end())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// With `cancel`, the in-flight "git status" is killed once the flag is set, so a
+/// scan of a huge tree the user navigated away from doesn't run to completion
+fn get_worktree_status(
+    worktree_path: &str,
+    untracked_mode: UntrackedMode,
+    cancel: Option<&AtomicBool>,
+) -> Result<WorktreeStatus, String> {
+    let args = build_status_args(untracked_mode);
+    let output = match cancel {
+        Some(cancel) => run_git_read_cancellable(worktree_path, &args, cancel)?
+            .ok_or_else(|| "Status scan cancelled".to_string())?,
+        None => run_git_read(worktree_path, &args)?,
+    };
+    let mut status = parse_status_porcelain(&output);
+    status.untracked_scanned = untracked_mode != UntrackedMode::No;
+    Ok(status)
+}
+
+/// How often output_cancellable checks the cancel flag
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Like Command::output, but kills the child and returns None once `cancel` is set
+/// stdout/stderr are drained on their own threads so a full pipe can't stall the child
+fn output_cancellable(cmd: &mut Command, cancel: &AtomicBool) -> std::io::Result<Option<Output>> {
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+
+    let drain = |pipe: Option<Box<dyn Read + Send>>| {
+        std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_end(&mut buf);
+            }
+            buf
+        })
+    };
+    let stdout = drain(
+        child
+            .stdout
+            .take()
+            .map(|p| Box::new(p) as Box<dyn Read + Send>),
+    );
+    let stderr = drain(
+        child
+            .stderr
+            .take()
+            .map(|p| Box::new(p) as Box<dyn Read + Send>),
+    );
+
+    let Some(status) = wait_cancellable(&mut child, cancel, || {
+        std::thread::sleep(CANCEL_POLL_INTERVAL)
+    })?
+    else {
+        return Ok(None);
+    };
+
+    Ok(Some(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    }))
+}
+
+/// Wait for `child` to exit, killing and reaping it (and returning None) once `cancel`
+/// is set. `between_polls` runs while the child is alive and should block for about
+/// CANCEL_POLL_INTERVAL, e.g. sleeping or waiting on a progress channel
+fn wait_cancellable<F>(
+    child: &mut Child,
+    cancel: &AtomicBool,
+    mut between_polls: F,
+) -> std::io::Result<Option<ExitStatus>>
+where
+    F: FnMut(),
+{
+    loop {
+        if cancel.load(Ordering::SeqCst) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        between_polls();
+    }
+}
+
+/// Build the argument list for "git status --porcelain" with an untracked-files mode
+/// Extracted for testability
+fn build_status_args(untracked_mode: UntrackedMode) -> [&'static str; 3] {
+    let untracked = match untracked_mode {
+        UntrackedMode::No => "--untracked-files=no",
+        UntrackedMode::Normal => "--untracked-files=normal",
+        UntrackedMode::All => "--untracked-files=all",
+    };
+    ["status", "--porcelain", untracked]
+}
+
+/// "git log" format consumed by parse_commit_log
+/// Use record separator (%x1e) between commits and unit separator (%x1f) between fields
+/// Format: hash, short_hash, author_name, author_email, commit time, author time, summary,
+/// body, parents
+const COMMIT_LOG_FORMAT: &str = "%H%x1f%h%x1f%an%x1f%ae%x1f%ct%x1f%at%x1f%s%x1f%B%x1f%P%x1e";
+
+// Get commit history for a worktree
+pub fn get_commit_history(
+    worktree_path: &str,
+    limit: usize,
+    offset: usize,
+    no_merges: bool,
+    first_parent: bool,
+) -> Result<Vec<CommitInfo>, String> {
+    let args = build_log_args(limit, offset, no_merges, first_parent);
+    let arg_refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    let output = match run_git(worktree_path, &arg_refs) {
+        Ok(output) => output,
+        // No commits yet - an empty history rather than an error
+        Err(_) if get_unborn_branch(worktree_path).is_some() => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    Ok(parse_commit_log(&output))
+}
+
+/// Build the argument list for "git log" used by get_commit_history
+/// Extracted for testability
+fn build_log_args(limit: usize, offset: usize, no_merges: bool, first_parent: bool) -> Vec<String> {
+    let mut args = vec![
+        "log".to_string(),
+        format!("--format={}", COMMIT_LOG_FORMAT),
+        format!("--skip={}", offset),
+        format!("-n{}", limit),
+    ];
+
+    if no_merges {
+        args.push("--no-merges".to_string());
+    }
+
+    if first_parent {
+        args.push("--first-parent".to_string());
+    }
+
+    args
+}
+
+/// Trimmed "git log" format consumed by parse_commit_oneline: short_hash, summary, refs
+const COMMIT_ONELINE_FORMAT: &str = "%h%x1f%s%x1f%D%x1e";
+
+/// Get a compact page of history (short hash, summary, refs) for dense views
+/// Roughly half the output of get_commit_history, which also fetches author and body
+pub fn get_commit_oneline(
+    worktree_path: &str,
+    limit: usize,
+    offset: usize,
+) -> Result<Vec<CommitOneline>, String> {
+    let format = format!("--format={}", COMMIT_ONELINE_FORMAT);
+    let skip = format!("--skip={}", offset);
+    let count = format!("-n{}", limit);
+    let output = match run_git(worktree_path, &["log", &format, &skip, &count]) {
+        Ok(output) => output,
+        // No commits yet - an empty history rather than an error
+        Err(_) if get_unborn_branch(worktree_path).is_some() => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    Ok(parse_commit_oneline(&output))
+}
+
+/// Parse records produced with COMMIT_ONELINE_FORMAT
+/// Refs come from %D ("HEAD -> main, origin/main, tag: v1.0") and are split on ", "
+/// Extracted for testability
+fn parse_commit_oneline(output: &str) -> Vec<CommitOneline> {
+    output
+        .split('\x1e')
+        .map(|record| record.trim())
+        .filter(|record| !record.is_empty())
+        .filter_map(|record| {
+            let fields: Vec<&str> = record.split('\x1f').collect();
+            if fields.len() < 2 {
+                return None;
+            }
+            let refs = fields
+                .get(2)
+                .map(|d| {
+                    d.split(", ")
+                        .filter(|r| !r.is_empty())
+                        .map(|r| r.to_string())
+                        .collect()
+                })
+                .unwrap_or_default();
+            Some(CommitOneline {
+                short_hash: fields[0].to_string(),
+                summary: fields[1].to_string(),
+                refs,
+            })
+        })
+        .collect()
+}
+
+/// Get recent commits with precomputed graph lanes and edge segments for rendering
+pub fn get_commit_graph(worktree_path: &str, limit: usize) -> Result<CommitGraph, String> {
+    let output = run_git(
+        worktree_path,
+        &[
+            "log",
+            "--topo-order",
+            &format!("--format={}", COMMIT_LOG_FORMAT),
+            &format!("-n{}", limit),
+        ],
+    )?;
+
+    let commits = parse_commit_log(&output);
+    let rows = assign_graph_lanes(&commits);
+    let lane_count = rows
+        .iter()
+        .flat_map(|r| std::iter::once(r.lane).chain(r.edges.iter().map(|e| e.to_lane)))
+        .max()
+        .map_or(0, |max| max + 1);
+
+    Ok(CommitGraph {
+        commits,
+        rows,
+        lane_count,
+    })
+}
+
+/// Assign each commit (in topological order, newest first) a lane and compute the
+/// edge segments drawn between its row and the next one
+/// Lanes are never compacted, so a line keeps its column until its commit is reached
+fn assign_graph_lanes(commits: &[CommitInfo]) -> Vec<GraphRow> {
+    // Hash each lane is waiting for, before the current row
+    let mut lanes: Vec<Option<String>> = Vec::new();
+    // Per row: commit lane, segments as (from lane, lane in `lanes` after the row), lanes after
+    type PendingRow = (usize, Vec<(usize, usize)>, Vec<Option<String>>);
+    let mut pending: Vec<PendingRow> = Vec::new();
+
+    for commit in commits {
+        let lane = lanes
+            .iter()
+            .position(|l| l.as_deref() == Some(commit.hash.as_str()))
+            .or_else(|| lanes.iter().position(|l| l.is_none()))
+            .unwrap_or_else(|| {
+                lanes.push(None);
+                lanes.len() - 1
+            });
+
+        // Every lane waiting for this commit converges here
+        for slot in lanes.iter_mut() {
+            if slot.as_deref() == Some(commit.hash.as_str()) {
+                *slot = None;
+            }
+        }
+
+        // Untouched lines pass straight through
+        let mut segments: Vec<(usize, usize)> = lanes
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| l.is_some())
+            .map(|(i, _)| (i, i))
+            .collect();
+
+        for (idx, parent) in commit.parent_hashes.iter().enumerate() {
+            if let Some(existing) = lanes.iter().position(|l| l.as_ref() == Some(parent)) {
+                // Parent already has a line - join it
+                segments.push((lane, existing));
+                continue;
+            }
+
+            let target = if idx == 0 && lanes[lane].is_none() {
+                lane
+            } else {
+                lanes.iter().position(|l| l.is_none()).unwrap_or_else(|| {
+                    lanes.push(None);
+                    lanes.len() - 1
+                })
+            };
+            lanes[target] = Some(parent.clone());
+            segments.push((lane, target));
+        }
+
+        pending.push((lane, segments, lanes.clone()));
+    }
+
+    // A segment ends in its own column unless the next row is the commit it leads to
+    (0..pending.len())
+        .map(|row| {
+            let (lane, segments, after) = &pending[row];
+            let next = commits
+                .get(row + 1)
+                .map(|c| (c.hash.as_str(), pending[row + 1].0));
+
+            let edges = segments
+                .iter()
+                .map(|&(from, to)| {
+                    let to_lane = match next {
+                        Some((hash, next_lane)) if after[to].as_deref() == Some(hash) => next_lane,
+                        _ => to,
+                    };
+                    GraphEdge {
+                        from_lane: from as u32,
+                        to_lane: to_lane as u32,
+                    }
+                })
+                .collect();
+
+            GraphRow {
+                lane: *lane as u32,
+                edges,
+            }
+        })
+        .collect()
+}
+
+/// Get CommitInfo for a single revision
+fn get_commit_info(worktree_path: &str, rev: &str) -> Result<CommitInfo, String> {
+    let output = run_git(
+        worktree_path,
+        &["log", "-1", &format!("--format={}", COMMIT_LOG_FORMAT), rev],
+    )?;
+
+    parse_commit_log(&output)
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("Failed to parse commit info for {}", rev))
+}
+
+// Get diff for a specific commit
+pub fn get_commit_diff(worktree_path: &str, commit_sha: &str) -> Result<CommitDiff, String> {
+    // Get commit info using git log
+    let commit_info = get_commit_info(worktree_path, commit_sha)?;
+
+    // Get the stat block and the patch from one git show; --stat=1000 keeps paths untruncated
+    let graph_width = format!("--stat-graph-width={}", STAT_GRAPH_WIDTH);
+    let output = run_git(
+        worktree_path,
+        &[
+            "show",
+            commit_sha,
+            "--format=",
+            "-U3",
+            "-M",
+            "--stat=1000",
+            &graph_width,
+            "-p",
+        ],
+    )?;
+    let (stat_output, diff_output) = split_stat_and_patch(&output);
+
+    let mut files = parse_git_diff_output(diff_output);
+
+    // Stat graph bars, scaled by git across all files in the commit
+    let graphs: std::collections::HashMap<String, String> = parse_stat_graphs(stat_output)
+        .into_iter()
+        .filter_map(|(path, graph)| graph.map(|graph| (path, graph)))
+        .collect();
+    for file in &mut files {
+        file.stat_graph = graphs.get(&file.path).cloned();
+    }
+
+    // Calculate stats
+    let mut total_insertions = 0u32;
+    let mut total_deletions = 0u32;
+
+    for file in &files {
+        for hunk in &file.hunks {
+            for line in &hunk.lines {
+                match line.kind {
+                    '+' => total_insertions += 1,
+                    '-' => total_deletions += 1,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    let files_changed = files.len() as u32;
+
+    Ok(CommitDiff {
+        commit: commit_info,
+        files,
+        stats: DiffStats {
+            files_changed,
+            insertions: total_insertions,
+            deletions: total_deletions,
+        },
+    })
+}
+
+/// Preview what reverting a commit would change: its diff applied in reverse
+/// `commit` is the commit being reverted; additions and deletions are swapped in
+/// `files` and `stats` relative to get_commit_diff
+pub fn get_revert_preview(worktree_path: &str, commit_sha: &str) -> Result<CommitDiff, String> {
+    let commit_info = get_commit_info(worktree_path, commit_sha)?;
+
+    let args = build_revert_preview_args(commit_sha);
+    let arg_refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    let diff_output = run_git(worktree_path, &arg_refs)?;
+
+    let files = parse_git_diff_output(&diff_output);
+    let stats = compute_diff_stats(&files, &[]);
+    Ok(CommitDiff {
+        commit: commit_info,
+        files,
+        stats,
+    })
+}
+
+/// Build the argument list for a commit's reversed patch ("git show -R")
+/// Extracted for testability
+fn build_revert_preview_args(commit_sha: &str) -> Vec<String> {
+    vec![
+        "show".to_string(),
+        "-R".to_string(),
+        commit_sha.to_string(),
+        "--format=".to_string(),
+        "-U3".to_string(),
+        "-M".to_string(),
+    ]
+}
+
+/// Stream a commit's diff, calling `on_file` as each file's block is complete
+/// Unlike get_commit_diff, the full "git show" output is never held in memory, so huge
+/// commits render progressively. Stat graphs aren't computed. Returns the file count
+pub fn stream_commit_diff<F>(
+    worktree_path: &str,
+    commit_sha: &str,
+    mut on_file: F,
+) -> Result<u32, String>
+where
+    F: FnMut(FileDiff),
+{
+    if commit_sha.is_empty() || commit_sha.starts_with('-') {
+        return Err(format!("Invalid commit: {}", commit_sha));
+    }
+
+    let args = ["show", commit_sha, "--format=", "-U3", "-M"];
+    let mut child = git_command(worktree_path, &args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run git {}: {}", args.join(" "), e))?;
+
+    // Drain stderr on its own thread so a chatty git can't block on a full pipe
+    let stderr = child.stderr.take();
+    let stderr_reader = std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut stderr) = stderr {
+            let _ = stderr.read_to_end(&mut buf);
+        }
+        buf
+    });
+
+    let mut splitter = DiffFileSplitter::default();
+    let mut count = 0u32;
+    if let Some(mut stdout) = child.stdout.take() {
+        let mut buf = [0u8; 64 * 1024];
+        loop {
+            let read = match stdout.read(&mut buf) {
+                Ok(read) => read,
+                Err(e) => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    let _ = stderr_reader.join();
+                    return Err(format!("Failed to read git show output: {}", e));
+                }
+            };
+            if read == 0 {
+                break;
+            }
+            for file in splitter.push(&buf[..read]) {
+                count += 1;
+                on_file(file);
+            }
+        }
+    }
+
+    let status = child
+        .wait()
+        .map_err(|e| format!("Failed to run git {}: {}", args.join(" "), e))?;
+    let stderr = stderr_reader.join().unwrap_or_default();
+    if !status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&stderr)
+        ));
+    }
+
+    for file in splitter.finish() {
+        count += 1;
+        on_file(file);
+    }
+    Ok(count)
+}
+
+/// Incremental diff parser: buffers raw output and yields each file once the next
+/// "diff --git" header shows its block is complete
+/// Reads may end mid-line (or mid UTF-8 character), so only whole lines are examined
+#[derive(Default)]
+struct DiffFileSplitter {
+    /// Bytes after the last newline seen so far
+    partial_line: Vec<u8>,
+    /// Complete lines of the file currently being read
+    current_block: String,
+}
+
+impl DiffFileSplitter {
+    /// Feed the next chunk of output, returning any files it completed
+    fn push(&mut self, chunk: &[u8]) -> Vec<FileDiff> {
+        self.partial_line.extend_from_slice(chunk);
+        let Some(last_newline) = self.partial_line.iter().rposition(|&b| b == b'\n') else {
+            return Vec::new();
+        };
+        let rest = self.partial_line.split_off(last_newline + 1);
+        let complete = std::mem::replace(&mut self.partial_line, rest);
+
+        let mut files = Vec::new();
+        for line in String::from_utf8_lossy(&complete).split_inclusive('\n') {
+            if line.starts_with("diff --git ") && !self.current_block.is_empty() {
+                files.extend(parse_git_diff_output(&std::mem::take(
+                    &mut self.current_block,
+                )));
+            }
+            self.current_block.push_str(line);
+        }
+        files
+    }
+
+    /// Parse whatever remains once the output has ended
+    fn finish(mut self) -> Vec<FileDiff> {
+        self.current_block
+            .push_str(&String::from_utf8_lossy(&self.partial_line));
+        parse_git_diff_output(&self.current_block)
+    }
+}
+
+/// Maximum width of the "+++---" bars requested from git --stat
+const STAT_GRAPH_WIDTH: usize = 40;
+
+/// Split "git show --stat -p" output into its stat block and the patch that follows
+/// Extracted for testability
+fn split_stat_and_patch(output: &str) -> (&str, &str) {
+    if output.starts_with("diff --git ") {
+        return ("", output);
+    }
+    match output.find("\ndiff --git ") {
+        Some(index) => output.split_at(index + 1),
+        None => (output, ""),
+    }
+}
+
+/// Parse the bar graphs from "git show --stat" output as (path, graph) pairs
+/// Renames are keyed by their new path (--stat uses the same forms as --numstat);
+/// binary ("Bin ...") and unchanged lines yield None; the summary line is skipped
+/// Extracted for testability
+fn parse_stat_graphs(output: &str) -> Vec<(String, Option<String>)> {
+    output
+        .lines()
+        .filter_map(|line| line.rsplit_once('|'))
+        .map(|(name, rest)| {
+            let (_, path) = split_numstat_rename(name.trim());
+            let rest = rest.trim();
+            if rest.starts_with("Bin") {
+                return (path, None);
+            }
+            let graph = rest
+                .split_once(' ')
+                .map(|(_, graph)| graph.trim().to_string())
+                .filter(|graph| !graph.is_empty());
+            (path, graph)
+        })
+        .collect()
+}
+
+/// Get the diff of a single file within a commit (lazy per-file loading)
+pub fn get_commit_file_diff(
+    worktree_path: &str,
+    commit_sha: &str,
+    file_path: &str,
+) -> Result<FileDiff, String> {
+    let diff_output = run_git(
+        worktree_path,
+        &[
+            "show",
+            commit_sha,
+            "--format=",
+            "-U3",
+            "-M",
+            "--",
+            file_path,
+        ],
+    )?;
+
+    select_file_diff(parse_git_diff_output(&diff_output), file_path)
+        .ok_or_else(|| format!("File {} is not changed in commit {}", file_path, commit_sha))
+}
+
+/// Get the staged (index vs HEAD) diff of a single file (lazy per-file loading)
+/// Returns a FileDiff with no hunks when nothing is staged for the file
+pub fn get_staged_file_diff(worktree_path: &str, file_path: &str) -> Result<FileDiff, String> {
+    let diff_output = run_git(worktree_path, &["diff", "--cached", "-U3", "--", file_path])?;
+
+    Ok(
+        select_file_diff(parse_git_diff_output(&diff_output), file_path)
+            .unwrap_or_else(|| unchanged_file_diff(file_path)),
+    )
+}
+
+/// Diff a working-tree file against its version at `rev` (e.g. "compare with main")
+/// A file missing at `rev` shows as all additions, even if it is untracked; an
+/// unchanged file gives a FileDiff with no hunks
+pub fn diff_file_against_rev(
+    worktree_path: &str,
+    file_path: &str,
+    rev: &str,
+) -> Result<FileDiff, String> {
+    if rev.is_empty() || rev.starts_with('-') {
+        return Err(format!("Invalid revision: {}", rev));
+    }
+
+    let diff_output = run_git(worktree_path, &["diff", "-U3", rev, "--", file_path])?;
+    if let Some(file) = select_file_diff(parse_git_diff_output(&diff_output), file_path) {
+        return Ok(file);
+    }
+
+    // "git diff <rev>" skips untracked files; diff those against nothing instead
+    let tracked = run_git(
+        worktree_path,
+        &["ls-files", "--error-unmatch", "--", file_path],
+    );
+    if tracked.is_err() && Path::new(worktree_path).join(file_path).is_file() {
+        let args = ["diff", "--no-index", "-U3", "--", "/dev/null", file_path];
+        let output = git_command(worktree_path, &args)
+            .output()
+            .map_err(|e| format!("Failed to run git {}: {}", args.join(" "), e))?;
+        // --no-index exits 1 when the files differ
+        if output.status.code() != Some(1) {
+            return Err(format!(
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        let diff_output = String::from_utf8_lossy(&output.stdout);
+        if let Some(file) = select_file_diff(parse_git_diff_output(&diff_output), file_path) {
+            return Ok(file);
+        }
+    }
+
+    Ok(unchanged_file_diff(file_path))
+}
+
+/// Blame a file line by line, as of `rev` when given (else the working tree version)
+/// Line numbers refer to the file as it is at `rev`, not to the current checkout
+pub fn blame_file(
+    worktree_path: &str,
+    file_path: &str,
+    rev: Option<&str>,
+) -> Result<Vec<BlameLine>, String> {
+    if let Some(rev) = rev {
+        if rev.is_empty() || rev.starts_with('-') {
+            return Err(format!("Invalid revision: {}", rev));
+        }
+        let object = format!("{}:{}", rev, file_path);
+        if run_git(worktree_path, &["cat-file", "-e", &object]).is_err() {
+            return Err(format!("File {} does not exist at {}", file_path, rev));
+        }
+    }
+
+    let args = build_blame_args(file_path, rev);
+    let output = run_git(worktree_path, &args)?;
+    Ok(parse_blame_porcelain(&output))
+}
+
+/// Build the argument list for "git blame --porcelain [<rev>] -- <file>"
+/// Extracted for testability
+fn build_blame_args<'a>(file_path: &'a str, rev: Option<&'a str>) -> Vec<&'a str> {
+    let mut args = vec!["blame", "--porcelain"];
+    args.extend(rev);
+    args.push("--");
+    args.push(file_path);
+    args
+}
+
+/// Parse "git blame --porcelain" output
+/// Each line starts with "<sha> <orig line> <final line>[ <group size>]"; commit details
+/// (author, author-time, summary) follow only the first time a commit appears, and the
+/// line's content comes last, prefixed with a tab
+/// Extracted for testability
+fn parse_blame_porcelain(output: &str) -> Vec<BlameLine> {
+    // sha -> (author, author time, summary)
+    let mut commits: std::collections::HashMap<String, (String, i64, String)> =
+        std::collections::HashMap::new();
+    let mut lines = Vec::new();
+    let mut current: Option<(String, u32, u32)> = None;
+
+    for line in output.lines() {
+        if let Some(content) = line.strip_prefix('\t') {
+            let Some((sha, original_line, line_number)) = current.take() else {
+                continue;
+            };
+            let (author_name, author_time, summary) =
+                commits.get(&sha).cloned().unwrap_or_default();
+            lines.push(BlameLine {
+                line_number,
+                original_line,
+                commit_sha: sha,
+                author_name,
+                author_time,
+                summary,
+                content: content.to_string(),
+            });
+        } else if let Some((sha, original_line, line_number)) = parse_blame_header(line) {
+            commits.entry(sha.clone()).or_default();
+            current = Some((sha, original_line, line_number));
+        } else if let Some((sha, _, _)) = &current {
+            let Some(details) = commits.get_mut(sha) else {
+                continue;
+            };
+            if let Some(author) = line.strip_prefix("author ") {
+                details.0 = author.to_string();
+            } else if let Some(time) = line.strip_prefix("author-time ") {
+                details.1 = time.parse().unwrap_or(0);
+            } else if let Some(summary) = line.strip_prefix("summary ") {
+                details.2 = summary.to_string();
+            }
+        }
+    }
+
+    lines
+}
+
+/// Parse a porcelain blame header "<40-hex sha> <orig line> <final line>[ <count>]"
+fn parse_blame_header(line: &str) -> Option<(String, u32, u32)> {
+    let mut fields = line.split(' ');
+    let sha = fields.next()?;
+    if sha.len() != 40 || !sha.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let original_line = fields.next()?.parse().ok()?;
+    let line_number = fields.next()?.parse().ok()?;
+    Some((sha.to_string(), original_line, line_number))
+}
+
+/// A FileDiff with no hunks, for a file with nothing to show
+fn unchanged_file_diff(file_path: &str) -> FileDiff {
+    FileDiff {
+        path: file_path.to_string(),
+        status: FileStatus::Modified,
+        old_path: None,
+        hunks: Vec::new(),
+        binary: false,
+        old_mode: None,
+        new_mode: None,
+        chmod: None,
+        old_blob_sha: None,
+        new_blob_sha: None,
+        mime_guess: None,
+        stat_graph: None,
+    }
+}
+
+/// Preview the commit that would be created from the index right now
+/// The CommitInfo is synthetic: empty hash and message, the configured author, and the
+/// current HEAD (none when unborn) as parent
+pub fn get_commit_preview(worktree_path: &str) -> Result<CommitDiff, String> {
+    let diff_output = run_git(worktree_path, &["diff", "--cached", "-U3", "-M"])?;
+    let files = parse_git_diff_output(&diff_output);
+    let stats = compute_diff_stats(&files, &[]);
+
+    let parent_hashes = run_git(worktree_path, &["rev-parse", "--verify", "-q", "HEAD"])
+        .map(|sha| vec![sha.trim().to_string()])
+        .unwrap_or_default();
+    let ident = run_git(worktree_path, &["var", "GIT_AUTHOR_IDENT"]).unwrap_or_default();
+    let (author_name, author_email, timestamp) = parse_ident(ident.trim());
+
+    Ok(CommitDiff {
+        commit: CommitInfo {
+            hash: String::new(),
+            short_hash: String::new(),
+            author_name,
+            author_email,
+            timestamp,
+            author_timestamp: timestamp,
+            message: String::new(),
+            summary: String::new(),
+            parent_hashes,
+            trailers: Vec::new(),
+        },
+        files,
+        stats,
+    })
+}
+
+/// Split a git ident ("Name <email> <unix time> <tz>") into name, email, and time
+/// Extracted for testability
+fn parse_ident(ident: &str) -> (String, String, i64) {
+    let Some((name, rest)) = ident.split_once(" <") else {
+        return (ident.to_string(), String::new(), 0);
+    };
+    let (email, time) = rest.split_once('>').unwrap_or((rest, ""));
+    let timestamp = time
+        .split_whitespace()
+        .next()
+        .and_then(|t| t.parse().ok())
+        .unwrap_or(0);
+    (name.to_string(), email.to_string(), timestamp)
+}
+
+/// Read a blob's raw content, base64-encoded for transfer to the frontend
+pub fn get_blob(worktree_path: &str, sha: &str) -> Result<String, String> {
+    // Only accept (possibly abbreviated) hex object names so the argument can't be an option
+    if sha.is_empty() || !sha.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid blob sha: {}", sha));
+    }
+
+    let args = ["cat-file", "blob", sha];
+    let output = git_command(worktree_path, &args)
+        .output()
+        .map_err(|e| format!("Failed to run git {}: {}", args.join(" "), e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    Ok(BASE64_STANDARD.encode(&output.stdout))
+}
+
+/// Read up to `max_bytes` of a working-tree file, base64-encoded, for image previews
+/// and hex dumps. Paths resolving outside the worktree (via ".." or symlinks) are rejected
+pub fn read_worktree_file(
+    worktree_path: &str,
+    file_path: &str,
+    max_bytes: u64,
+) -> Result<FileBytes, String> {
+    let root = Path::new(worktree_path)
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve worktree {}: {}", worktree_path, e))?;
+    let target = root
+        .join(file_path)
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve {}: {}", file_path, e))?;
+    if !target.starts_with(&root) {
+        return Err(format!("Path {} is outside the worktree", file_path));
+    }
+
+    let file =
+        std::fs::File::open(&target).map_err(|e| format!("Failed to open {}: {}", file_path, e))?;
+    let total_size = file
+        .metadata()
+        .map_err(|e| format!("Failed to read {}: {}", file_path, e))?
+        .len();
+
+    let mut data = Vec::new();
+    file.take(max_bytes)
+        .read_to_end(&mut data)
+        .map_err(|e| format!("Failed to read {}: {}", file_path, e))?;
+
+    Ok(FileBytes {
+        data_base64: BASE64_STANDARD.encode(&data),
+        truncated: (data.len() as u64) < total_size,
+        total_size,
+        is_binary: looks_binary(&data),
+    })
+}
+
+/// Bytes git inspects for a NUL when deciding whether content is binary
+const BINARY_SNIFF_LEN: usize = 8000;
+
+/// Git's heuristic: content is binary if its first 8000 bytes contain a NUL
+/// Extracted for testability
+fn looks_binary(data: &[u8]) -> bool {
+    data.iter().take(BINARY_SNIFF_LEN).any(|&b| b == 0)
+}
+
+/// Preview the changes a stash would apply
+/// `commit` describes the stash entry itself (its "WIP on ..." message and timestamp)
+pub fn get_stash_diff(
+    worktree_path: &str,
+    index: u32,
+    include_untracked: bool,
+) -> Result<CommitDiff, String> {
+    let commit = get_commit_info(worktree_path, &format!("stash@{{{}}}", index))?;
+
+    let args = build_stash_show_args(index, include_untracked);
+    let arg_refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    let files = parse_git_diff_output(&run_git(worktree_path, &arg_refs)?);
+    let stats = compute_diff_stats(&files, &[]);
+
+    Ok(CommitDiff {
+        commit,
+        files,
+        stats,
+    })
+}
+
+/// Build the argument list for "git stash show -p"
+/// Extracted for testability
+fn build_stash_show_args(index: u32, include_untracked: bool) -> Vec<String> {
+    let mut args = vec!["stash".to_string(), "show".to_string(), "-p".to_string()];
+    if include_untracked {
+        args.push("--include-untracked".to_string());
+    }
+    args.push("-U3".to_string());
+    args.push(format!("stash@{{{}}}", index));
+    args
+}
+
+/// Count the stashes made while the current branch was checked out
+/// Stashes are repo-global; with a detached HEAD there is no branch to match, so 0
+pub fn branch_stash_count(worktree_path: &str) -> Result<u32, String> {
+    let Some(branch) = get_current_branch(worktree_path)? else {
+        return Ok(0);
+    };
+    let output = run_git(worktree_path, &["stash", "list", "--format=%gd %gs"])?;
+    Ok(count_branch_stashes(&output, &branch))
+}
+
+/// Count "stash list --format=%gd %gs" lines whose message is "WIP on <branch>:" or
+/// "On <branch>:" (the latter for stashes pushed with a message)
+/// Extracted for testability
+fn count_branch_stashes(output: &str, branch: &str) -> u32 {
+    output
+        .lines()
+        .filter_map(|line| line.split_once(' ').map(|(_, subject)| subject))
+        .filter_map(|subject| {
+            subject
+                .strip_prefix("WIP on ")
+                .or_else(|| subject.strip_prefix("On "))
+        })
+        .filter(|rest| {
+            rest.strip_prefix(branch)
+                .is_some_and(|after| after.starts_with(':'))
+        })
+        .count() as u32
+}
+
+/// Pick the FileDiff matching `file_path` (by new or old path) from parsed diff output
+/// Extracted for testability
+fn select_file_diff(files: Vec<FileDiff>, file_path: &str) -> Option<FileDiff> {
+    files
+        .into_iter()
+        .find(|f| f.path == file_path || f.old_path.as_deref() == Some(file_path))
+}
+
+/// Pair a hunk's lines into rows for a side-by-side view
+/// Each run of deletions is matched row-by-row with the additions that follow it;
+/// context lines appear on both sides
+pub fn to_split_diff(hunk: &DiffHunk) -> Vec<SplitRow> {
+    let mut rows = Vec::new();
+    let mut deletions: Vec<DiffLine> = Vec::new();
+    let mut additions: Vec<DiffLine> = Vec::new();
+
+    for line in &hunk.lines {
+        match line.kind {
+            '-' => {
+                // A deletion after additions starts a new replacement block
+                if !additions.is_empty() {
+                    flush_split_block(&mut rows, &mut deletions, &mut additions);
+                }
+                deletions.push(line.clone());
+            }
+            '+' => additions.push(line.clone()),
+            _ => {
+                flush_split_block(&mut rows, &mut deletions, &mut additions);
+                rows.push(SplitRow {
+                    left: Some(line.clone()),
+                    right: Some(line.clone()),
+                });
+            }
+        }
+    }
+    flush_split_block(&mut rows, &mut deletions, &mut additions);
+
+    rows
+}
+
+/// Emit pending deletions/additions as paired rows, padding the shorter side with None
+fn flush_split_block(
+    rows: &mut Vec<SplitRow>,
+    deletions: &mut Vec<DiffLine>,
+    additions: &mut Vec<DiffLine>,
+) {
+    let count = deletions.len().max(additions.len());
+    let mut left = deletions.drain(..);
+    let mut right = additions.drain(..);
+    for _ in 0..count {
+        rows.push(SplitRow {
+            left: left.next(),
+            right: right.next(),
+        });
+    }
+}
+
+// Get uncommitted working directory changes using git CLI
+// `scope` skips the git invocations for panes the caller doesn't need
+// `detect_renames` forces -M on the staged diff and pairs unstaged deletions with
+// similar untracked files, which git can't report as renames until they're staged
+pub fn get_working_diff(
+    worktree_path: &str,
+    scope: DiffScope,
+    detect_renames: bool,
+) -> Result<WorkingDiff, String> {
+    let mut diff = collect_working_diff(scope, detect_renames, |args| {
+        run_git_read(worktree_path, args)
+    })?;
+
+    if detect_renames {
+        pair_untracked_renames(worktree_path, &mut diff.unstaged_files);
+        diff.stats = compute_diff_stats(&diff.staged_files, &diff.unstaged_files);
+    }
+
+    Ok(diff)
+}
+
+/// Group all uncommitted changes (staged, unstaged, untracked) by directory
+pub fn get_working_diff_tree(worktree_path: &str) -> Result<DiffTree, String> {
+    let diff = get_working_diff(worktree_path, DiffScope::All, false)?;
+    Ok(build_diff_tree(
+        diff.staged_files.iter().chain(diff.unstaged_files.iter()),
+    ))
+}
+
+/// Build the directory tree for a set of changed files
+/// Extracted for testability
+fn build_diff_tree<'a>(files: impl Iterator<Item = &'a FileDiff>) -> DiffTree {
+    let mut root = diff_tree_node("", "", false);
+    for file in files {
+        let (additions, deletions) = file
+            .hunks
+            .iter()
+            .fold((0, 0), |(a, d), h| (a + h.additions, d + h.deletions));
+        let components: Vec<&str> = file.path.split('/').filter(|c| !c.is_empty()).collect();
+        insert_diff_tree_file(&mut root, &components, additions, deletions);
+    }
+    sort_diff_tree(&mut root);
+    root
+}
+
+fn diff_tree_node(name: &str, path: &str, is_file: bool) -> DiffTree {
+    DiffTree {
+        name: name.to_string(),
+        path: path.to_string(),
+        is_file,
+        additions: 0,
+        deletions: 0,
+        file_count: 0,
+        children: Vec::new(),
+    }
+}
+
+/// Add a file's counts along its path; returns whether the file was new to the tree
+fn insert_diff_tree_file(
+    node: &mut DiffTree,
+    components: &[&str],
+    additions: u32,
+    deletions: u32,
+) -> bool {
+    let Some((&name, rest)) = components.split_first() else {
+        return false;
+    };
+    node.additions += additions;
+    node.deletions += deletions;
+
+    let is_file = rest.is_empty();
+    let index = match node
+        .children
+        .iter()
+        .position(|c| c.name == name && c.is_file == is_file)
+    {
+        Some(index) => index,
+        None => {
+            let path = if node.path.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}/{}", node.path, name)
+            };
+            node.children.push(diff_tree_node(name, &path, is_file));
+            node.children.len() - 1
+        }
+    };
+
+    let child = &mut node.children[index];
+    let added = if is_file {
+        child.additions += additions;
+        child.deletions += deletions;
+        let added = child.file_count == 0;
+        child.file_count = 1;
+        added
+    } else {
+        insert_diff_tree_file(child, rest, additions, deletions)
+    };
+    if added {
+        node.file_count += 1;
+    }
+    added
+}
+
+/// Order each directory's children: subdirectories first, then files, by name
+fn sort_diff_tree(node: &mut DiffTree) {
+    node.children
+        .sort_by(|a, b| a.is_file.cmp(&b.is_file).then_with(|| a.name.cmp(&b.name)));
+    for child in &mut node.children {
+        sort_diff_tree(child);
+    }
+}
+
+/// Minimum line similarity for a deleted and an untracked file to count as a rename
+/// (matches git's default -M threshold of 50%)
+const RENAME_SIMILARITY_THRESHOLD: f64 = 0.5;
+
+/// Replace unstaged delete + untracked add pairs with similar content by a single rename
+fn pair_untracked_renames(worktree_path: &str, files: &mut Vec<FileDiff>) {
+    let deleted: Vec<(String, String)> = files
+        .iter()
+        .filter(|f| matches!(f.status, FileStatus::Deleted))
+        .filter_map(|f| {
+            // Deleted from the working tree, so the last known content is in the index
+            let content = run_git_read(worktree_path, &["show", &format!(":{}", f.path)]).ok()?;
+            Some((f.path.clone(), content))
+        })
+        .collect();
+    if deleted.is_empty() {
+        return;
+    }
+
+    // Untracked entries are the Added files without hunks
+    let added: Vec<(String, String)> = files
+        .iter()
+        .filter(|f| matches!(f.status, FileStatus::Added) && f.hunks.is_empty())
+        .filter_map(|f| {
+            let content = std::fs::read_to_string(Path::new(worktree_path).join(&f.path)).ok()?;
+            Some((f.path.clone(), content))
+        })
+        .collect();
+
+    for (old_path, new_path) in pair_by_similarity(&deleted, &added) {
+        files.retain(|f| !(f.path == old_path && matches!(f.status, FileStatus::Deleted)));
+        if let Some(file) = files.iter_mut().find(|f| f.path == new_path) {
+            file.status = FileStatus::Renamed;
+            file.old_path = Some(old_path);
+        }
+    }
+}
+
+/// Greedily pair each deleted file with the most similar unused added file
+/// Returns (old_path, new_path) pairs above RENAME_SIMILARITY_THRESHOLD
+/// Extracted for testability
+fn pair_by_similarity(
+    deleted: &[(String, String)],
+    added: &[(String, String)],
+) -> Vec<(String, String)> {
+    let mut used = vec![false; added.len()];
+    let mut pairs = Vec::new();
+
+    for (old_path, old_content) in deleted {
+        let best = added
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !used[*i])
+            .map(|(i, (_, new_content))| (i, content_similarity(old_content, new_content)))
+            .filter(|(_, score)| *score >= RENAME_SIMILARITY_THRESHOLD)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        if let Some((i, _)) = best {
+            used[i] = true;
+            pairs.push((old_path.clone(), added[i].0.clone()));
+        }
+    }
+
+    pairs
+}
+
+/// Line-based similarity in [0, 1]: twice the shared lines over the total line count
+/// Empty files are never similar, as with git's rename detection
+/// Extracted for testability
+fn content_similarity(a: &str, b: &str) -> f64 {
+    let total = a.lines().count() + b.lines().count();
+    if total == 0 {
+        return 0.0;
+    }
+
+    let mut remaining: std::collections::HashMap<&str, usize> = std::collections::HashMap::new();
+    for line in a.lines() {
+        *remaining.entry(line).or_default() += 1;
+    }
+    let mut shared = 0;
+    for line in b.lines() {
+        if let Some(count) = remaining.get_mut(line) {
+            if *count > 0 {
+                *count -= 1;
+                shared += 1;
+            }
+        }
+    }
+
+    (2 * shared) as f64 / total as f64
+}
+
+/// Build a WorkingDiff from the git invocations `scope` needs, run through `run`
+/// Extracted for testability (tests pass a recording runner)
+fn collect_working_diff<F>(
+    scope: DiffScope,
+    detect_renames: bool,
+    mut run: F,
+) -> Result<WorkingDiff, String>
+where
+    F: FnMut(&[&str]) -> Result<String, String>,
+{
+    let include_staged = matches!(scope, DiffScope::All | DiffScope::StagedOnly);
+    let include_unstaged = matches!(scope, DiffScope::All | DiffScope::UnstagedOnly);
+
+    // Get staged changes: git diff --cached
+    let staged_files = if include_staged {
+        let staged_args: &[&str] = if detect_renames {
+            &["diff", "--cached", "-U3", "-M"]
+        } else {
+            &["diff", "--cached", "-U3"]
+        };
+        parse_git_diff_output(&run(staged_args)?)
+    } else {
+        Vec::new()
+    };
+
+    let mut unstaged_files = Vec::new();
+    if include_unstaged {
+        // Get unstaged changes: git diff
+        unstaged_files = parse_git_diff_output(&run(&["diff", "-U3"])?);
+
+        // Get untracked files: git ls-files --others --exclude-standard
+        let untracked_text = run(&["ls-files", "--others", "--exclude-standard"])?;
+        for line in untracked_text.lines() {
+            if !line.is_empty() {
+                unstaged_files.push(FileDiff {
+                    path: line.to_string(),
+                    status: FileStatus::Added,
+                    old_path: None,
+                    hunks: Vec::new(), // Untracked files don't have hunks
+                    binary: false,
+                    old_mode: None,
+                    new_mode: None,
+                    chmod: None,
+                    old_blob_sha: None,
+                    new_blob_sha: None,
+                    mime_guess: None,
+                    stat_graph: None,
+                });
+            }
+        }
+    }
+
+    let stats = compute_diff_stats(&staged_files, &unstaged_files);
+
+    Ok(WorkingDiff {
+        staged_files,
+        unstaged_files,
+        stats,
+    })
+}
+
+/// Calculate total stats across the staged and unstaged file lists
+fn compute_diff_stats(staged_files: &[FileDiff], unstaged_files: &[FileDiff]) -> DiffStats {
+    let mut total_insertions = 0u32;
+    let mut total_deletions = 0u32;
+
+    for file in staged_files.iter().chain(unstaged_files.iter()) {
+        for hunk in &file.hunks {
+            for line in &hunk.lines {
+                match line.kind {
+                    '+' => total_insertions += 1,
+                    '-' => total_deletions += 1,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    DiffStats {
+        files_changed: (staged_files.len() + unstaged_files.len()) as u32,
+        insertions: total_insertions,
+        deletions: total_deletions,
+    }
+}
+
+/// Parse git diff output into Vec<FileDiff>
+fn parse_git_diff_output(diff_text: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = Vec::new();
+    let mut current_file: Option<FileDiff> = None;
+    let mut current_hunk: Option<DiffHunk> = None;
+    // Line numbers of the next old/new line within the current hunk
+    let mut old_lineno = 0u32;
+    let mut new_lineno = 0u32;
+    // Blob shas from the current file's "index" line, kept for binary files
+    let mut index_shas: (Option<String>, Option<String>) = (None, None);
+
+    for line in diff_text.lines() {
+        // New file header: diff --git a/path b/path
+        if line.starts_with("diff --git ") {
+            // Save previous file
+            if let Some(mut file) = current_file.take() {
+                if let Some(hunk) = current_hunk.take() {
+                    file.hunks.push(hunk);
+                }
+                files.push(file);
+            }
+
+            // Extract path from "diff --git a/path b/path"
+            let parts: Vec<&str> = line.split(" b/").collect();
+            let path = if parts.len() >= 2 {
+                parts[1].to_string()
+            } else {
+                // Fallback: extract from a/ part
+                line.split(" a/")
+                    .nth(1)
+                    .and_then(|s| s.split(' ').next())
+                    .unwrap_or("")
+                    .to_string()
+            };
+
+            current_file = Some(FileDiff {
+                path,
+                status: FileStatus::Modified, // Will be updated below
+                old_path: None,
+                hunks: Vec::new(),
+                binary: false,
+                old_mode: None,
+                new_mode: None,
+                chmod: None,
+                old_blob_sha: None,
+                new_blob_sha: None,
+                mime_guess: None,
+                stat_graph: None,
+            });
+            index_shas = (None, None);
+            continue;
+        }
+
+        // Blob shas: "index <old>..<new>[ <mode>]" (only in the header, before any hunk)
+        if current_hunk.is_none() {
+            if let Some(rest) = line.strip_prefix("index ") {
+                index_shas = parse_index_line(rest);
+                continue;
+            }
+        }
+
+        // Check for binary file
+        if line.starts_with("Binary files") {
+            if let Some(ref mut file) = current_file {
+                file.binary = true;
+                file.old_blob_sha = index_shas.0.take();
+                file.new_blob_sha = index_shas.1.take();
+                file.mime_guess = guess_mime_type(&file.path).map(|m| m.to_string());
+            }
+            continue;
+        }
+
+        // New file indicator
+        if line.starts_with("new file mode") {
+            if let Some(ref mut file) = current_file {
+                file.status = FileStatus::Added;
+            }
+            continue;
+        }
+
+        // Deleted file indicator
+        if line.starts_with("deleted file mode") {
+            if let Some(ref mut file) = current_file {
+                file.status = FileStatus::Deleted;
+            }
+            continue;
+        }
+
+        // Mode change: "old mode 100644" followed by "new mode 100755"
+        if let Some(mode) = line.strip_prefix("old mode ") {
+            if let Some(ref mut file) = current_file {
+                file.old_mode = Some(mode.to_string());
+            }
+            continue;
+        }
+        if let Some(mode) = line.strip_prefix("new mode ") {
+            if let Some(ref mut file) = current_file {
+                file.new_mode = Some(mode.to_string());
+                file.chmod = file
+                    .old_mode
+                    .as_deref()
+                    .map(|old_mode| chmod_change(old_mode, mode));
+            }
+            continue;
+        }
+
+        // Rename detection: rename from / rename to
+        if line.starts_with("rename from ") {
+            if let Some(ref mut file) = current_file {
+                file.old_path = Some(line.trim_start_matches("rename from ").to_string());
+                file.status = FileStatus::Renamed;
+            }
+            continue;
+        }
+
+        // Hunk header: @@ -old_start,old_lines +new_start,new_lines @@
+        if line.starts_with("@@ ") {
+            // Save previous hunk
+            if let Some(ref mut file) = current_file {
+                if let Some(hunk) = current_hunk.take() {
+                    file.hunks.push(hunk);
+                }
+            }
+
+            // Parse hunk header
+            if let Some((old_start, old_lines, new_start, new_lines)) = parse_hunk_header(line) {
+                old_lineno = old_start;
+                new_lineno = new_start;
+                current_hunk = Some(DiffHunk {
+                    old_start,
+                    old_lines,
+                    new_start,
+                    new_lines,
+                    header: line.to_string(),
+                    lines: Vec::new(),
+                    additions: 0,
+                    deletions: 0,
+                });
+            }
+            continue;
+        }
+
+        // Diff lines: +, -, or space (context)
+        if let Some(ref mut hunk) = current_hunk {
+            let first_char = line.chars().next();
+            match first_char {
+                Some('+') => {
+                    hunk.lines.push(DiffLine {
+                        kind: '+',
+                        content: line[1..].to_string(),
+                        old_lineno: None,
+                        new_lineno: Some(new_lineno),
+                    });
+                    hunk.additions += 1;
+                    new_lineno += 1;
+                }
+                Some('-') => {
+                    hunk.lines.push(DiffLine {
+                        kind: '-',
+                        content: line[1..].to_string(),
+                        old_lineno: Some(old_lineno),
+                        new_lineno: None,
+                    });
+                    hunk.deletions += 1;
+                    old_lineno += 1;
+                }
+                Some(' ') => {
+                    hunk.lines.push(DiffLine {
+                        kind: ' ',
+                        content: line[1..].to_string(),
+                        old_lineno: Some(old_lineno),
+                        new_lineno: Some(new_lineno),
+                    });
+                    old_lineno += 1;
+                    new_lineno += 1;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Save last file
+    if let Some(mut file) = current_file {
+        if let Some(hunk) = current_hunk {
+            file.hunks.push(hunk);
+        }
+        files.push(file);
+    }
+
+    files
+}
+
+/// Read lines of a file at a revision to expand context around a commit's hunks
+/// `start_line` is 1-based in the file at `rev` (the new side); old-side numbers are
+/// mapped through the hunks of `rev`'s own change to the file
+pub fn get_diff_context(
+    worktree_path: &str,
+    rev: &str,
+    file_path: &str,
+    start_line: u32,
+    line_count: u32,
+) -> Result<Vec<DiffLine>, String> {
+    let content = run_git(worktree_path, &["show", &format!("{}:{}", rev, file_path)])?;
+
+    // Hunks of rev's change to the file; a root commit has no parent and no old side
+    let hunks = match run_git(
+        worktree_path,
+        &["rev-parse", "--verify", "-q", &format!("{}^", rev)],
+    ) {
+        Ok(_) => {
+            let diff = run_git(
+                worktree_path,
+                &["diff", "-U0", &format!("{}^", rev), rev, "--", file_path],
+            )?;
+            Some(
+                parse_git_diff_output(&diff)
+                    .into_iter()
+                    .flat_map(|f| f.hunks)
+                    .collect::<Vec<_>>(),
+            )
+        }
+        Err(_) => None,
+    };
+
+    Ok(build_context_lines(
+        &content,
+        start_line,
+        line_count,
+        hunks.as_deref(),
+    ))
+}
+
+/// Slice `line_count` lines starting at 1-based `start_line` into context DiffLines
+/// Old-side numbers shift by the net lines added in hunks that end before each line;
+/// lines inside a changed region (or with no old side at all) get no old number
+/// Extracted for testability
+fn build_context_lines(
+    content: &str,
+    start_line: u32,
+    line_count: u32,
+    hunks: Option<&[DiffHunk]>,
+) -> Vec<DiffLine> {
+    let start = start_line.max(1);
+
+    content
+        .lines()
+        .enumerate()
+        .skip((start - 1) as usize)
+        .take(line_count as usize)
+        .map(|(idx, text)| {
+            let new_lineno = idx as u32 + 1;
+            DiffLine {
+                kind: ' ',
+                content: text.to_string(),
+                old_lineno: hunks.and_then(|h| map_new_to_old_line(new_lineno, h)),
+                new_lineno: Some(new_lineno),
+            }
+        })
+        .collect()
+}
+
+/// Map a new-side line number outside any hunk to its old-side line number
+fn map_new_to_old_line(new_lineno: u32, hunks: &[DiffHunk]) -> Option<u32> {
+    let mut offset: i64 = 0;
+
+    for hunk in hunks {
+        // With -U0 a pure deletion has new_lines = 0 and new_start = the line before it
+        let new_end = if hunk.new_lines == 0 {
+            hunk.new_start
+        } else {
+            hunk.new_start + hunk.new_lines - 1
+        };
+
+        if hunk.new_lines > 0 && new_lineno >= hunk.new_start && new_lineno <= new_end {
+            return None;
+        }
+        if new_lineno > new_end {
+            offset += hunk.old_lines as i64 - hunk.new_lines as i64;
+        }
+    }
+
+    u32::try_from(new_lineno as i64 + offset).ok()
+}
+
+/// Parse hunk header like "@@ -1,5 +1,7 @@" into (old_start, old_lines, new_start, new_lines)
+fn parse_hunk_header(line: &str) -> Option<(u32, u32, u32, u32)> {
+    // Format: @@ -old_start,old_lines +new_start,new_lines @@
+    let line = line.trim_start_matches("@@ ");
+    let parts: Vec<&str> = line.split(" @@").next()?.split(' ').collect();
+
+    if parts.len() < 2 {
+        return None;
+    }
+
+    let old_part = parts[0].trim_start_matches('-');
+    let new_part = parts[1].trim_start_matches('+');
+
+    let (old_start, old_lines) = parse_range(old_part)?;
+    let (new_start, new_lines) = parse_range(new_part)?;
+
+    Some((old_start, old_lines, new_start, new_lines))
+}
+
+/// Parse "start,lines" or just "start" (implies lines=1)
+fn parse_range(s: &str) -> Option<(u32, u32)> {
+    let parts: Vec<&str> = s.split(',').collect();
+    let start = parts[0].parse().ok()?;
+    let lines = if parts.len() > 1 {
+        parts[1].parse().ok()?
+    } else {
+        1
+    };
+    Some((start, lines))
+}
+
+/// Export a commit (or a series ending at commit_sha) as mailbox-format patch text
+/// When base is given, every commit in base..commit_sha is included
+pub fn format_patch(
+    worktree_path: &str,
+    commit_sha: &str,
+    base: Option<&str>,
+) -> Result<String, String> {
+    let args = build_format_patch_args(commit_sha, base);
+    let arg_refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    run_git(worktree_path, &arg_refs)
+}
+
+/// Build the argument list for "git format-patch"
+/// Extracted for testability
+fn build_format_patch_args(commit_sha: &str, base: Option<&str>) -> Vec<String> {
+    let mut args = vec!["format-patch".to_string(), "--stdout".to_string()];
+
+    match base {
+        Some(base) => args.push(format!("{}..{}", base, commit_sha)),
+        None => {
+            args.push("-1".to_string());
+            args.push(commit_sha.to_string());
+        }
+    }
+
+    args
+}
+
+/// Object name of the empty tree, used as the diff base when HEAD is unborn
+const EMPTY_TREE_SHA: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+/// Stage only the unstaged changes on the given new-file line ranges (inclusive)
+/// A minimal patch is built from the current unstaged diff and applied to the index
+pub fn stage_lines(
+    worktree_path: &str,
+    file_path: &str,
+    ranges: Vec<(u32, u32)>,
+) -> Result<(), String> {
+    let diff_output = run_git(worktree_path, &["diff", "-U3", "--", file_path])?;
+    // The diff parser drops "\ No newline at end of file", so a rebuilt patch would be wrong
+    if diff_output.contains("\n\\ ") {
+        return Err(format!(
+            "Line staging isn't supported for {} (no newline at end of file)",
+            file_path
+        ));
+    }
+
+    let file = select_file_diff(parse_git_diff_output(&diff_output), file_path)
+        .ok_or_else(|| format!("File {} has no unstaged changes", file_path))?;
+    if file.binary || !matches!(file.status, FileStatus::Modified) {
+        return Err(format!(
+            "Only modified text files can be staged by line: {}",
+            file_path
+        ));
+    }
+
+    let patch = build_line_range_patch(&file, &ranges)
+        .ok_or_else(|| "No changes in the selected lines".to_string())?;
+
+    let args = ["apply", "--cached", "-"];
+    let output = run_git_with_stdin(worktree_path, &args, &patch)?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(())
+}
+
+/// Build a patch (against the index) containing only the changes on `ranges` of new-file
+/// line numbers. A deletion counts as selected when the new-file position it sits at is.
+/// Unselected additions are dropped and unselected deletions become context, since both
+/// are absent from / still present in the index. Returns None if nothing is selected.
+/// Extracted for testability
+fn build_line_range_patch(file: &FileDiff, ranges: &[(u32, u32)]) -> Option<String> {
+    let selected = |line_no: u32| {
+        ranges
+            .iter()
+            .any(|&(start, end)| start <= line_no && line_no <= end)
+    };
+
+    let mut body = String::new();
+    // Net lines added by hunks emitted so far, to place later hunks on the new side
+    let mut offset: i64 = 0;
+
+    for hunk in &file.hunks {
+        let mut lines = Vec::new();
+        let mut old_count = 0u32;
+        let mut new_count = 0u32;
+        let mut changed = false;
+        let mut new_pos = hunk.new_start;
+
+        for line in &hunk.lines {
+            match line.kind {
+                '+' => {
+                    let line_no = line.new_lineno.unwrap_or(new_pos);
+                    new_pos = line_no + 1;
+                    if selected(line_no) {
+                        lines.push(format!("+{}", line.content));
+                        new_count += 1;
+                        changed = true;
+                    }
+                }
+                '-' => {
+                    if selected(new_pos) {
+                        lines.push(format!("-{}", line.content));
+                        changed = true;
+                    } else {
+                        lines.push(format!(" {}", line.content));
+                        new_count += 1;
+                    }
+                    old_count += 1;
+                }
+                _ => {
+                    new_pos = line.new_lineno.unwrap_or(new_pos) + 1;
+                    lines.push(format!(" {}", line.content));
+                    old_count += 1;
+                    new_count += 1;
+                }
+            }
+        }
+
+        if !changed {
+            continue;
+        }
+
+        let new_start = (hunk.old_start as i64 + offset).max(0);
+        offset += new_count as i64 - old_count as i64;
+        body.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            hunk.old_start, old_count, new_start, new_count
+        ));
+        for line in lines {
+            body.push_str(&line);
+            body.push('\n');
+        }
+    }
+
+    if body.is_empty() {
+        return None;
+    }
+
+    Some(format!(
+        "diff --git a/{path} b/{path}\n--- a/{path}\n+++ b/{path}\n{body}",
+        path = file.path,
+        body = body
+    ))
+}
+
+/// Export all uncommitted changes (staged and unstaged) as one applyable patch
+/// With include_untracked, untracked files are temporarily marked intent-to-add so they
+/// appear in the diff; those index entries are removed again afterwards
+pub fn export_working_patch(
+    worktree_path: &str,
+    include_untracked: bool,
+) -> Result<String, String> {
+    // Diffing the working tree against HEAD covers staged and unstaged changes in a
+    // single patch; two separate diffs of the same file wouldn't apply in sequence
+    let base = if get_unborn_branch(worktree_path).is_some() {
+        EMPTY_TREE_SHA
+    } else {
+        "HEAD"
+    };
+
+    let untracked: Vec<String> = if include_untracked {
+        run_git(
+            worktree_path,
+            &["ls-files", "--others", "--exclude-standard", "-z"],
+        )?
+        .split('\0')
+        .filter(|p| !p.is_empty())
+        .map(|p| p.to_string())
+        .collect()
+    } else {
+        Vec::new()
+    };
+
+    if untracked.is_empty() {
+        return run_git(worktree_path, &["diff", "--binary", base]);
+    }
+
+    let mut add_args = vec!["add", "-N", "--"];
+    add_args.extend(untracked.iter().map(|p| p.as_str()));
+    run_git(worktree_path, &add_args)?;
+
+    let patch = run_git(worktree_path, &["diff", "--binary", base]);
+
+    // Always drop the intent-to-add entries, even if the diff failed
+    let mut reset_args = vec!["rm", "--cached", "-q", "--"];
+    reset_args.extend(untracked.iter().map(|p| p.as_str()));
+    let reset = run_git(worktree_path, &reset_args);
+
+    let patch = patch?;
+    reset.map_err(|e| format!("Failed to remove intent-to-add entries: {}", e))?;
+    Ok(patch)
+}
+
+/// Copy uncommitted changes from one worktree into another, e.g. after starting work in
+/// the wrong one. Changes land unstaged in the destination; with `discard_source` the
+/// source is then reset (and untracked files cleaned when `include_untracked`)
+/// Nothing is touched unless the whole patch applies cleanly in the destination
+pub fn transfer_changes(
+    from_worktree: &str,
+    to_worktree: &str,
+    include_untracked: bool,
+    discard_source: bool,
+) -> Result<(), String> {
+    let patch = export_working_patch(from_worktree, include_untracked)?;
+    if patch.trim().is_empty() {
+        return Err(format!("No uncommitted changes in {}", from_worktree));
+    }
+
+    let check = run_git_with_stdin(to_worktree, &["apply", "--check", "-"], &patch)?;
+    if !check.status.success() {
+        return Err(format!(
+            "Changes conflict with edits in {}: {}",
+            to_worktree,
+            String::from_utf8_lossy(&check.stderr).trim()
+        ));
+    }
+
+    let args = ["apply", "-"];
+    let applied = run_git_with_stdin(to_worktree, &args, &patch)?;
+    if !applied.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&applied.stderr)
+        ));
+    }
+
+    if discard_source {
+        run_git(from_worktree, &["reset", "-q", "--hard", "HEAD"])?;
+        if include_untracked {
+            run_git(from_worktree, &["clean", "-f", "-d", "-q"])?;
+        }
+    }
+    Ok(())
+}
+
+/// Apply mailbox-format patch text with "git am", reading the patch from stdin
+/// A conflicted apply is reported via AmResult.conflicts rather than as an error
+pub fn apply_mailbox(
+    worktree_path: &str,
+    patch_text: String,
+    three_way: bool,
+) -> Result<AmResult, String> {
+    let head_before = run_git(worktree_path, &["rev-parse", "HEAD"])
+        .ok()
+        .map(|s| s.trim().to_string());
+
+    let mut args = vec!["am"];
+    if three_way {
+        args.push("--3way");
+    }
+    args.push("-");
+
+    let output = run_git_with_stdin(worktree_path, &args, &patch_text)?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let message = format!("{}{}", stdout, stderr).trim().to_string();
+
+    // Count the commits that landed on top of the previous HEAD
+    let applied = head_before
+        .and_then(|before| {
+            run_git(
+                worktree_path,
+                &["rev-list", "--count", &format!("{}..HEAD", before)],
+            )
+            .ok()
+        })
+        .and_then(|s| s.trim().parse::<u32>().ok())
+        .unwrap_or(0);
+
+    let conflicts = !output.status.success() && is_am_in_progress(worktree_path);
+
+    if !output.status.success() && !conflicts {
+        return Err(format!("git am failed: {}", message));
+    }
+
+    Ok(AmResult {
+        applied,
+        conflicts,
+        message,
+    })
+}
+
+/// Abort an in-progress "git am" session and restore the original branch
+pub fn am_abort(worktree_path: &str) -> Result<(), String> {
+    run_git(worktree_path, &["am", "--abort"])?;
+    Ok(())
+}
+
+/// Check whether a "git am" session is stopped waiting for conflict resolution
+fn is_am_in_progress(worktree_path: &str) -> bool {
+    run_git(worktree_path, &["rev-parse", "--git-path", "rebase-apply"])
+        .map(|p| {
+            let p = PathBuf::from(p.trim());
+            let p = if p.is_absolute() {
+                p
+            } else {
+                PathBuf::from(worktree_path).join(p)
+            };
+            p.join("applying").exists()
+        })
+        .unwrap_or(false)
+}
+
+/// Start a bisect session; mark commits with bisect_good/bisect_bad
+pub fn bisect_start(worktree_path: &str) -> Result<BisectState, String> {
+    let output = run_git(worktree_path, &["bisect", "start"])?;
+    Ok(parse_bisect_output(&output))
+}
+
+/// Mark a commit as good and report the next commit to test
+pub fn bisect_good(worktree_path: &str, sha: &str) -> Result<BisectState, String> {
+    let output = run_git(worktree_path, &["bisect", "good", sha])?;
+    Ok(parse_bisect_output(&output))
+}
+
+/// Mark a commit as bad and report the next commit to test
+pub fn bisect_bad(worktree_path: &str, sha: &str) -> Result<BisectState, String> {
+    let output = run_git(worktree_path, &["bisect", "bad", sha])?;
+    Ok(parse_bisect_output(&output))
+}
+
+/// End the bisect session and return to the original HEAD
+pub fn bisect_reset(worktree_path: &str) -> Result<(), String> {
+    run_git(worktree_path, &["bisect", "reset"])?;
+    Ok(())
+}
+
+/// Parse "git bisect" output into a BisectState
+/// Progress looks like:
+///   Bisecting: 3 revisions left to test after this (roughly 2 steps)
+///   [<sha>] <subject>
+/// and the session ends with "<sha> is the first bad commit"
+/// Extracted for testability
+fn parse_bisect_output(output: &str) -> BisectState {
+    let mut state = BisectState {
+        remaining_steps: None,
+        current_sha: None,
+        finished: false,
+        first_bad: None,
+    };
+
+    for line in output.lines() {
+        if let Some(progress) = line.strip_prefix("Bisecting: ") {
+            // Prefer git's step estimate, falling back to the revision count
+            let steps = progress
+                .split_once("(roughly ")
+                .and_then(|(_, rest)| rest.split(' ').next());
+            let revisions = progress.split(' ').next();
+            state.remaining_steps = steps.or(revisions).and_then(|n| n.parse().ok());
+        } else if let Some(rest) = line.strip_prefix('[') {
+            if let Some((sha, _)) = rest.split_once(']') {
+                state.current_sha = Some(sha.to_string());
+            }
+        } else if let Some(sha) = line.strip_suffix(" is the first bad commit") {
+            state.finished = true;
+            state.first_bad = Some(sha.to_string());
+            state.remaining_steps = Some(0);
+            state.current_sha = None;
+        }
+    }
+
+    state
+}
+
+/// Read the HEAD reflog (most recent first) so users can recover from resets
+pub fn get_reflog(worktree_path: &str, limit: usize) -> Result<Vec<ReflogEntry>, String> {
+    // %gd renders as HEAD@{<unix time>} with --date=unix; entries are newest first
+    // Fetch one extra entry so the last returned entry still knows its old sha
+    let output = run_git(
+        worktree_path,
+        &[
+            "reflog",
+            "--date=unix",
+            "--format=%gd%x1f%H%x1f%gs%x1e",
+            &format!("-n{}", limit + 1),
+        ],
+    )?;
+
+    let mut entries = parse_reflog(&output);
+    entries.truncate(limit);
+    Ok(entries)
+}
+
+/// Read one branch's reflog (most recent first), e.g. to recover after a reset of that
+/// branch. A branch whose reflog is missing or empty yields no entries
+pub fn get_branch_reflog(
+    repo_path: &str,
+    branch: &str,
+    limit: usize,
+) -> Result<Vec<ReflogEntry>, String> {
+    let branch = branch.strip_prefix("refs/heads/").unwrap_or(branch);
+    if branch.is_empty() || branch.starts_with('-') {
+        return Err(format!("Invalid branch name: {}", branch));
+    }
+    let full_ref = format!("refs/heads/{}", branch);
+    if run_git(repo_path, &["rev-parse", "--verify", "-q", &full_ref]).is_err() {
+        return Err(format!("Branch {} does not exist", branch));
+    }
+
+    let args = build_branch_reflog_args(&full_ref, limit);
+    let arg_refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    let output = run_git(repo_path, &arg_refs)?;
+
+    let mut entries = parse_reflog(&output);
+    entries.truncate(limit);
+    Ok(entries)
+}
+
+/// Build the argument list for a branch-qualified "git reflog show"
+/// Like get_reflog, one extra entry is fetched so the last one knows its old sha
+/// Extracted for testability
+fn build_branch_reflog_args(full_ref: &str, limit: usize) -> Vec<String> {
+    vec![
+        "reflog".to_string(),
+        "show".to_string(),
+        "--date=unix".to_string(),
+        "--format=%gd%x1f%H%x1f%gs%x1e".to_string(),
+        format!("-n{}", limit + 1),
+        full_ref.to_string(),
+        "--".to_string(),
+    ]
+}
+
+/// Parse "git reflog --date=unix --format=%gd%x1f%H%x1f%gs%x1e" output into Vec<ReflogEntry>
+/// Extracted for testability
+fn parse_reflog(output: &str) -> Vec<ReflogEntry> {
+    let mut entries: Vec<ReflogEntry> = Vec::new();
+
+    for record in output.split('\x1e') {
+        let record = record.trim();
+        if record.is_empty() {
+            continue;
+        }
+
+        let fields: Vec<&str> = record.split('\x1f').collect();
+        if fields.len() < 3 {
+            continue;
+        }
+
+        // "HEAD@{1700000000}" -> ref name "HEAD", timestamp 1700000000
+        let (ref_name, timestamp) = match fields[0].split_once("@{") {
+            Some((name, rest)) => (
+                name.to_string(),
+                rest.trim_end_matches('}').parse::<i64>().unwrap_or(0),
+            ),
+            None => (fields[0].to_string(), 0),
+        };
+
+        // Subject looks like "reset: moving to HEAD~1" or "commit (initial): Initial commit"
+        let (action, message) = match fields[2].split_once(": ") {
+            Some((action, message)) => (action.to_string(), message.to_string()),
+            None => (fields[2].to_string(), String::new()),
+        };
+
+        entries.push(ReflogEntry {
+            selector: format!("{}@{{{}}}", ref_name, entries.len()),
+            old_sha: String::new(),
+            new_sha: fields[1].to_string(),
+            action,
+            message,
+            timestamp,
+        });
+    }
+
+    // Each entry moved the ref from the value recorded by the next (older) entry
+    for i in 0..entries.len() {
+        entries[i].old_sha = match entries.get(i + 1) {
+            Some(older) => older.new_sha.clone(),
+            None => "0".repeat(40),
+        };
+    }
+
+    entries
+}
+
+/// Branches recently checked out in this worktree, most recent first, without duplicates
+pub fn recent_branches(worktree_path: &str, limit: usize) -> Result<Vec<String>, String> {
+    let output = run_git(worktree_path, &["reflog", "--format=%gs"])?;
+    let local_branches: Vec<String> = run_git(
+        worktree_path,
+        &["for-each-ref", "--format=%(refname:short)", "refs/heads"],
+    )?
+    .lines()
+    .map(|l| l.trim().to_string())
+    .filter(|l| !l.is_empty())
+    .collect();
+    Ok(parse_recent_branches(&output, &local_branches, limit))
+}
+
+/// Collect destinations of "checkout: moving from X to Y" reflog subjects (newest first)
+/// Only existing local branches are kept: detached checkouts of a sha, tag, or
+/// remote-tracking ref (and since-deleted branches) are skipped
+/// Extracted for testability
+fn parse_recent_branches(output: &str, local_branches: &[String], limit: usize) -> Vec<String> {
+    let mut branches: Vec<String> = Vec::new();
+    for line in output.lines() {
+        if branches.len() >= limit {
+            break;
+        }
+        let Some((_, to)) = line
+            .strip_prefix("checkout: moving from ")
+            .and_then(|rest| rest.rsplit_once(" to "))
+        else {
+            continue;
+        };
+        let is_branch = local_branches.iter().any(|b| b == to);
+        if is_branch && !branches.iter().any(|b| b == to) {
+            branches.push(to.to_string());
+        }
+    }
+    branches
+}
+
+/// Stage all tracked modifications and deletions and commit them in one step
+/// Matches "git commit -a": untracked files are NOT included and must be added first
+pub fn commit_all(worktree_path: &str, message: String) -> Result<CommitInfo, String> {
+    let args = build_commit_all_args(&message);
+    let arg_refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    run_git(worktree_path, &arg_refs)?;
+
+    get_commit_info(worktree_path, "HEAD")
+}
+
+/// Build the argument list for "git commit -a"
+/// Extracted for testability
+fn build_commit_all_args(message: &str) -> Vec<String> {
+    vec![
+        "commit".to_string(),
+        "-q".to_string(),
+        "-a".to_string(),
+        "-m".to_string(),
+        message.to_string(),
+    ]
+}
+
+/// Undo the last commit but keep its changes staged ("git reset --soft HEAD~1")
+pub fn uncommit_keep_changes(worktree_path: &str) -> Result<WorktreeStatus, String> {
+    if run_git(worktree_path, &["rev-parse", "--verify", "-q", "HEAD~1"]).is_err() {
+        return Err("Cannot uncommit: HEAD has no parent commit".to_string());
+    }
+
+    run_git(worktree_path, &build_uncommit_args())?;
+    get_worktree_status(worktree_path, UntrackedMode::default(), None)
+}
+
+/// Build the argument list for undoing the last commit while keeping its changes
+/// Extracted for testability
+fn build_uncommit_args() -> [&'static str; 3] {
+    ["reset", "--soft", "HEAD~1"]
+}
+
+/// Squash the last `count` commits into one with `message`
+/// Soft-resets to HEAD~count and commits the still-staged result; the message goes
+/// through stdin so it's never parsed as an option. HEAD is restored if the commit fails
+pub fn squash_last(worktree_path: &str, count: u32, message: String) -> Result<CommitInfo, String> {
+    if count < 2 {
+        return Err(format!("Squashing needs at least 2 commits, got {}", count));
+    }
+
+    let base = format!("HEAD~{}", count);
+    if run_git(worktree_path, &["rev-parse", "--verify", "-q", &base]).is_err() {
+        return Err(format!(
+            "Cannot squash {} commits: {} does not exist",
+            count, base
+        ));
+    }
+    let original_head = run_git(worktree_path, &["rev-parse", "HEAD"])?;
+    let original_head = original_head.trim();
+
+    let reset_args = build_squash_reset_args(count);
+    let arg_refs: Vec<&str> = reset_args.iter().map(|s| s.as_str()).collect();
+    run_git(worktree_path, &arg_refs)?;
+
+    let commit_args = ["commit", "-q", "-F", "-"];
+    let committed = run_git_with_stdin(worktree_path, &commit_args, &message)?;
+    if !committed.status.success() {
+        // Put the original commits back rather than leave their changes dangling in the index
+        run_git(worktree_path, &["reset", "--soft", original_head])?;
+        return Err(format!(
+            "git {} failed: {}",
+            commit_args.join(" "),
+            String::from_utf8_lossy(&committed.stderr)
+        ));
+    }
+
+    get_commit_info(worktree_path, "HEAD")
+}
+
+/// Build the soft reset that un-commits the last `count` commits, keeping them staged
+/// Extracted for testability
+fn build_squash_reset_args(count: u32) -> Vec<String> {
+    vec![
+        "reset".to_string(),
+        "--soft".to_string(),
+        format!("HEAD~{}", count),
+    ]
+}
+
+/// Restore a file (e.g. one deleted by accident) to its content at a given commit
+/// The restored content is written to both the index and the working tree
+pub fn restore_file_from_commit(
+    worktree_path: &str,
+    commit_sha: &str,
+    file_path: &str,
+) -> Result<(), String> {
+    let object = format!("{}:{}", commit_sha, file_path);
+    if run_git(worktree_path, &["cat-file", "-e", &object]).is_err() {
+        return Err(format!(
+            "File {} does not exist in commit {}",
+            file_path, commit_sha
+        ));
+    }
+
+    run_git(
+        worktree_path,
+        &build_restore_file_args(commit_sha, file_path),
+    )?;
+    Ok(())
+}
+
+/// Build the argument list for checking out a single file from a commit
+/// Extracted for testability
+fn build_restore_file_args<'a>(commit_sha: &'a str, file_path: &'a str) -> [&'a str; 4] {
+    ["checkout", commit_sha, "--", file_path]
+}
+
+/// Set or clear the assume-unchanged bit on files (e.g. local config edits to ignore)
+pub fn set_assume_unchanged(
+    worktree_path: &str,
+    paths: Vec<String>,
+    enabled: bool,
+) -> Result<(), String> {
+    if paths.is_empty() {
+        return Err("No files to update".to_string());
+    }
+
+    let flag = if enabled {
+        "--assume-unchanged"
+    } else {
+        "--no-assume-unchanged"
+    };
+    let mut args = vec!["update-index", flag, "--"];
+    args.extend(paths.iter().map(|p| p.as_str()));
+    run_git(worktree_path, &args)?;
+    Ok(())
+}
+
+/// List files marked assume-unchanged
+pub fn list_assume_unchanged(worktree_path: &str) -> Result<Vec<String>, String> {
+    let output = run_git(worktree_path, &["ls-files", "-v", "-z"])?;
+    Ok(parse_assume_unchanged(&output))
+}
+
+/// Pick assume-unchanged entries from "git ls-files -v -z" output
+/// Each entry is "<tag> <path>"; the tag letter is lowercase (e.g. "h") when assume-unchanged
+/// Extracted for testability
+fn parse_assume_unchanged(output: &str) -> Vec<String> {
+    output
+        .split('\0')
+        .filter_map(|entry| entry.split_once(' '))
+        .filter(|(tag, _)| !tag.is_empty() && tag.chars().all(|c| c.is_ascii_lowercase()))
+        .map(|(_, path)| path.to_string())
+        .collect()
+}
+
+/// Stage conflicted files to mark them resolved, returning the refreshed status
+/// Files that still contain conflict markers are staged anyway, with a warning each
+pub fn mark_resolved(
+    worktree_path: &str,
+    paths: Vec<String>,
+) -> Result<MarkResolvedResult, String> {
+    if paths.is_empty() {
+        return Err("No files to mark as resolved".to_string());
+    }
+
+    let warnings: Vec<String> = paths
+        .iter()
+        .filter(|path| {
+            std::fs::read(Path::new(worktree_path).join(path))
+                .map(|content| has_conflict_markers(&String::from_utf8_lossy(&content)))
+                .unwrap_or(false)
+        })
+        .map(|path| format!("{} still contains conflict markers", path))
+        .collect();
+
+    let mut args = vec!["add", "--"];
+    args.extend(paths.iter().map(|p| p.as_str()));
+    run_git(worktree_path, &args)?;
+
+    Ok(MarkResolvedResult {
+        status: get_worktree_status(worktree_path, UntrackedMode::default(), None)?,
+        warnings,
+    })
+}
+
+/// List conflicted files, noting which use the diff3 conflict style
+/// A "|||||||" section means the base text is inline, so a three-way view can show it
+pub fn list_conflicts(worktree_path: &str) -> Result<Vec<ConflictEntry>, String> {
+    let output = run_git(worktree_path, &["ls-files", "-u", "-z"])?;
+
+    Ok(parse_unmerged_paths(&output)
+        .into_iter()
+        .map(|path| {
+            let has_base_section = std::fs::read(Path::new(worktree_path).join(&path))
+                .map(|content| has_diff3_base_section(&String::from_utf8_lossy(&content)))
+                .unwrap_or(false);
+            ConflictEntry {
+                path,
+                has_base_section,
+            }
+        })
+        .collect())
+}
+
+/// Read the base, ours, and theirs versions of a conflicted file from the index
+/// A stage is None when that side doesn't have the file (e.g. no base in add/add)
+pub fn get_conflict_versions(
+    worktree_path: &str,
+    file_path: &str,
+) -> Result<ConflictVersions, String> {
+    let output = run_git(worktree_path, &["ls-files", "-u", "-z", "--", file_path])?;
+    let [base, ours, theirs] = parse_unmerged_stages(&output);
+    if base.is_none() && ours.is_none() && theirs.is_none() {
+        return Err(format!("{} is not conflicted", file_path));
+    }
+
+    let read = |sha: Option<String>| -> Result<Option<String>, String> {
+        sha.map(|sha| run_git(worktree_path, &["cat-file", "blob", &sha]))
+            .transpose()
+    };
+
+    Ok(ConflictVersions {
+        base: read(base)?,
+        ours: read(ours)?,
+        theirs: read(theirs)?,
+    })
+}
+
+/// Parse "git ls-files -u -z" entries ("<mode> <sha> <stage>\t<path>") into the blob
+/// shas for stages 1 (base), 2 (ours), and 3 (theirs)
+/// Extracted for testability
+fn parse_unmerged_stages(output: &str) -> [Option<String>; 3] {
+    let mut stages: [Option<String>; 3] = [None, None, None];
+    for entry in output.split('\0') {
+        let Some((info, _path)) = entry.split_once('\t') else {
+            continue;
+        };
+        let fields: Vec<&str> = info.split(' ').collect();
+        if fields.len() != 3 {
+            continue;
+        }
+        if let Ok(stage @ 1..=3) = fields[2].parse::<usize>() {
+            stages[stage - 1] = Some(fields[1].to_string());
+        }
+    }
+    stages
+}
+
+/// Unique paths from "git ls-files -u -z" output, in index order (one entry per stage)
+/// Extracted for testability
+fn parse_unmerged_paths(output: &str) -> Vec<String> {
+    let mut paths: Vec<String> = Vec::new();
+    for entry in output.split('\0') {
+        if let Some((_info, path)) = entry.split_once('\t') {
+            if paths.last().map(|p| p.as_str()) != Some(path) {
+                paths.push(path.to_string());
+            }
+        }
+    }
+    paths
+}
+
+/// Check whether conflict markers include a diff3/zdiff3 base section ("|||||||")
+/// Extracted for testability
+fn has_diff3_base_section(content: &str) -> bool {
+    content
+        .lines()
+        .any(|line| line.starts_with("||||||| ") || line == "|||||||")
+}
+
+/// Check whether file content still has unresolved conflict markers
+/// Markers must start a line; "=======" alone is too common (e.g. Markdown) to count
+/// Extracted for testability
+fn has_conflict_markers(content: &str) -> bool {
+    content.lines().any(|line| {
+        line.starts_with("<<<<<<< ")
+            || line == "<<<<<<<"
+            || line.starts_with(">>>>>>> ")
+            || line == ">>>>>>>"
+    })
+}
+
+/// Create a new worktree
+/// Non-fatal stderr output from "git worktree add" is returned as warnings
+pub fn create_worktree(
+    repo_path: &str,
+    options: CreateWorktreeOptions,
+) -> Result<CreateWorktreeResult, String> {
+    validate_create_options(&options)?;
+
+    // "git worktree add --orphan" only exists in git 2.42+; older versions need a fallback
+    let native_orphan = options.orphan
+        && run_git(repo_path, &["version"])
+            .map(|v| supports_worktree_orphan(&v))
+            .unwrap_or(false);
+
+    let args = build_create_worktree_args(&options, native_orphan);
+    let arg_refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    let (_, stderr) = run_git_capture(repo_path, &arg_refs)?;
+    let warnings = parse_worktree_add_warnings(&stderr);
+    let mut prepared = parse_prepared_worktree(&stderr);
+
+    if options.orphan && !native_orphan {
+        // Fallback: the worktree was added detached; switch it to an empty orphan branch
+        let branch = options.new_branch.as_deref().unwrap_or_default();
+        run_git(&options.path, &["checkout", "-q", "--orphan", branch])?;
+        run_git(&options.path, &["rm", "-rfq", "--ignore-unmatch", "."])?;
+        prepared = Some(PreparedWorktree::NewBranch(branch.to_string()));
+    }
+
+    // Build the new worktree info, preferring what git reported over the re-derived branch
+    let path = PathBuf::from(&options.path);
+    let mut worktree = build_worktree_info(&path, false)?;
+    let created_branch = match prepared {
+        Some(PreparedWorktree::NewBranch(branch)) => {
+            worktree.head.branch = Some(branch.clone());
+            Some(branch)
+        }
+        Some(PreparedWorktree::CheckedOut(branch)) => {
+            worktree.head.branch = Some(branch);
+            None
+        }
+        Some(PreparedWorktree::Detached) => {
+            worktree.head.branch = None;
+            None
+        }
+        None => None,
+    };
+
+    Ok(CreateWorktreeResult {
+        worktree,
+        created_branch,
+        warnings,
+    })
+}
+
+/// What "git worktree add" reported preparing on its "Preparing worktree (...)" line
+#[derive(Debug, PartialEq, Eq)]
+enum PreparedWorktree {
+    /// "(new branch 'x')" - including branches created by DWIM from a remote branch
+    NewBranch(String),
+    /// "(checking out 'x')" or "(resetting branch 'x'; was at ...)"
+    CheckedOut(String),
+    /// "(detached HEAD abc1234)"
+    Detached,
+}
+
+/// Parse the "Preparing worktree (...)" status line from "git worktree add" stderr
+/// Extracted for testability
+fn parse_prepared_worktree(stderr: &str) -> Option<PreparedWorktree> {
+    let line = stderr
+        .lines()
+        .map(|line| line.trim())
+        .find(|line| line.starts_with("Preparing worktree ("))?;
+    let detail = line
+        .strip_prefix("Preparing worktree (")?
+        .strip_suffix(')')?;
+
+    // Branch names are quoted; take the text between the first pair of quotes
+    let quoted = || {
+        let (_, rest) = detail.split_once('\'')?;
+        let (name, _) = rest.split_once('\'')?;
+        Some(name.to_string())
+    };
+
+    if detail.starts_with("new branch ") {
+        quoted().map(PreparedWorktree::NewBranch)
+    } else if detail.starts_with("checking out ") || detail.starts_with("resetting branch ") {
+        quoted().map(PreparedWorktree::CheckedOut)
+    } else if detail.starts_with("detached HEAD") {
+        Some(PreparedWorktree::Detached)
+    } else {
+        None
+    }
+}
+
+/// Extract warnings worth surfacing from "git worktree add" stderr
+/// The "Preparing worktree (...)" status line and progress updates are dropped
+/// Extracted for testability
+fn parse_worktree_add_warnings(stderr: &str) -> Vec<String> {
+    stderr
+        .lines()
+        // Progress output redraws with '\r'; keep only the final state of the line
+        .filter_map(|line| line.rsplit('\r').next())
+        .map(|line| line.trim())
+        .filter(|line| {
+            !line.is_empty()
+                && !line.starts_with("Preparing worktree")
+                && !line.starts_with("Updating files:")
+        })
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// Reject option combinations git would refuse or silently misinterpret
+fn validate_create_options(options: &CreateWorktreeOptions) -> Result<(), String> {
+    if options.orphan {
+        if options.new_branch.is_none() {
+            return Err("An orphan worktree requires a new branch name".to_string());
+        }
+        if options.commit_ish.is_some() {
+            return Err("An orphan worktree cannot start from a commit".to_string());
+        }
+        if options.detach {
+            return Err("An orphan worktree cannot be detached".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Build the argument list for "git worktree add"
+/// With orphan set and no native support, the worktree is added detached and the
+/// orphan branch is created afterwards by create_worktree
+/// Extracted for testability
+fn build_create_worktree_args(options: &CreateWorktreeOptions, native_orphan: bool) -> Vec<String> {
+    let mut args = vec!["worktree".to_string(), "add".to_string()];
+
+    if options.orphan {
+        if native_orphan {
+            args.push("--orphan".to_string());
+            if let Some(ref branch) = options.new_branch {
+                args.push("-b".to_string());
+                args.push(branch.clone());
+            }
+        } else {
+            args.push("--detach".to_string());
+        }
+        args.push(options.path.clone());
+        return args;
+    }
+
+    if let Some(ref branch) = options.new_branch {
+        args.push("-b".to_string());
+        args.push(branch.clone());
+    }
+
+    if options.detach {
+        args.push("--detach".to_string());
+    }
+
+    args.push(options.path.clone());
+
+    if let Some(ref commit_ish) = options.commit_ish {
+        args.push(commit_ish.clone());
+    }
+
+    args
+}
+
+/// Check "git version" output for "git worktree add --orphan" support (2.42+)
+/// Output looks like "git version 2.42.0" or "git version 2.39.3 (Apple Git-146)"
+fn supports_worktree_orphan(version_output: &str) -> bool {
+    let version = version_output.split_whitespace().nth(2).unwrap_or_default();
+    let mut parts = version.split('.').map(|p| p.parse::<u32>().unwrap_or(0));
+    let major = parts.next().unwrap_or(0);
+    let minor = parts.next().unwrap_or(0);
+    (major, minor) >= (2, 42)
+}
+
+/// Create a worktree tracking a remote branch (e.g. "origin/feature") in one step
+/// The local branch name is derived by stripping the remote prefix; if that name is
+/// already taken a numeric suffix is appended ("feature-2", "feature-3", ...)
+pub fn create_worktree_from_remote(
+    repo_path: &str,
+    remote_branch: &str,
+    path: &str,
+) -> Result<Worktree, String> {
+    let remotes: Vec<String> = run_git(repo_path, &["remote"])?
+        .lines()
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .collect();
+
+    let local_branches: Vec<String> = run_git(
+        repo_path,
+        &["for-each-ref", "--format=%(refname:short)", "refs/heads"],
+    )?
+    .lines()
+    .map(|l| l.trim().to_string())
+    .filter(|l| !l.is_empty())
+    .collect();
+
+    let base_name = derive_local_branch_name(remote_branch, &remotes).ok_or_else(|| {
+        format!(
+            "Cannot derive a local branch name from '{}'; expected <remote>/<branch>",
+            remote_branch
+        )
+    })?;
+    let local_name = unique_branch_name(&base_name, &local_branches);
+
+    let args = build_remote_worktree_args(&local_name, path, remote_branch);
+    let arg_refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    run_git(repo_path, &arg_refs)?;
+
+    build_worktree_info(&PathBuf::from(path), false)
+}
+
+/// Strip the remote prefix from a remote branch name ("origin/feature/x" -> "feature/x")
+/// Known remote names are matched first so remotes containing '/' are handled
+fn derive_local_branch_name(remote_branch: &str, remotes: &[String]) -> Option<String> {
+    let remote_branch = remote_branch.trim_start_matches("refs/remotes/");
+
+    let stripped = remotes
+        .iter()
+        .filter_map(|remote| remote_branch.strip_prefix(&format!("{}/", remote)))
+        .min_by_key(|rest| rest.len())
+        .or_else(|| remote_branch.split_once('/').map(|(_, rest)| rest))?;
+
+    if stripped.is_empty() || stripped == "HEAD" {
+        None
+    } else {
+        Some(stripped.to_string())
+    }
+}
+
+/// Pick a branch name not present in `existing`, appending -2, -3, ... as needed
+fn unique_branch_name(base: &str, existing: &[String]) -> String {
+    if !existing.iter().any(|b| b == base) {
+        return base.to_string();
+    }
+
+    (2..)
+        .map(|n| format!("{}-{}", base, n))
+        .find(|candidate| !existing.iter().any(|b| b == candidate))
+        .unwrap_or_else(|| base.to_string())
+}
+
+/// Build the argument list for "git worktree add --track -b <local> <path> <remote_branch>"
+/// Extracted for testability
+fn build_remote_worktree_args(local_name: &str, path: &str, remote_branch: &str) -> Vec<String> {
+    vec![
+        "worktree".to_string(),
+        "add".to_string(),
+        "--track".to_string(),
+        "-b".to_string(),
+        local_name.to_string(),
+        path.to_string(),
+        remote_branch.to_string(),
+    ]
+}
+
+/// Delete a worktree
+pub fn delete_worktree(repo_path: &str, worktree_path: &str, force: bool) -> Result<(), String> {
+    let mut args = vec!["worktree", "remove"];
+
+    if force {
+        args.push("--force");
+    }
+
+    args.push(worktree_path);
+
+    run_git(repo_path, &args)?;
+    Ok(())
+}
+
+/// Move a worktree's directory and rename its checked-out branch to match
+/// The move is rolled back if the branch rename fails
+pub fn rename_worktree_and_branch(
+    repo_path: &str,
+    worktree_path: &str,
+    new_dir: &str,
+    new_branch: &str,
+) -> Result<Worktree, String> {
+    rename_worktree_with(repo_path, worktree_path, new_dir, new_branch, run_git)?;
+    build_worktree_info(&PathBuf::from(new_dir), false)
+}
+
+/// Sequence "worktree move" then "branch -m" through `run(dir, args)`
+/// Extracted for testability (tests pass a recording runner)
+fn rename_worktree_with<F>(
+    repo_path: &str,
+    worktree_path: &str,
+    new_dir: &str,
+    new_branch: &str,
+    mut run: F,
+) -> Result<(), String>
+where
+    F: FnMut(&str, &[&str]) -> Result<String, String>,
+{
+    run(repo_path, &["worktree", "move", worktree_path, new_dir])?;
+
+    // Without an old name, "branch -m" renames the branch checked out in new_dir
+    if let Err(rename_err) = run(new_dir, &["branch", "-m", new_branch]) {
+        return match run(repo_path, &["worktree", "move", new_dir, worktree_path]) {
+            Ok(_) => Err(format!("Failed to rename branch: {}", rename_err)),
+            Err(rollback_err) => Err(format!(
+                "Failed to rename branch: {}; moving the worktree back to {} also failed: {}",
+                rename_err, worktree_path, rollback_err
+            )),
+        };
+    }
+
+    Ok(())
+}
+
+/// List registered worktrees whose directories no longer exist on disk
+/// These are what "git worktree prune" would clean up, so the UI can offer it
+pub fn find_missing_worktrees(repo_path: &str) -> Result<Vec<String>, String> {
+    let output = run_git(repo_path, &["worktree", "list", "--porcelain"])?;
+    Ok(parse_worktree_list_paths(&output)
+        .into_iter()
+        .filter(|path| !path.exists())
+        .map(|path| path.to_string_lossy().to_string())
+        .collect())
+}
+
+/// Prune stale worktree references
+pub fn prune_worktrees(repo_path: &str) -> Result<PruneResult, String> {
+    // First, do a dry run to see what would be pruned (git reports this on stderr)
+    let (_, dry_run_output) = run_git_capture(repo_path, &["worktree", "prune", "--dry-run"])?;
+
+    let messages = parse_prune_output(&dry_run_output);
+    let pruned_count = messages.len() as u32;
+
+    // Actually prune
+    run_git(repo_path, &["worktree", "prune"])?;
+
+    Ok(PruneResult {
+        pruned_count,
+        messages,
+    })
+}
+
+/// Parse "git worktree prune --dry-run" output into one message per pruned worktree
+/// Counts lines rather than matching message text so it doesn't depend on git's locale
+fn parse_prune_output(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// Resolve the repository's default branch
+/// Uses origin/HEAD when set, otherwise a local "main" or "master"; returns
+/// "origin/<name>" if the default only exists as a remote-tracking branch
+pub fn get_default_branch(repo_path: &str) -> Result<String, String> {
+    let origin_head = run_git(
+        repo_path,
+        &["symbolic-ref", "-q", "--short", "refs/remotes/origin/HEAD"],
+    )
+    .ok()
+    .map(|s| s.trim().to_string())
+    .filter(|s| !s.is_empty());
+
+    let local_branches: Vec<String> = run_git(
+        repo_path,
+        &["for-each-ref", "--format=%(refname:short)", "refs/heads"],
+    )?
+    .lines()
+    .map(|l| l.trim().to_string())
+    .filter(|l| !l.is_empty())
+    .collect();
+
+    pick_default_branch(origin_head.as_deref(), &local_branches)
+        .ok_or_else(|| "Could not determine the default branch".to_string())
+}
+
+/// Read the branch a remote's cached HEAD points at, e.g. "main" for refs/remotes/origin/HEAD
+/// Returns None when the symbolic ref is unset (see "git remote set-head"); never fetches
+pub fn get_remote_head(repo_path: &str, remote: &str) -> Result<Option<String>, String> {
+    ensure_remote_exists(repo_path, remote)?;
+
+    let head_ref = format!("refs/remotes/{}/HEAD", remote);
+    let args = ["symbolic-ref", "-q", head_ref.as_str()];
+    let output = git_command(repo_path, &args)
+        .output()
+        .map_err(|e| format!("Failed to run git {}: {}", args.join(" "), e))?;
+
+    // With -q, a missing or non-symbolic ref exits 1 quietly; anything else is a real failure
+    match output.status.code() {
+        Some(0) => Ok(remote_head_branch(
+            String::from_utf8_lossy(&output.stdout).trim(),
+            remote,
+        )),
+        Some(1) => Ok(None),
+        _ => Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        )),
+    }
+}
+
+/// Reject remote names git would parse as options, and names that aren't configured remotes
+fn ensure_remote_exists(repo_path: &str, remote: &str) -> Result<(), String> {
+    if remote.is_empty() || remote.starts_with('-') || remote.contains(char::is_whitespace) {
+        return Err(format!("Invalid remote name: {}", remote));
+    }
+    let remotes = run_git(repo_path, &["remote"])?;
+    if !remotes.lines().any(|r| r.trim() == remote) {
+        return Err(format!("Remote '{}' does not exist", remote));
+    }
+    Ok(())
+}
+
+/// Strip "refs/remotes/<remote>/" from a remote HEAD's target, leaving the branch name
+/// Extracted for testability
+fn remote_head_branch(target: &str, remote: &str) -> Option<String> {
+    let prefix = format!("refs/remotes/{}/", remote);
+    target
+        .strip_prefix(&prefix)
+        .filter(|branch| !branch.is_empty())
+        .map(|branch| branch.to_string())
+}
+
+/// Choose the default branch from origin/HEAD ("origin/main") and local branch names
+/// Extracted for testability
+fn pick_default_branch(origin_head: Option<&str>, local_branches: &[String]) -> Option<String> {
+    if let Some(origin_head) = origin_head {
+        let name = origin_head.strip_prefix("origin/").unwrap_or(origin_head);
+        if local_branches.iter().any(|b| b == name) {
+            return Some(name.to_string());
+        }
+        return Some(origin_head.to_string());
+    }
+
+    ["main", "master"]
+        .into_iter()
+        .find(|name| local_branches.iter().any(|b| b == name))
+        .map(|name| name.to_string())
+}
+
+/// Count commits on the worktree's HEAD that aren't on the default branch
+/// A worktree with the default branch checked out is always 0 ahead
+pub fn commits_ahead_of_default(repo_path: &str, worktree_path: &str) -> Result<u32, String> {
+    let default_branch = get_default_branch(repo_path)?;
+
+    let current = run_git(worktree_path, &["symbolic-ref", "-q", "--short", "HEAD"])
+        .ok()
+        .map(|s| s.trim().to_string());
+    if current.as_deref() == Some(default_branch.as_str()) {
+        return Ok(0);
+    }
+
+    let range = format!("{}..HEAD", default_branch);
+    let output = run_git(worktree_path, &["rev-list", "--count", &range])?;
+    parse_rev_list_count(&output)
+}
+
+/// List the commits on the worktree's HEAD that aren't on `base` (what a PR would contain)
+pub fn get_branch_commits(
+    worktree_path: &str,
+    base: &str,
+    limit: usize,
+    no_merges: bool,
+) -> Result<Vec<CommitInfo>, String> {
+    // The range is a single argument, so a leading '-' would be read as an option
+    if base.is_empty() || base.starts_with('-') {
+        return Err(format!("Invalid base revision: {}", base));
+    }
+
+    let args = build_branch_commits_args(base, limit, no_merges);
+    let arg_refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    Ok(parse_commit_log(&run_git(worktree_path, &arg_refs)?))
+}
+
+/// Build "git log <base>..HEAD" arguments for get_branch_commits
+/// Extracted for testability
+fn build_branch_commits_args(base: &str, limit: usize, no_merges: bool) -> Vec<String> {
+    let mut args = vec![
+        "log".to_string(),
+        format!("--format={}", COMMIT_LOG_FORMAT),
+        format!("-n{}", limit),
+    ];
+    if no_merges {
+        args.push("--no-merges".to_string());
+    }
+    args.push(format!("{}..HEAD", base));
+    args.push("--".to_string());
+    args
+}
+
+/// For every worktree, the commits on its HEAD that aren't on the default branch
+/// (its unmerged, in-flight work), computed in parallel and in "git worktree list" order
+/// Worktrees whose directory is missing are skipped
+pub fn get_all_in_flight_commits(
+    repo_path: &str,
+    limit_per_branch: usize,
+) -> Result<Vec<WorktreeCommits>, String> {
+    let default_branch = get_default_branch(repo_path)?;
+    let output = run_git(repo_path, &["worktree", "list", "--porcelain"])?;
+    let branches = parse_worktree_branches(&output);
+
+    parse_worktree_list_paths(&output)
+        .into_par_iter()
+        .filter(|path| path.exists())
+        .map(|path| {
+            let worktree_path = path.to_string_lossy().to_string();
+            let commits =
+                get_branch_commits(&worktree_path, &default_branch, limit_per_branch, false)?;
+            let branch = branches
+                .iter()
+                .find(|(branch_path, _)| *branch_path == path)
+                .map(|(_, branch)| branch.clone());
+            Ok(WorktreeCommits {
+                worktree_path,
+                branch,
+                commits,
+            })
+        })
+        .collect()
+}
+
+/// Parse "git rev-list --count" output
+/// Extracted for testability
+fn parse_rev_list_count(output: &str) -> Result<u32, String> {
+    output
+        .trim()
+        .parse()
+        .map_err(|_| format!("Unexpected rev-list count: {}", output.trim()))
+}
+
+/// List every ref in the repository (branches, remotes, tags, notes, and others)
+pub fn list_all_refs(repo_path: &str) -> Result<Vec<RefEntry>, String> {
+    // %1f is a unit separator; %(symref) is only set for symbolic refs like origin/HEAD
+    let output = run_git(
+        repo_path,
+        &[
+            "for-each-ref",
+            "--format=%(refname)%1f%(refname:short)%1f%(objectname)%1f%(symref)",
+        ],
+    )?;
+    Ok(parse_ref_entries(&output))
+}
+
+/// Resolve a tag to the commit it points at, peeling annotated tag objects
+/// Lightweight tags already name the commit; annotated ones name a tag object
+pub fn resolve_tag(repo_path: &str, tag: &str) -> Result<String, String> {
+    if tag.is_empty() || tag.starts_with('-') {
+        return Err(format!("Invalid tag name: {}", tag));
+    }
+
+    let args = build_resolve_tag_args(tag);
+    let arg_refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    run_git(repo_path, &arg_refs)
+        .map(|sha| sha.trim().to_string())
+        .map_err(|_| format!("Tag {} does not point to a commit", tag))
+}
+
+/// Resolve a (possibly abbreviated) revision to a full commit sha
+/// An abbreviated sha shared by several commits yields their full shas instead
+pub fn resolve_commit(worktree_path: &str, rev: &str) -> Result<ResolveResult, String> {
+    if rev.is_empty() || rev.starts_with('-') {
+        return Err(format!("Invalid revision: {}", rev));
+    }
+
+    let peeled = format!("{}^{{commit}}", rev);
+    match run_git(worktree_path, &["rev-parse", "--verify", &peeled]) {
+        Ok(sha) => Ok(ResolveResult::Resolved(sha.trim().to_string())),
+        Err(e) if e.contains("is ambiguous") => {
+            let disambiguate = format!("--disambiguate={}", rev);
+            let candidates = run_git(worktree_path, &["rev-parse", &disambiguate])?;
+            // --disambiguate lists objects of every type; keep only commits
+            let output = run_git_with_stdin(
+                worktree_path,
+                &["cat-file", "--batch-check=%(objectname) %(objecttype)"],
+                &candidates,
+            )?;
+            Ok(ResolveResult::Ambiguous(parse_commit_candidates(
+                &String::from_utf8_lossy(&output.stdout),
+            )))
+        }
+        Err(_) => Err(format!("No commit matches {}", rev)),
+    }
+}
+
+/// Pick commit shas from "git cat-file --batch-check=%(objectname) %(objecttype)" output
+/// Extracted for testability
+fn parse_commit_candidates(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .filter(|(_, kind)| *kind == "commit")
+        .map(|(sha, _)| sha.to_string())
+        .collect()
+}
+
+/// Build the argument list for peeling a tag to its commit ("<tag>^{commit}")
+/// Extracted for testability
+fn build_resolve_tag_args(tag: &str) -> Vec<String> {
+    vec![
+        "rev-parse".to_string(),
+        "--verify".to_string(),
+        "-q".to_string(),
+        format!("{}^{{commit}}", tag),
+    ]
+}
+
+/// Parse for-each-ref output (full name, short name, sha, symref) into RefEntry values
+/// Extracted for testability
+fn parse_ref_entries(output: &str) -> Vec<RefEntry> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\x1f').collect();
+            if fields.len() < 4 {
+                return None;
+            }
+            Some(RefEntry {
+                full_name: fields[0].to_string(),
+                short_name: fields[1].to_string(),
+                kind: classify_ref(fields[0]),
+                target_sha: fields[2].to_string(),
+                is_symbolic: !fields[3].is_empty(),
+            })
+        })
+        .collect()
+}
+
+/// Classify a full ref name by its namespace
+fn classify_ref(full_name: &str) -> RefKind {
+    if full_name.starts_with("refs/heads/") {
+        RefKind::LocalBranch
+    } else if full_name.starts_with("refs/remotes/") {
+        RefKind::RemoteBranch
+    } else if full_name.starts_with("refs/tags/") {
+        RefKind::Tag
+    } else if full_name.starts_with("refs/notes/") {
+        RefKind::Note
+    } else {
+        RefKind::Other
+    }
+}
+
+/// Find the worktree that already has `branch` checked out, if any
+/// Git refuses to check out a branch in two worktrees, so the UI can warn up front
+pub fn branch_checkout_conflict(repo_path: &str, branch: &str) -> Result<Option<String>, String> {
+    let output = run_git(repo_path, &["worktree", "list", "--porcelain"])?;
+    let branch = branch.strip_prefix("refs/heads/").unwrap_or(branch);
+
+    Ok(parse_worktree_branches(&output)
+        .into_iter()
+        .find(|(_, checked_out)| checked_out == branch)
+        .map(|(path, _)| path.to_string_lossy().to_string()))
+}
+
+/// List the remotes that have a branch of the same name as local `branch`
+/// An empty result means deleting the local branch loses it entirely
+pub fn branch_remote_presence(repo_path: &str, branch: &str) -> Result<Vec<String>, String> {
+    let branch = branch.strip_prefix("refs/heads/").unwrap_or(branch);
+    if branch.is_empty() || branch.starts_with('-') {
+        return Err(format!("Invalid branch name: {}", branch));
+    }
+
+    let pattern = format!("refs/remotes/*/{}", branch);
+    let output = run_git(
+        repo_path,
+        &["for-each-ref", "--format=%(refname)", &pattern],
+    )?;
+    Ok(parse_remote_presence(&output, branch))
+}
+
+/// Extract remote names from "refs/remotes/<remote>/<branch>" lines
+/// Extracted for testability
+fn parse_remote_presence(output: &str, branch: &str) -> Vec<String> {
+    let suffix = format!("/{}", branch);
+    output
+        .lines()
+        .filter_map(|line| line.strip_prefix("refs/remotes/")?.strip_suffix(&suffix))
+        .filter(|remote| !remote.is_empty())
+        .map(|remote| remote.to_string())
+        .collect()
+}
+
+/// Map each worktree in "git worktree list --porcelain" output to its checked-out branch
+/// Detached and bare worktrees have no "branch" line and are omitted
+/// Extracted for testability
+fn parse_worktree_branches(output: &str) -> Vec<(PathBuf, String)> {
+    let mut branches = Vec::new();
+    let mut current_path: Option<PathBuf> = None;
+
+    for line in output.lines() {
+        if let Some(path) = line.strip_prefix("worktree ") {
+            current_path = Some(PathBuf::from(path));
+        } else if let Some(branch) = line.strip_prefix("branch refs/heads/") {
+            if let Some(path) = current_path.take() {
+                branches.push((path, branch.to_string()));
+            }
+        }
+    }
+
+    branches
+}
+
+/// List all branches (local and remote)
+pub fn list_branches(repo_path: &str) -> Result<Vec<BranchInfo>, String> {
+    // Get list of checked out branches from worktrees
+    let worktree_output = run_git(repo_path, &["worktree", "list", "--porcelain"])?;
+    let checked_out_branches: Vec<String> = parse_worktree_branches(&worktree_output)
+        .into_iter()
+        .map(|(_, branch)| branch)
+        .collect();
+
+    // Get all branches with format: refname, is_remote indicator
+    // Using for-each-ref for better control over output
+    let output = run_git(
+        repo_path,
+        &[
+            "for-each-ref",
+            "--format=%(refname:short)%09%(if)%(upstream)%(then)local%(else)%(if:equals=refs/remotes)%(refname:rstrip=-2)%(then)remote%(else)local%(end)%(end)",
+            "refs/heads",
+            "refs/remotes",
+        ],
+    )?;
+
+    let mut branches: Vec<BranchInfo> = Vec::new();
+
+    for line in output.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let parts: Vec<&str> = line.split('\t').collect();
+        let name = parts[0].to_string();
+
+        // Skip HEAD references from remotes
+        if name.ends_with("/HEAD") {
+            continue;
+        }
+
+        let is_remote = name.contains('/');
+        let is_checked_out = if is_remote {
+            false
+        } else {
+            checked_out_branches.contains(&name)
+        };
+
+        branches.push(BranchInfo {
+            name,
+            is_remote,
+            is_checked_out,
+        });
+    }
+
+    // Sort: local branches first, then remote, alphabetically within each group
+    branches.sort_by(|a, b| {
+        match (a.is_remote, b.is_remote) {
+            (false, true) => std::cmp::Ordering::Less,
+            (true, false) => std::cmp::Ordering::Greater,
+            _ => a.name.cmp(&b.name),
+        }
+    });
+
+    Ok(branches)
+}
+
+/// Find the best common ancestor of two or more refs
+/// Returns None when the refs share no history; with more than two refs the base
+/// common to all of them is used (--octopus)
+pub fn merge_base(repo_path: &str, refs: &[String]) -> Result<Option<String>, String> {
+    if refs.len() < 2 {
+        return Err("merge_base needs at least two refs".to_string());
+    }
+
+    let args = build_merge_base_args(refs);
+    let arg_refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    let output = git_command(repo_path, &arg_refs)
+        .output()
+        .map_err(|e| format!("Failed to run git {}: {}", args.join(" "), e))?;
+
+    interpret_merge_base_output(
+        output.status.code(),
+        &String::from_utf8_lossy(&output.stdout),
+        &String::from_utf8_lossy(&output.stderr),
+    )
+}
+
+/// Build the argument list for "git merge-base"
+/// Extracted for testability
+fn build_merge_base_args(refs: &[String]) -> Vec<String> {
+    let mut args = vec!["merge-base".to_string()];
+    if refs.len() > 2 {
+        args.push("--octopus".to_string());
+    }
+    args.extend(refs.iter().cloned());
+    args
+}
+
+/// Map "git merge-base" results: exit 1 with no output means no common ancestor
+/// Extracted for testability
+fn interpret_merge_base_output(
+    code: Option<i32>,
+    stdout: &str,
+    stderr: &str,
+) -> Result<Option<String>, String> {
+    match code {
+        Some(0) => Ok(stdout.lines().next().map(|sha| sha.trim().to_string())),
+        Some(1) if stdout.trim().is_empty() && stderr.trim().is_empty() => Ok(None),
+        _ => Err(format!("git merge-base failed: {}", stderr.trim())),
+    }
+}
+
+/// Check whether `branch`'s tip is an ancestor of `into` (safe to delete)
+pub fn is_branch_merged(repo_path: &str, branch: &str, into: &str) -> Result<bool, String> {
+    for rev in [branch, into] {
+        if rev.is_empty() || rev.starts_with('-') {
+            return Err(format!("Invalid revision: {}", rev));
+        }
+    }
+
+    let args = ["merge-base", "--is-ancestor", branch, into];
+    let output = git_command(repo_path, &args)
+        .output()
+        .map_err(|e| format!("Failed to run git {}: {}", args.join(" "), e))?;
+
+    interpret_is_ancestor_exit(
+        output.status.code(),
+        &String::from_utf8_lossy(&output.stderr),
+    )
+}
+
+/// Check whether HEAD can be fast-forwarded to `source` (HEAD is an ancestor of it)
+pub fn can_fast_forward(worktree_path: &str, source: &str) -> Result<bool, String> {
+    if source.is_empty() || source.starts_with('-') {
+        return Err(format!("Invalid revision: {}", source));
+    }
+
+    let args = ["merge-base", "--is-ancestor", "HEAD", source];
+    let output = git_command(worktree_path, &args)
+        .output()
+        .map_err(|e| format!("Failed to run git {}: {}", args.join(" "), e))?;
+
+    interpret_is_ancestor_exit(
+        output.status.code(),
+        &String::from_utf8_lossy(&output.stderr),
+    )
+}
+
+/// Fast-forward HEAD to `source` ("git merge --ff-only"), never creating a merge commit
+pub fn merge_fast_forward(worktree_path: &str, source: &str) -> Result<HeadInfo, String> {
+    if source.is_empty() || source.starts_with('-') {
+        return Err(format!("Invalid revision: {}", source));
+    }
+
+    let args = build_merge_ff_args(source);
+    run_git(worktree_path, &args).map_err(|e| ff_merge_error(source, &e))?;
+    get_head_info(worktree_path)
+}
+
+/// Build the argument list for a fast-forward-only merge
+/// Extracted for testability
+fn build_merge_ff_args(source: &str) -> [&str; 4] {
+    ["merge", "-q", "--ff-only", source]
+}
+
+/// Turn git's refusal to fast-forward into a clear message; other failures pass through
+/// Extracted for testability
+fn ff_merge_error(source: &str, error: &str) -> String {
+    if error.contains("Not possible to fast-forward") {
+        format!(
+            "Cannot fast-forward to {}: the branches have diverged (not fast-forwardable)",
+            source
+        )
+    } else {
+        error.to_string()
+    }
+}
+
+/// Map "git merge-base --is-ancestor" exit status to merged / not merged / error
+/// Exit 1 only means "not an ancestor"; bad revisions exit 128
+/// Extracted for testability
+fn interpret_is_ancestor_exit(code: Option<i32>, stderr: &str) -> Result<bool, String> {
+    match code {
+        Some(0) => Ok(true),
+        Some(1) => Ok(false),
+        _ => Err(format!(
+            "git merge-base --is-ancestor failed: {}",
+            stderr.trim()
+        )),
+    }
+}
+
+/// List local and remote branches whose history contains the given commit
+pub fn branches_containing(repo_path: &str, commit_sha: &str) -> Result<Vec<String>, String> {
+    if commit_sha.is_empty() || commit_sha.starts_with('-') {
+        return Err(format!("Invalid revision: {}", commit_sha));
+    }
+
+    // --contains takes an optional value, so attach it rather than passing it separately
+    let contains = format!("--contains={}", commit_sha);
+    let output = run_git(
+        repo_path,
+        &["branch", "--all", &contains, "--format=%(refname:short)"],
+    )?;
+
+    Ok(parse_branches_containing(&output))
+}
+
+/// Parse "git branch --contains --format=%(refname:short)" output into branch names
+/// Extracted for testability
+fn parse_branches_containing(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .map(|line| line.trim())
+        // "(HEAD detached at abc1234)" is localized, so match on the parentheses only
+        .filter(|line| !line.is_empty() && !line.starts_with('('))
+        // Skip symbolic remote HEAD references like "origin/HEAD"
+        .filter(|line| !line.ends_with("/HEAD"))
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// Get the status of each submodule registered in a worktree
+pub fn get_submodule_status(worktree_path: &str) -> Result<Vec<SubmoduleStatus>, String> {
+    let output = run_git(worktree_path, &["submodule", "status"])?;
+    Ok(parse_submodule_status(&output))
+}
+
+/// Parse "git submodule status" output into Vec<SubmoduleStatus>
+/// Format: "<state char><sha> <path>[ (<describe>)]"
+/// Extracted for testability
+fn parse_submodule_status(output: &str) -> Vec<SubmoduleStatus> {
+    let mut submodules = Vec::new();
+
+    for line in output.lines() {
+        let mut chars = line.chars();
+        let state = match chars.next() {
+            Some(' ') => SubmoduleState::Clean,
+            Some('-') => SubmoduleState::Uninitialized,
+            Some('+') => SubmoduleState::OutOfDate,
+            Some('U') => SubmoduleState::MergeConflict,
+            _ => continue,
+        };
+
+        let Some((sha, rest)) = chars.as_str().split_once(' ') else {
+            continue;
+        };
+
+        // Drop the trailing "(describe)" that initialized submodules carry
+        let path = match rest.rfind(" (") {
+            Some(idx) if rest.ends_with(')') => &rest[..idx],
+            _ => rest,
+        };
+
+        submodules.push(SubmoduleStatus {
+            path: path.to_string(),
+            sha: sha.to_string(),
+            state,
+        });
+    }
+
+    submodules
+}
+
+/// Summarize how two branches have diverged: merge base, ahead counts, and unique commits
+/// `limit` caps the a_only / b_only lists; the ahead counts are always exact
+pub fn compare_branches(
+    repo_path: &str,
+    a: &str,
+    b: &str,
+    limit: usize,
+) -> Result<BranchComparison, String> {
+    let merge_base = run_git(repo_path, &["merge-base", a, b])?
+        .trim()
+        .to_string();
+
+    // Left side counts commits only on a, right side commits only on b
+    let counts = run_git(
+        repo_path,
+        &[
+            "rev-list",
+            "--count",
+            "--left-right",
+            &format!("{}...{}", a, b),
+        ],
+    )?;
+    let (a_ahead, b_ahead) = parse_ahead_behind(&counts);
+
+    let a_args = build_range_log_args(b, a, limit);
+    let a_refs: Vec<&str> = a_args.iter().map(|s| s.as_str()).collect();
+    let a_only = parse_commit_log(&run_git(repo_path, &a_refs)?);
+
+    let b_args = build_range_log_args(a, b, limit);
+    let b_refs: Vec<&str> = b_args.iter().map(|s| s.as_str()).collect();
+    let b_only = parse_commit_log(&run_git(repo_path, &b_refs)?);
+
+    Ok(BranchComparison {
+        merge_base,
+        a_ahead,
+        b_ahead,
+        a_only,
+        b_only,
+    })
+}
+
+/// Build "git log" arguments listing commits reachable from `to` but not from `from`
+/// Extracted for testability
+fn build_range_log_args(from: &str, to: &str, limit: usize) -> Vec<String> {
+    vec![
+        "log".to_string(),
+        format!("--format={}", COMMIT_LOG_FORMAT),
+        format!("-n{}", limit),
+        format!("{}..{}", from, to),
+        "--".to_string(),
+    ]
+}
+
+/// Default remote ref for pull requests (GitHub); GitLab uses "merge-requests/{number}/head"
+const DEFAULT_PR_REF_PATTERN: &str = "pull/{number}/head";
+
+/// Fetch a pull request head from `remote` into a local "pr-<number>" branch
+/// `ref_pattern` overrides the remote ref, with "{number}" substituted
+/// Returns the local branch name
+pub fn fetch_pull_request(
+    repo_path: &str,
+    remote: &str,
+    number: u32,
+    ref_pattern: Option<&str>,
+) -> Result<String, String> {
+    ensure_remote_exists(repo_path, remote)?;
+    let (refspec, local_branch) =
+        build_pr_refspec(ref_pattern.unwrap_or(DEFAULT_PR_REF_PATTERN), number);
+
+    match run_git_network(repo_path, &["fetch", remote, &refspec]) {
+        Ok(_) => Ok(local_branch),
+        // Output is forced to the C locale, so this message is stable
+        Err(e) if e.contains("couldn't find remote ref") => Err(format!(
+            "Pull request #{} was not found on remote '{}'",
+            number, remote
+        )),
+        Err(e) => Err(e),
+    }
+}
+
+/// Build the fetch refspec and local branch name for a pull request
+/// The refspec is forced (+) so re-fetching after a force-push updates the branch
+/// Extracted for testability
+fn build_pr_refspec(ref_pattern: &str, number: u32) -> (String, String) {
+    let remote_ref = ref_pattern.replace("{number}", &number.to_string());
+    let local_branch = format!("pr-{}", number);
+    (format!("+{}:{}", remote_ref, local_branch), local_branch)
+}
+
+/// Derive the executable bit change between two git file modes
+/// Other mode changes (e.g. file to symlink) report neither flag
+/// Extracted for testability
+fn chmod_change(old_mode: &str, new_mode: &str) -> ChmodChange {
+    const REGULAR: &str = "100644";
+    const EXECUTABLE: &str = "100755";
+    ChmodChange {
+        made_executable: old_mode == REGULAR && new_mode == EXECUTABLE,
+        removed_executable: old_mode == EXECUTABLE && new_mode == REGULAR,
+    }
+}
+
+/// List paths that differ between the working tree and `base`
+/// Cheap pre-fetch before loading full hunks
+pub fn diff_name_only(worktree_path: &str, base: &str) -> Result<Vec<String>, String> {
+    // quotePath=false keeps non-ASCII paths unescaped
+    let output = run_git_read(
+        worktree_path,
+        &[
+            "-c",
+            "core.quotePath=false",
+            "diff",
+            "--name-only",
+            base,
+            "--",
+        ],
+    )?;
+    Ok(parse_name_only(&output))
+}
+
+/// Parse "git diff --name-only" output into a path list
+/// Extracted for testability
+fn parse_name_only(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// Count files added, deleted, modified, and renamed between two revisions
+pub fn file_count_delta(
+    worktree_path: &str,
+    base: &str,
+    head: &str,
+) -> Result<FileCountDelta, String> {
+    // The range is a single argument, so a leading '-' would be read as an option
+    for rev in [base, head] {
+        if rev.is_empty() || rev.starts_with('-') {
+            return Err(format!("Invalid revision: {}", rev));
+        }
+    }
+
+    let range = format!("{}..{}", base, head);
+    let output = run_git(
+        worktree_path,
+        &["diff", "--name-status", "-M", &range, "--"],
+    )?;
+    Ok(parse_name_status_counts(&output))
+}
+
+/// Tally "git diff --name-status" lines by their status letter
+/// Copies (C<score>) add a file; type changes (T) count as modifications
+/// Extracted for testability
+fn parse_name_status_counts(output: &str) -> FileCountDelta {
+    let mut delta = FileCountDelta {
+        added: 0,
+        deleted: 0,
+        modified: 0,
+        renamed: 0,
+    };
+    for line in output.lines() {
+        match line.chars().next() {
+            Some('A') | Some('C') => delta.added += 1,
+            Some('D') => delta.deleted += 1,
+            Some('M') | Some('T') => delta.modified += 1,
+            Some('R') => delta.renamed += 1,
+            _ => {}
+        }
+    }
+    delta
+}
+
+/// Parse the "<old>..<new>[ <mode>]" part of a diff "index" line into blob shas
+/// An all-zero sha (file added or deleted) means there is no blob on that side
+/// Extracted for testability
+fn parse_index_line(rest: &str) -> (Option<String>, Option<String>) {
+    let range = rest.split(' ').next().unwrap_or("");
+    let Some((old, new)) = range.split_once("..") else {
+        return (None, None);
+    };
+    let blob = |sha: &str| {
+        if sha.is_empty() || sha.chars().all(|c| c == '0') {
+            None
+        } else {
+            Some(sha.to_string())
+        }
+    };
+    (blob(old), blob(new))
+}
+
+/// Guess a MIME type from a file extension, for previewing binary files
+/// Extracted for testability
+fn guess_mime_type(path: &str) -> Option<&'static str> {
+    let ext = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+    let mime = match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "ico" => "image/x-icon",
+        "svg" => "image/svg+xml",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+/// Per-file addition/deletion counts between two revisions, without hunks
+pub fn get_range_numstat(
+    worktree_path: &str,
+    base: &str,
+    head: &str,
+) -> Result<Vec<FileNumstat>, String> {
+    // The range is a single argument, so a leading '-' would be read as an option
+    for rev in [base, head] {
+        if rev.is_empty() || rev.starts_with('-') {
+            return Err(format!("Invalid revision: {}", rev));
+        }
+    }
+
+    let range = format!("{}..{}", base, head);
+    let output = run_git(
+        worktree_path,
+        &[
+            "-c",
+            "core.quotePath=false",
+            "diff",
+            "--numstat",
+            "-M",
+            &range,
+            "--",
+        ],
+    )?;
+    Ok(parse_numstat(&output))
+}
+
+/// Parse "git diff --numstat" output ("<added>\t<deleted>\t<path>")
+/// Binary files report "-" counts; renames use "old => new" or "dir/{old => new}/file"
+/// Extracted for testability
+fn parse_numstat(output: &str) -> Vec<FileNumstat> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, '\t');
+            let additions = parts.next()?.parse().ok();
+            let deletions = parts.next()?.parse().ok();
+            let (old_path, path) = split_numstat_rename(parts.next()?);
+            Some(FileNumstat {
+                path,
+                old_path,
+                additions,
+                deletions,
+            })
+        })
+        .collect()
+}
+
+/// Expand a numstat rename path into (old_path, new_path)
+/// Extracted for testability
+fn split_numstat_rename(path: &str) -> (Option<String>, String) {
+    if let (Some(open), Some(close)) = (path.find('{'), path.rfind('}')) {
+        if let Some((old, new)) = path[open + 1..close].split_once(" => ") {
+            let (prefix, suffix) = (&path[..open], &path[close + 1..]);
+            // An empty side ("{ => sub}/") leaves a doubled or leading separator behind
+            let join = |middle: &str| {
+                let joined = format!("{}{}{}", prefix, middle, suffix).replace("//", "/");
+                joined.trim_start_matches('/').to_string()
+            };
+            return (Some(join(old)), join(new));
+        }
+    }
+
+    match path.split_once(" => ") {
+        Some((old, new)) => (Some(old.to_string()), new.to_string()),
+        None => (None, path.to_string()),
+    }
+}
+
+/// Parse git status --porcelain output into WorktreeStatus
+/// Extracted for testability
+fn parse_status_porcelain(output: &str) -> WorktreeStatus {
+    let mut modified = 0u32;
+    let mut staged = 0u32;
+    let mut untracked = 0u32;
+    let mut conflicted = 0u32;
+
+    for line in output.lines() {
+        if line.len() < 2 {
+            continue;
+        }
+
+        let index_status = line.chars().next().unwrap_or(' ');
+        let worktree_status = line.chars().nth(1).unwrap_or(' ');
+
+        // Check for conflicts (UU, AA, DD, AU, UA, DU, UD)
+        if matches!(
+            (index_status, worktree_status),
+            ('U', 'U') | ('A', 'A') | ('D', 'D') | ('A', 'U') | ('U', 'A') | ('D', 'U') | ('U', 'D')
+        ) {
+            conflicted += 1;
+        } else if index_status == '?' && worktree_status == '?' {
+            // Untracked
+            untracked += 1;
+        } else {
+            // Check staged changes (index column)
+            if matches!(index_status, 'M' | 'A' | 'D' | 'R' | 'C') {
+                staged += 1;
+            }
+            // Check unstaged changes (worktree column) - only if not already counted as staged
+            else if matches!(worktree_status, 'M' | 'D') {
+                modified += 1;
+            }
+        }
+    }
+
+    WorktreeStatus {
+        is_clean: modified == 0 && staged == 0 && untracked == 0 && conflicted == 0,
+        modified,
+        staged,
+        untracked,
+        conflicted,
+        untracked_scanned: true,
+    }
+}
+
+/// Parse git log output with record/unit separators into Vec<CommitInfo>
+/// Extracted for testability
+fn parse_commit_log(output: &str) -> Vec<CommitInfo> {
+    let mut commits = Vec::new();
+
+    for record in output.split('\x1e') {
+        let record = record.trim();
+        if record.is_empty() {
+            continue;
+        }
+
+        let fields: Vec<&str> = record.split('\x1f').collect();
+        if fields.len() < 7 {
+            continue;
+        }
+
+        let hash = fields[0].to_string();
+        let short_hash = fields[1].to_string();
+        let author_name = fields[2].to_string();
+        let author_email = fields[3].to_string();
+        let timestamp = fields[4].parse::<i64>().unwrap_or(0);
+        let author_timestamp = fields[5].parse::<i64>().unwrap_or(0);
+        let summary = fields[6].to_string();
+        let message = fields.get(7).unwrap_or(&"").trim().to_string();
+        let parent_hashes = fields
+            .get(8)
+            .map(|p| p.split_whitespace().map(|h| h.to_string()).collect())
+            .unwrap_or_default();
+        let trailers = parse_trailers(&message);
+
+        commits.push(CommitInfo {
+            hash,
+            short_hash,
+            author_name,
+            author_email,
+            timestamp,
+            author_timestamp,
+            message,
+            summary,
+            parent_hashes,
+            trailers,
+        });
+    }
+
+    commits
+}
+
+/// Parse trailers ("Signed-off-by: ...", "Co-authored-by: ...") from a commit message
+/// Like git, trailers are only recognised in the final paragraph, never the subject,
+/// and only when every line of that paragraph is a "Key: value" line (or an indented
+/// continuation of one), so prose that happens to contain a colon is left alone
+/// Extracted for testability
+fn parse_trailers(message: &str) -> Vec<(String, String)> {
+    let paragraphs: Vec<&str> = message
+        .trim()
+        .split("\n\n")
+        .filter(|p| !p.trim().is_empty())
+        .collect();
+    if paragraphs.len() < 2 {
+        return Vec::new();
+    }
+
+    let mut trailers: Vec<(String, String)> = Vec::new();
+    for line in paragraphs[paragraphs.len() - 1].lines() {
+        if line.starts_with([' ', '\t']) {
+            match trailers.last_mut() {
+                Some((_, value)) => {
+                    value.push(' ');
+                    value.push_str(line.trim());
+                }
+                None => return Vec::new(),
+            }
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once(':') else {
+            return Vec::new();
+        };
+        let valid_key =
+            !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+        if !valid_key {
+            return Vec::new();
+        }
+        trailers.push((key.to_string(), value.trim().to_string()));
+    }
+
+    trailers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unique scratch path under the system temp dir
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("woodeye-test-{}-{}", name, std::process::id()))
+    }
+
+    /// Create a throwaway repository with a single commit on "main"
+    fn init_test_repo(name: &str) -> PathBuf {
+        let dir = temp_path(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.to_string_lossy().to_string();
+        run_git(&path, &["init", "-q", "-b", "main"]).unwrap();
+        run_git(&path, &["config", "user.name", "Test User"]).unwrap();
+        run_git(&path, &["config", "user.email", "test@example.com"]).unwrap();
+        run_git(&path, &["config", "commit.gpgsign", "false"]).unwrap();
+
+        std::fs::write(dir.join("README.md"), "hello\n").unwrap();
+        run_git(&path, &["add", "README.md"]).unwrap();
+        run_git(&path, &["commit", "-q", "-m", "Initial commit"]).unwrap();
+
+        dir
+    }
+
+    // ==================== isolate_git_env tests ====================
+
+    #[cfg(unix)]
+    #[test]
+    fn test_isolate_git_env() {
+        // Use `env` as a shim that prints the environment it was spawned with
+        let mut cmd = Command::new("env");
+        cmd.env("GIT_DIR", "/bogus/.git")
+            .env("GIT_WORK_TREE", "/bogus")
+            .env("GIT_PAGER", "less")
+            .env("LANGUAGE", "de")
+            .env("LC_ALL", "de_DE.UTF-8");
+        let output = isolate_git_env(&mut cmd).output().unwrap();
+        let env = String::from_utf8_lossy(&output.stdout);
+        let lines: Vec<&str> = env.lines().collect();
+
+        assert!(!lines.iter().any(|l| l.starts_with("GIT_DIR=")));
+        assert!(!lines.iter().any(|l| l.starts_with("GIT_WORK_TREE=")));
+        assert!(!lines.iter().any(|l| l.starts_with("LANGUAGE=")));
+        assert!(lines.contains(&"GIT_PAGER=cat"));
+        assert!(lines.contains(&"GIT_TERMINAL_PROMPT=0"));
+        assert!(lines.contains(&"LC_ALL=C"));
+    }
+
+    // ==================== with_parallelism tests ====================
+
+    #[test]
+    fn test_with_parallelism_respects_limit() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let active = AtomicUsize::new(0);
+        let max_seen = AtomicUsize::new(0);
+
+        // Instrumented stand-in for a git invocation that records peak concurrency
+        let results: Vec<usize> = with_parallelism(Some(2), || {
+            (0..16)
+                .into_par_iter()
+                .map(|i| {
+                    let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_seen.fetch_max(now, Ordering::SeqCst);
+                    std::thread::sleep(std::time::Duration::from_millis(5));
+                    active.fetch_sub(1, Ordering::SeqCst);
+                    i
+                })
+                .collect()
+        })
+        .unwrap();
+
+        assert_eq!(results.len(), 16);
+        assert!(max_seen.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[test]
+    fn test_with_parallelism_default() {
+        let sum: usize = with_parallelism(None, || (1..=4usize).into_par_iter().sum()).unwrap();
+        assert_eq!(sum, 10);
+    }
+
+    // ==================== parse_range tests ====================
+
+    #[test]
+    fn test_parse_range_with_comma() {
+        assert_eq!(parse_range("1,5"), Some((1, 5)));
+        assert_eq!(parse_range("10,20"), Some((10, 20)));
+        assert_eq!(parse_range("0,0"), Some((0, 0)));
+    }
+
+    #[test]
+    fn test_parse_range_single_number() {
+        // Single number implies 1 line
+        assert_eq!(parse_range("1"), Some((1, 1)));
+        assert_eq!(parse_range("10"), Some((10, 1)));
+        assert_eq!(parse_range("0"), Some((0, 1)));
+    }
+
+    #[test]
+    fn test_parse_range_invalid() {
+        assert_eq!(parse_range(""), None);
+        assert_eq!(parse_range("abc"), None);
+        assert_eq!(parse_range("1,abc"), None);
+        assert_eq!(parse_range("abc,1"), None);
+    }
+
+    // ==================== parse_hunk_header tests ====================
+
+    #[test]
+    fn test_parse_hunk_header_basic() {
+        assert_eq!(
+            parse_hunk_header("@@ -1,5 +1,7 @@"),
+            Some((1, 5, 1, 7))
+        );
+        assert_eq!(
+            parse_hunk_header("@@ -10,3 +12,5 @@"),
+            Some((10, 3, 12, 5))
+        );
+    }
+
+    #[test]
+    fn test_parse_hunk_header_single_line() {
+        // Single line changes (no comma means 1 line)
+        assert_eq!(
+            parse_hunk_header("@@ -1 +1 @@"),
+            Some((1, 1, 1, 1))
+        );
+    }
+
+    #[test]
+    fn test_parse_hunk_header_with_context() {
+        // Hunk headers can have function context after @@
+        assert_eq!(
+            parse_hunk_header("@@ -10,3 +12,5 @@ fn some_function()"),
+            Some((10, 3, 12, 5))
+        );
+    }
+
+    #[test]
+    fn test_parse_hunk_header_new_file() {
+        // New file: old side is 0,0
+        assert_eq!(
+            parse_hunk_header("@@ -0,0 +1,10 @@"),
+            Some((0, 0, 1, 10))
+        );
+    }
+
+    #[test]
+    fn test_parse_hunk_header_deleted_file() {
+        // Deleted file: new side is 0,0
+        assert_eq!(
+            parse_hunk_header("@@ -1,10 +0,0 @@"),
+            Some((1, 10, 0, 0))
+        );
+    }
+
+    #[test]
+    fn test_parse_hunk_header_invalid() {
+        assert_eq!(parse_hunk_header("not a hunk header"), None);
+        assert_eq!(parse_hunk_header("@@ @@"), None);
+        assert_eq!(parse_hunk_header("@@ -1 @@"), None);
+    }
+
+    // ==================== parse_git_diff_output tests ====================
+
+    #[test]
+    fn test_parse_diff_empty() {
+        let files = parse_git_diff_output("");
+        assert!(files.is_empty());
+    }
+
+    #[test]
+    fn test_parse_diff_single_modified_file() {
+        let diff = r#"diff --git a/src/main.rs b/src/main.rs
+index abc1234..def5678 100644
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,3 +1,4 @@
+ fn main() {
++    println!("Hello");
+     println!("World");
+ }
+"#;
+        let files = parse_git_diff_output(diff);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "src/main.rs");
+        assert!(matches!(files[0].status, FileStatus::Modified));
+        assert!(!files[0].binary);
+        assert_eq!(files[0].hunks.len(), 1);
+
+        let hunk = &files[0].hunks[0];
+        assert_eq!(hunk.old_start, 1);
+        assert_eq!(hunk.old_lines, 3);
+        assert_eq!(hunk.new_start, 1);
+        assert_eq!(hunk.new_lines, 4);
+        assert_eq!(hunk.lines.len(), 4);
+
+        // Check line types
+        assert_eq!(hunk.lines[0].kind, ' '); // context
+        assert_eq!(hunk.lines[1].kind, '+'); // addition
+        assert_eq!(hunk.lines[2].kind, ' '); // context
+        assert_eq!(hunk.lines[3].kind, ' '); // context
+    }
+
+    #[test]
+    fn test_parse_diff_new_file() {
+        let diff = r#"diff --git a/new_file.txt b/new_file.txt
+new file mode 100644
+index 0000000..abc1234
+--- /dev/null
++++ b/new_file.txt
+@@ -0,0 +1,2 @@
++line 1
++line 2
+"#;
+        let files = parse_git_diff_output(diff);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "new_file.txt");
+        assert!(matches!(files[0].status, FileStatus::Added));
+    }
+
+    #[test]
+    fn test_parse_diff_deleted_file() {
+        let diff = r#"diff --git a/old_file.txt b/old_file.txt
+deleted file mode 100644
+index abc1234..0000000
+--- a/old_file.txt
++++ /dev/null
+@@ -1,2 +0,0 @@
+-line 1
+-line 2
+"#;
+        let files = parse_git_diff_output(diff);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "old_file.txt");
+        assert!(matches!(files[0].status, FileStatus::Deleted));
+    }
+
+    #[test]
+    fn test_parse_diff_renamed_file() {
+        let diff = r#"diff --git a/old_name.rs b/new_name.rs
+similarity index 95%
+rename from old_name.rs
+rename to new_name.rs
+index abc1234..def5678 100644
+--- a/old_name.rs
++++ b/new_name.rs
+@@ -1,3 +1,3 @@
+ fn main() {
+-    old();
++    new();
+ }
+"#;
+        let files = parse_git_diff_output(diff);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "new_name.rs");
+        assert!(matches!(files[0].status, FileStatus::Renamed));
+        assert_eq!(files[0].old_path, Some("old_name.rs".to_string()));
+    }
+
+    #[test]
+    fn test_parse_diff_binary_file() {
+        let diff = r#"diff --git a/image.png b/image.png
+new file mode 100644
+index 0000000..abc1234
+Binary files /dev/null and b/image.png differ
+"#;
+        let files = parse_git_diff_output(diff);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "image.png");
+        assert!(files[0].binary);
+        assert!(files[0].hunks.is_empty());
+    }
+
+    #[test]
+    fn test_parse_diff_binary_blob_shas() {
+        let diff = r#"diff --git a/logo.png b/logo.png
+index 3b18e51..a7c2f09 100644
+Binary files a/logo.png and b/logo.png differ
+diff --git a/data.bin b/data.bin
+new file mode 100644
+index 0000000..abc1234
+Binary files /dev/null and b/data.bin differ
+diff --git a/file.rs b/file.rs
+index 123abcd..456ef01 100644
+--- a/file.rs
++++ b/file.rs
+@@ -1 +1 @@
+-old
++new
+"#;
+        let files = parse_git_diff_output(diff);
+        assert_eq!(files.len(), 3);
+        assert_eq!(files[0].old_blob_sha.as_deref(), Some("3b18e51"));
+        assert_eq!(files[0].new_blob_sha.as_deref(), Some("a7c2f09"));
+        assert_eq!(files[0].mime_guess.as_deref(), Some("image/png"));
+
+        // Added file has no old blob; unknown extension has no mime guess
+        assert_eq!(files[1].old_blob_sha, None);
+        assert_eq!(files[1].new_blob_sha.as_deref(), Some("abc1234"));
+        assert_eq!(files[1].mime_guess, None);
+
+        // Text files don't carry blob metadata
+        assert!(!files[2].binary);
+        assert_eq!(files[2].new_blob_sha, None);
+    }
+
+    #[test]
+    fn test_parse_index_line() {
+        assert_eq!(
+            parse_index_line("abc1234..def5678 100755"),
+            (Some("abc1234".to_string()), Some("def5678".to_string()))
+        );
+        assert_eq!(
+            parse_index_line("abc1234..0000000"),
+            (Some("abc1234".to_string()), None)
+        );
+        assert_eq!(parse_index_line("garbage"), (None, None));
+    }
+
+    #[test]
+    fn test_get_blob() {
+        let repo = init_test_repo("get-blob");
+        let path = repo.to_string_lossy();
+        let sha = run_git(&path, &["rev-parse", "HEAD:README.md"]).unwrap();
+
+        assert_eq!(get_blob(&path, sha.trim()).unwrap(), "aGVsbG8K");
+        assert!(get_blob(&path, "--output=x").is_err());
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn test_parse_diff_multiple_files() {
+        let diff = r#"diff --git a/file1.rs b/file1.rs
+index abc..def 100644
+--- a/file1.rs
++++ b/file1.rs
+@@ -1 +1 @@
+-old
++new
+diff --git a/file2.rs b/file2.rs
+index 123..456 100644
+--- a/file2.rs
++++ b/file2.rs
+@@ -1 +1 @@
+-foo
++bar
+"#;
+        let files = parse_git_diff_output(diff);
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, "file1.rs");
+        assert_eq!(files[1].path, "file2.rs");
+    }
+
+    #[test]
+    fn test_parse_diff_multiple_hunks() {
+        let diff = r#"diff --git a/file.rs b/file.rs
+index abc..def 100644
+--- a/file.rs
++++ b/file.rs
+@@ -1,3 +1,3 @@
+ fn foo() {
+-    old1();
++    new1();
+ }
+@@ -10,3 +10,3 @@
+ fn bar() {
+-    old2();
++    new2();
+ }
+"#;
+        let files = parse_git_diff_output(diff);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].hunks.len(), 2);
+        assert_eq!(files[0].hunks[0].old_start, 1);
+        assert_eq!(files[0].hunks[1].old_start, 10);
+    }
+
+    // ==================== get_commit_file_diff tests ====================
+
+    #[test]
+    fn test_select_file_diff_only_requested_path() {
+        let diff = r#"diff --git a/file1.rs b/file1.rs
+index abc..def 100644
+--- a/file1.rs
++++ b/file1.rs
+@@ -1 +1 @@
+-old
++new
+diff --git a/file2.rs b/file2.rs
+index 123..456 100644
+--- a/file2.rs
++++ b/file2.rs
+@@ -1 +1 @@
+-foo
++bar
+"#;
+        let file = select_file_diff(parse_git_diff_output(diff), "file2.rs").unwrap();
+        assert_eq!(file.path, "file2.rs");
+        assert_eq!(file.hunks.len(), 1);
+        assert_eq!(file.hunks[0].lines[0].content, "foo");
+
+        assert!(select_file_diff(parse_git_diff_output(diff), "missing.rs").is_none());
+    }
+
+    #[test]
+    fn test_get_staged_file_diff() {
+        let repo = init_test_repo("staged-file-diff");
+        let path = repo.to_string_lossy();
+
+        std::fs::write(repo.join("README.md"), "staged\n").unwrap();
+        std::fs::write(repo.join("other.txt"), "other\n").unwrap();
+        run_git(&path, &["add", "-A"]).unwrap();
+        // An unstaged edit on top must not show up in the staged diff
+        std::fs::write(repo.join("README.md"), "unstaged\n").unwrap();
+
+        let file = get_staged_file_diff(&path, "README.md").unwrap();
+        assert_eq!(file.path, "README.md");
+        assert_eq!(file.hunks.len(), 1);
+        let added: Vec<&str> = file.hunks[0]
+            .lines
+            .iter()
+            .filter(|l| l.kind == '+')
+            .map(|l| l.content.as_str())
+            .collect();
+        assert_eq!(added, vec!["staged"]);
+
+        run_git(&path, &["reset", "-q"]).unwrap();
+        let empty = get_staged_file_diff(&path, "README.md").unwrap();
+        assert_eq!(empty.path, "README.md");
+        assert!(empty.hunks.is_empty());
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== blame_file tests ====================
+
+    #[test]
+    fn test_blame_args() {
+        assert_eq!(
+            build_blame_args("src/main.rs", Some("v1.0")),
+            vec!["blame", "--porcelain", "v1.0", "--", "src/main.rs"]
+        );
+        assert_eq!(
+            build_blame_args("src/main.rs", None),
+            vec!["blame", "--porcelain", "--", "src/main.rs"]
+        );
+    }
+
+    #[test]
+    fn test_parse_blame_porcelain() {
+        let a = "a".repeat(40);
+        let b = "b".repeat(40);
+        let output = format!(
+            "{a} 1 1 2\nauthor Alice\nauthor-time 1700000000\nsummary First\nfilename f\n\tone\n\
+             {a} 2 2\n\ttwo\n\
+             {b} 5 3 1\nauthor Bob\nauthor-time 1700000100\nsummary Second\nfilename f\n\tthree\n\
+             {a} 3 4 1\n\tfour\n"
+        );
+        let lines = parse_blame_porcelain(&output);
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[1].line_number, 2);
+        assert_eq!(lines[1].content, "two");
+        assert_eq!(lines[1].author_name, "Alice");
+        assert_eq!(lines[2].commit_sha, b);
+        assert_eq!(lines[2].original_line, 5);
+        assert_eq!(lines[2].author_time, 1700000100);
+        assert_eq!(lines[2].summary, "Second");
+        // Details are remembered for later lines of an already-seen commit
+        assert_eq!(lines[3].summary, "First");
+    }
+
+    #[test]
+    fn test_blame_file_at_rev() {
+        let repo = init_test_repo("blame-rev");
+        let path = repo.to_string_lossy();
+        run_git(&path, &["tag", "v1"]).unwrap();
+        std::fs::write(repo.join("README.md"), "new top\nhello\n").unwrap();
+        run_git(&path, &["commit", "-q", "-am", "Add top line"]).unwrap();
+
+        let old = blame_file(&path, "README.md", Some("v1")).unwrap();
+        assert_eq!(old.len(), 1);
+        assert_eq!(old[0].line_number, 1);
+        assert_eq!(old[0].content, "hello");
+        assert_eq!(old[0].summary, "Initial commit");
+
+        let current = blame_file(&path, "README.md", None).unwrap();
+        assert_eq!(current[1].line_number, 2);
+        assert_eq!(current[1].content, "hello");
+        assert_eq!(current[0].summary, "Add top line");
+
+        let err = blame_file(&path, "later.txt", Some("v1")).unwrap_err();
+        assert!(err.contains("does not exist at v1"), "{}", err);
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== diff_file_against_rev tests ====================
+
+    #[test]
+    fn test_diff_file_against_rev_added() {
+        let repo = init_test_repo("diff-against-rev-added");
+        let path = repo.to_string_lossy();
+        run_git(&path, &["checkout", "-q", "-b", "feature"]).unwrap();
+        std::fs::write(repo.join("committed.txt"), "one\ntwo\n").unwrap();
+        run_git(&path, &["add", "committed.txt"]).unwrap();
+        run_git(&path, &["commit", "-q", "-m", "Add file"]).unwrap();
+        std::fs::write(repo.join("untracked.txt"), "fresh\n").unwrap();
+
+        // Committed on this branch but absent on main
+        let file = diff_file_against_rev(&path, "committed.txt", "main").unwrap();
+        assert!(matches!(file.status, FileStatus::Added));
+        assert_eq!(file.hunks[0].additions, 2);
+        assert_eq!(file.hunks[0].deletions, 0);
+
+        let file = diff_file_against_rev(&path, "untracked.txt", "main").unwrap();
+        assert_eq!(file.path, "untracked.txt");
+        assert!(matches!(file.status, FileStatus::Added));
+        assert_eq!(file.hunks[0].lines[0].content, "fresh");
+
+        assert!(diff_file_against_rev(&path, "README.md", "--cached").is_err());
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn test_diff_file_against_rev_unchanged() {
+        let repo = init_test_repo("diff-against-rev-unchanged");
+        let path = repo.to_string_lossy();
+        run_git(&path, &["checkout", "-q", "-b", "feature"]).unwrap();
+        run_git(&path, &["commit", "-q", "--allow-empty", "-m", "Empty"]).unwrap();
+
+        let file = diff_file_against_rev(&path, "README.md", "main").unwrap();
+        assert_eq!(file.path, "README.md");
+        assert!(file.hunks.is_empty());
+
+        std::fs::write(repo.join("README.md"), "hello\nworld\n").unwrap();
+        let file = diff_file_against_rev(&path, "README.md", "main").unwrap();
+        assert_eq!(file.hunks[0].additions, 1);
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn test_get_commit_file_diff() {
+        let repo = init_test_repo("commit-file-diff");
+        let path = repo.to_string_lossy();
+
+        std::fs::write(repo.join("README.md"), "hello\nworld\n").unwrap();
+        std::fs::write(repo.join("other.txt"), "other\n").unwrap();
+        run_git(&path, &["add", "."]).unwrap();
+        run_git(&path, &["commit", "-q", "-m", "Two files"]).unwrap();
+
+        let file = get_commit_file_diff(&path, "HEAD", "other.txt").unwrap();
+        assert_eq!(file.path, "other.txt");
+        assert!(matches!(file.status, FileStatus::Added));
+
+        let err = get_commit_file_diff(&path, "HEAD~1", "other.txt").unwrap_err();
+        assert!(err.contains("not changed"));
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn test_parse_diff_hunk_stats() {
+        let diff = r#"diff --git a/file.rs b/file.rs
+index abc..def 100644
+--- a/file.rs
++++ b/file.rs
+@@ -1,6 +1,8 @@
+ fn main() {
+-    old_a();
+-    old_b();
++    new_a();
++    new_b();
++    new_c();
+     shared();
++    added();
++    more();
+ }
+@@ -20,3 +22,2 @@ fn other() {
+ context
+-removed
+ context
+"#;
+        let files = parse_git_diff_output(diff);
+        let hunks = &files[0].hunks;
+        assert_eq!(hunks.len(), 2);
+        // Context lines are not counted
+        assert_eq!((hunks[0].additions, hunks[0].deletions), (5, 2));
+        assert_eq!((hunks[1].additions, hunks[1].deletions), (0, 1));
+    }
+
+    #[test]
+    fn test_parse_diff_line_numbers() {
+        let diff = r#"diff --git a/file.rs b/file.rs
+index abc..def 100644
+--- a/file.rs
++++ b/file.rs
+@@ -10,3 +10,3 @@
+ keep
+-old
++new
+ keep
+"#;
+        let files = parse_git_diff_output(diff);
+        let lines = &files[0].hunks[0].lines;
+        assert_eq!(
+            (lines[0].old_lineno, lines[0].new_lineno),
+            (Some(10), Some(10))
+        );
+        assert_eq!((lines[1].old_lineno, lines[1].new_lineno), (Some(11), None));
+        assert_eq!((lines[2].old_lineno, lines[2].new_lineno), (None, Some(11)));
+        assert_eq!(
+            (lines[3].old_lineno, lines[3].new_lineno),
+            (Some(12), Some(12))
+        );
+    }
+
+    // ==================== get_diff_context tests ====================
+
+    #[test]
+    fn test_context_lines_slicing() {
+        let content = "one\ntwo\nthree\nfour\nfive\n";
+        let lines = build_context_lines(content, 2, 3, None);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].content, "two");
+        assert_eq!(lines[0].new_lineno, Some(2));
+        assert_eq!(lines[2].content, "four");
+        assert_eq!(lines[2].new_lineno, Some(4));
+        assert!(lines
+            .iter()
+            .all(|l| l.kind == ' ' && l.old_lineno.is_none()));
+
+        // Past the end of the file is truncated
+        let lines = build_context_lines(content, 4, 10, None);
+        assert_eq!(lines.len(), 2);
+        assert!(build_context_lines(content, 9, 2, None).is_empty());
+    }
+
+    #[test]
+    fn test_context_lines_old_numbering() {
+        // Two lines inserted after old line 2 (new lines 3-4), one line deleted after old 6
+        let diff = r#"diff --git a/f b/f
+--- a/f
++++ b/f
+@@ -2,0 +3,2 @@
++a
++b
+@@ -7 +8,0 @@
+-gone
+"#;
+        let hunks: Vec<DiffHunk> = parse_git_diff_output(diff)
+            .into_iter()
+            .flat_map(|f| f.hunks)
+            .collect();
+        let content = (1..=10).map(|n| format!("line{}\n", n)).collect::<String>();
+
+        let lines = build_context_lines(&content, 1, 10, Some(&hunks));
+        let old: Vec<Option<u32>> = lines.iter().map(|l| l.old_lineno).collect();
+        assert_eq!(
+            old,
+            vec![
+                Some(1),
+                Some(2),
+                None,
+                None,
+                Some(3),
+                Some(4),
+                Some(5),
+                Some(6),
+                Some(8),
+                Some(9)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_diff_context() {
+        let repo = init_test_repo("diff-context");
+        let path = repo.to_string_lossy();
+
+        let original: String = (1..=20).map(|n| format!("line{}\n", n)).collect();
+        std::fs::write(repo.join("file.txt"), &original).unwrap();
+        run_git(&path, &["add", "file.txt"]).unwrap();
+        run_git(&path, &["commit", "-q", "-m", "Add file"]).unwrap();
+
+        let updated = original.replacen("line2\n", "line2\ninserted\n", 1);
+        std::fs::write(repo.join("file.txt"), updated).unwrap();
+        run_git(&path, &["commit", "-q", "-am", "Insert line"]).unwrap();
+
+        let lines = get_diff_context(&path, "HEAD", "file.txt", 10, 2).unwrap();
+        assert_eq!(lines[0].content, "line9");
+        assert_eq!(lines[0].new_lineno, Some(10));
+        assert_eq!(lines[0].old_lineno, Some(9));
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== untracked mode tests ====================
+
+    #[test]
+    fn test_build_status_args() {
+        assert_eq!(
+            build_status_args(UntrackedMode::No),
+            ["status", "--porcelain", "--untracked-files=no"]
+        );
+        assert_eq!(
+            build_status_args(UntrackedMode::Normal),
+            ["status", "--porcelain", "--untracked-files=normal"]
+        );
+        assert_eq!(
+            build_status_args(UntrackedMode::All),
+            ["status", "--porcelain", "--untracked-files=all"]
+        );
+    }
+
+    #[test]
+    fn test_status_untracked_not_scanned() {
+        let repo = init_test_repo("untracked-mode");
+        let path = repo.to_string_lossy();
+        std::fs::create_dir_all(repo.join("new")).unwrap();
+        std::fs::write(repo.join("new").join("a.txt"), "a\n").unwrap();
+        std::fs::write(repo.join("new").join("b.txt"), "b\n").unwrap();
+
+        let skipped = get_worktree_status(&path, UntrackedMode::No, None).unwrap();
+        assert_eq!(skipped.untracked, 0);
+        assert!(!skipped.untracked_scanned);
+
+        let normal = get_worktree_status(&path, UntrackedMode::Normal, None).unwrap();
+        assert_eq!(normal.untracked, 1);
+        assert!(normal.untracked_scanned);
+
+        let all = get_worktree_status(&path, UntrackedMode::All, None).unwrap();
+        assert_eq!(all.untracked, 2);
+        assert!(all.untracked_scanned);
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn test_worktree_status_with_cancel_flag() {
+        let repo = init_test_repo("status-cancellable");
+        let path = repo.to_string_lossy();
+        std::fs::write(repo.join("README.md"), "changed\n").unwrap();
+        std::fs::write(repo.join("new.txt"), "new\n").unwrap();
+
+        let status =
+            get_worktree_status(&path, UntrackedMode::Normal, Some(&AtomicBool::new(false)))
+                .unwrap();
+        assert_eq!(status.modified, 1);
+        assert_eq!(status.untracked, 1);
+
+        let err = get_worktree_status(&path, UntrackedMode::Normal, Some(&AtomicBool::new(true)))
+            .unwrap_err();
+        assert!(err.contains("cancelled"));
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn test_output_cancellable_kills_long_running_command() {
+        // Stands in for a git status that would take far longer than the test
+        let mut slow = Command::new("sleep");
+        slow.arg("30");
+        let cancel = std::sync::Arc::new(AtomicBool::new(false));
+        let canceller = {
+            let cancel = cancel.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(100));
+                cancel.store(true, Ordering::SeqCst);
+            })
+        };
+
+        let started = std::time::Instant::now();
+        let result = output_cancellable(&mut slow, &cancel).unwrap();
+        canceller.join().unwrap();
+        assert!(result.is_none());
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    // ==================== parse_status_porcelain tests ====================
+
+    #[test]
+    fn test_status_clean() {
+        let status = parse_status_porcelain("");
+        assert!(status.is_clean);
+        assert_eq!(status.modified, 0);
+        assert_eq!(status.staged, 0);
+        assert_eq!(status.untracked, 0);
+        assert_eq!(status.conflicted, 0);
+    }
+
+    #[test]
+    fn test_status_modified_unstaged() {
+        let status = parse_status_porcelain(" M src/main.rs\n");
+        assert!(!status.is_clean);
+        assert_eq!(status.modified, 1);
+        assert_eq!(status.staged, 0);
+    }
+
+    #[test]
+    fn test_status_staged() {
+        let status = parse_status_porcelain("M  src/main.rs\n");
+        assert!(!status.is_clean);
+        assert_eq!(status.staged, 1);
+        assert_eq!(status.modified, 0);
+    }
+
+    #[test]
+    fn test_status_added_staged() {
+        let status = parse_status_porcelain("A  new_file.rs\n");
+        assert!(!status.is_clean);
+        assert_eq!(status.staged, 1);
+    }
+
+    #[test]
+    fn test_status_deleted_staged() {
+        let status = parse_status_porcelain("D  old_file.rs\n");
+        assert!(!status.is_clean);
+        assert_eq!(status.staged, 1);
+    }
+
+    #[test]
+    fn test_status_untracked() {
+        let status = parse_status_porcelain("?? untracked.txt\n");
+        assert!(!status.is_clean);
+        assert_eq!(status.untracked, 1);
+    }
+
+    #[test]
+    fn test_status_conflicts() {
+        // UU - both modified (merge conflict)
+        let status = parse_status_porcelain("UU conflicted.rs\n");
+        assert!(!status.is_clean);
+        assert_eq!(status.conflicted, 1);
+
+        // AA - both added
+        let status = parse_status_porcelain("AA both_added.rs\n");
+        assert_eq!(status.conflicted, 1);
+
+        // DD - both deleted
+        let status = parse_status_porcelain("DD both_deleted.rs\n");
+        assert_eq!(status.conflicted, 1);
+    }
+
+    #[test]
+    fn test_status_mixed() {
+        let output = "M  staged.rs\n M modified.rs\n?? untracked.txt\nUU conflict.rs\n";
+        let status = parse_status_porcelain(output);
+        assert!(!status.is_clean);
+        assert_eq!(status.staged, 1);
+        assert_eq!(status.modified, 1);
+        assert_eq!(status.untracked, 1);
+        assert_eq!(status.conflicted, 1);
+    }
+
+    // ==================== build_log_args tests ====================
+
+    #[test]
+    fn test_log_args_default() {
+        let args = build_log_args(50, 0, false, false);
+        assert_eq!(args[0], "log");
+        assert!(args.contains(&"--skip=0".to_string()));
+        assert!(args.contains(&"-n50".to_string()));
+        assert!(!args.contains(&"--no-merges".to_string()));
+        assert!(!args.contains(&"--first-parent".to_string()));
+    }
+
+    #[test]
+    fn test_log_args_no_merges() {
+        let args = build_log_args(50, 100, true, false);
+        assert!(args.contains(&"--skip=100".to_string()));
+        assert!(args.contains(&"--no-merges".to_string()));
+        assert!(!args.contains(&"--first-parent".to_string()));
+    }
+
+    #[test]
+    fn test_log_args_first_parent() {
+        let args = build_log_args(50, 0, false, true);
+        assert!(!args.contains(&"--no-merges".to_string()));
+        assert!(args.contains(&"--first-parent".to_string()));
+
+        let args = build_log_args(50, 0, true, true);
+        assert!(args.contains(&"--no-merges".to_string()));
+        assert!(args.contains(&"--first-parent".to_string()));
+    }
+
+    // ==================== get_commit_oneline tests ====================
+
+    #[test]
+    fn test_parse_commit_oneline() {
+        let output = "abc1234\x1fAdd feature\x1fHEAD -> main, origin/main, tag: v1.0\x1e\n\
+                      def5678\x1fFix: a, b and c\x1f\x1e\n";
+        let commits = parse_commit_oneline(output);
+        assert_eq!(commits.len(), 2);
+
+        assert_eq!(commits[0].short_hash, "abc1234");
+        assert_eq!(commits[0].summary, "Add feature");
+        assert_eq!(
+            commits[0].refs,
+            vec!["HEAD -> main", "origin/main", "tag: v1.0"]
+        );
+
+        assert_eq!(commits[1].summary, "Fix: a, b and c");
+        assert!(commits[1].refs.is_empty());
+        assert!(parse_commit_oneline("").is_empty());
+    }
+
+    // ==================== format_patch tests ====================
+
+    #[test]
+    fn test_format_patch_args_single() {
+        let args = build_format_patch_args("abc123", None);
+        assert_eq!(args, vec!["format-patch", "--stdout", "-1", "abc123"]);
+    }
+
+    #[test]
+    fn test_format_patch_args_range() {
+        let args = build_format_patch_args("abc123", Some("main"));
+        assert_eq!(args, vec!["format-patch", "--stdout", "main..abc123"]);
+    }
+
+    #[test]
+    fn test_format_patch_output() {
+        let repo = init_test_repo("format-patch");
+        let path = repo.to_string_lossy();
+
+        let patch = format_patch(&path, "HEAD", None).unwrap();
+        assert!(patch.starts_with("From "));
+        assert!(patch.contains("Subject: [PATCH] Initial commit"));
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== apply_mailbox tests ====================
+
+    #[test]
+    fn test_apply_mailbox_from_stdin() {
+        let repo = init_test_repo("apply-mailbox");
+        let path = repo.to_string_lossy();
+
+        std::fs::write(repo.join("README.md"), "hello\nworld\n").unwrap();
+        run_git(&path, &["commit", "-q", "-am", "Add world"]).unwrap();
+        let patch = format_patch(&path, "HEAD", None).unwrap();
+        run_git(&path, &["reset", "-q", "--hard", "HEAD~1"]).unwrap();
+
+        let result = apply_mailbox(&path, patch, false).unwrap();
+        assert_eq!(result.applied, 1);
+        assert!(!result.conflicts);
+
+        let summary = run_git(&path, &["log", "-1", "--format=%s"]).unwrap();
+        assert_eq!(summary.trim(), "Add world");
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn test_apply_mailbox_conflict() {
+        let repo = init_test_repo("apply-mailbox-conflict");
+        let path = repo.to_string_lossy();
+
+        std::fs::write(repo.join("README.md"), "hello\nworld\n").unwrap();
+        run_git(&path, &["commit", "-q", "-am", "Add world"]).unwrap();
+        let patch = format_patch(&path, "HEAD", None).unwrap();
+        run_git(&path, &["reset", "-q", "--hard", "HEAD~1"]).unwrap();
+
+        std::fs::write(repo.join("README.md"), "goodbye\n").unwrap();
+        run_git(&path, &["commit", "-q", "-am", "Diverge"]).unwrap();
+
+        let result = apply_mailbox(&path, patch, false).unwrap();
+        assert_eq!(result.applied, 0);
+        assert!(result.conflicts);
+
+        am_abort(&path).unwrap();
+        assert!(!is_am_in_progress(&path));
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== get_branch_reflog tests ====================
+
+    #[test]
+    fn test_branch_reflog_args() {
+        assert_eq!(
+            build_branch_reflog_args("refs/heads/feature/x", 5),
+            vec![
+                "reflog",
+                "show",
+                "--date=unix",
+                "--format=%gd%x1f%H%x1f%gs%x1e",
+                "-n6",
+                "refs/heads/feature/x",
+                "--"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_branch_reflog() {
+        let repo = init_test_repo("branch-reflog");
+        let path = repo.to_string_lossy();
+        run_git(&path, &["branch", "feature"]).unwrap();
+        run_git(&path, &["commit", "-q", "--allow-empty", "-m", "Main only"]).unwrap();
+
+        // Only the branch creation, not main's later commit
+        let entries = get_branch_reflog(&path, "feature", 10).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].action, "branch");
+
+        let main = get_branch_reflog(&path, "refs/heads/main", 1).unwrap();
+        assert_eq!(main.len(), 1);
+        assert_eq!(main[0].message, "Main only");
+
+        assert!(get_branch_reflog(&path, "missing", 10).is_err());
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn test_get_branch_reflog_empty() {
+        let repo = init_test_repo("branch-reflog-empty");
+        let path = repo.to_string_lossy();
+        run_git(&path, &["branch", "nolog"]).unwrap();
+        std::fs::remove_file(repo.join(".git/logs/refs/heads/nolog")).unwrap();
+
+        assert!(get_branch_reflog(&path, "nolog", 10).unwrap().is_empty());
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== parse_reflog tests ====================
+
+    #[test]
+    fn test_reflog_reset_and_commit() {
+        let output = "HEAD@{1700000200}\x1fbbb\x1freset: moving to HEAD~1\x1e\n\
+                      HEAD@{1700000100}\x1fccc\x1fcommit: Add feature\x1e\n\
+                      HEAD@{1700000000}\x1fbbb\x1fcommit (initial): Initial commit\x1e\n";
+        let entries = parse_reflog(output);
+        assert_eq!(entries.len(), 3);
+
+        assert_eq!(entries[0].selector, "HEAD@{0}");
+        assert_eq!(entries[0].action, "reset");
+        assert_eq!(entries[0].message, "moving to HEAD~1");
+        assert_eq!(entries[0].new_sha, "bbb");
+        assert_eq!(entries[0].old_sha, "ccc");
+        assert_eq!(entries[0].timestamp, 1700000200);
+
+        assert_eq!(entries[1].selector, "HEAD@{1}");
+        assert_eq!(entries[1].action, "commit");
+        assert_eq!(entries[1].message, "Add feature");
+        assert_eq!(entries[1].old_sha, "bbb");
+
+        assert_eq!(entries[2].action, "commit (initial)");
+        assert_eq!(entries[2].old_sha, "0".repeat(40));
+    }
+
+    #[test]
+    fn test_parse_recent_branches() {
+        let output = "checkout: moving from main to feature/login\n\
+                      commit: Add form\n\
+                      checkout: moving from fix-typo to main\n\
+                      checkout: moving from main to 0123456789abcdef0123456789abcdef01234567\n\
+                      checkout: moving from main to abc1234\n\
+                      checkout: moving from main to v1.2.0\n\
+                      checkout: moving from main to origin/main\n\
+                      checkout: moving from main to deleted-branch\n\
+                      checkout: moving from feature/login to fix-typo\n\
+                      checkout: moving from main to feature/login\n\
+                      reset: moving to HEAD~1\n";
+        let local: Vec<String> = ["main", "feature/login", "fix-typo"]
+            .iter()
+            .map(|b| b.to_string())
+            .collect();
+        assert_eq!(
+            parse_recent_branches(output, &local, 10),
+            vec!["feature/login", "main", "fix-typo"]
+        );
+        assert_eq!(
+            parse_recent_branches(output, &local, 2),
+            vec!["feature/login", "main"]
+        );
+        assert!(parse_recent_branches("", &local, 5).is_empty());
+    }
+
+    #[test]
+    fn test_recent_branches_skips_detached_checkouts() {
+        let repo = init_test_repo("recent-branches");
+        let path = repo.to_string_lossy();
+        run_git(&path, &["branch", "feature"]).unwrap();
+        run_git(&path, &["tag", "v1.0.0"]).unwrap();
+        let short_sha = run_git(&path, &["rev-parse", "--short", "HEAD"]).unwrap();
+
+        run_git(&path, &["checkout", "-q", "feature"]).unwrap();
+        run_git(&path, &["checkout", "-q", short_sha.trim()]).unwrap();
+        run_git(&path, &["checkout", "-q", "v1.0.0"]).unwrap();
+        run_git(&path, &["checkout", "-q", "main"]).unwrap();
+
+        assert_eq!(recent_branches(&path, 10).unwrap(), vec!["main", "feature"]);
+        assert_eq!(recent_branches(&path, 1).unwrap(), vec!["main"]);
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn test_reflog_empty() {
+        assert!(parse_reflog("").is_empty());
+    }
+
+    // ==================== is_git_repository tests ====================
+
+    #[test]
+    fn test_is_git_repository_repo_root() {
+        let repo = init_test_repo("probe-root");
+        let probe = is_git_repository(&repo.to_string_lossy()).unwrap();
+        assert!(probe.is_repo);
+        assert!(probe.is_worktree);
+        let toplevel = PathBuf::from(probe.toplevel.unwrap());
+        assert_eq!(
+            toplevel.canonicalize().unwrap(),
+            repo.canonicalize().unwrap()
+        );
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn test_is_git_repository_subdir() {
+        let repo = init_test_repo("probe-subdir");
+        let subdir = repo.join("nested").join("deeper");
+        std::fs::create_dir_all(&subdir).unwrap();
+
+        let probe = is_git_repository(&subdir.to_string_lossy()).unwrap();
+        assert!(probe.is_repo);
+        let toplevel = PathBuf::from(probe.toplevel.unwrap());
+        assert_eq!(
+            toplevel.canonicalize().unwrap(),
+            repo.canonicalize().unwrap()
+        );
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn test_is_git_repository_non_repo() {
+        let dir = temp_path("nonrepo");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let probe = is_git_repository(&dir.to_string_lossy()).unwrap();
+        assert!(!probe.is_repo);
+        assert!(!probe.is_worktree);
+        assert!(probe.toplevel.is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    // ==================== prune tests ====================
+
+    #[test]
+    fn test_prune_output_english() {
+        let output = "Removing worktrees/feature: gitdir file points to non-existent location\n\
+                      Removing worktrees/old: not a valid directory\n";
+        let messages = parse_prune_output(output);
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].contains("worktrees/feature"));
+    }
+
+    #[test]
+    fn test_prune_output_non_english() {
+        // Simulated German output; the count must not depend on message wording
+        let output =
+            "Entferne worktrees/feature: gitdir-Datei zeigt auf einen nicht existierenden Ort\n\n";
+        let messages = parse_prune_output(output);
+        assert_eq!(messages.len(), 1);
+    }
+
+    #[test]
+    fn test_prune_worktrees_counts_stale() {
+        let repo = init_test_repo("prune");
+        let path = repo.to_string_lossy();
+        let linked = temp_path("prune-wt");
+        let linked_str = linked.to_string_lossy();
+
+        run_git(&path, &["worktree", "add", "-q", &linked_str]).unwrap();
+        std::fs::remove_dir_all(&linked).unwrap();
+
+        let result = prune_worktrees(&path).unwrap();
+        assert_eq!(result.pruned_count, 1);
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn test_find_missing_worktrees() {
+        let repo = init_test_repo("missing-wt");
+        let path = repo.to_string_lossy();
+        let kept = temp_path("missing-wt-kept");
+        let deleted = temp_path("missing-wt-deleted");
+        run_git(&path, &["worktree", "add", "-q", &kept.to_string_lossy()]).unwrap();
+        run_git(
+            &path,
+            &["worktree", "add", "-q", &deleted.to_string_lossy()],
+        )
+        .unwrap();
+
+        assert!(find_missing_worktrees(&path).unwrap().is_empty());
+
+        let deleted_canonical = deleted.canonicalize().unwrap();
+        std::fs::remove_dir_all(&deleted).unwrap();
+        let missing = find_missing_worktrees(&path).unwrap();
+        assert_eq!(
+            missing,
+            vec![deleted_canonical.to_string_lossy().to_string()]
+        );
+
+        let _ = std::fs::remove_dir_all(&kept);
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== parse_branches_containing tests ====================
+
+    #[test]
+    fn test_branches_containing_skips_detached() {
+        let output = "(HEAD detached at abc1234)\nmain\nfeature/login\norigin/main\n";
+        let branches = parse_branches_containing(output);
+        assert_eq!(branches, vec!["main", "feature/login", "origin/main"]);
+    }
+
+    #[test]
+    fn test_branches_containing_skips_remote_head() {
+        let output = "main\norigin/HEAD\norigin/main\n";
+        let branches = parse_branches_containing(output);
+        assert_eq!(branches, vec!["main", "origin/main"]);
+    }
+
+    #[test]
+    fn test_branches_containing_empty() {
+        assert!(parse_branches_containing("").is_empty());
+    }
+
+    #[test]
+    fn test_branches_containing() {
+        let repo = init_test_repo("branches-containing");
+        let path = repo.to_string_lossy();
+        let base = run_git(&path, &["rev-parse", "HEAD"]).unwrap();
+        run_git(&path, &["checkout", "-q", "-b", "feature"]).unwrap();
+        run_git(&path, &["commit", "-q", "--allow-empty", "-m", "Feature"]).unwrap();
+
+        assert_eq!(
+            branches_containing(&path, base.trim()).unwrap(),
+            vec!["feature", "main"]
+        );
+        assert_eq!(
+            branches_containing(&path, "feature").unwrap(),
+            vec!["feature"]
+        );
+        assert_eq!(
+            branches_containing(&path, "--merged"),
+            Err("Invalid revision: --merged".to_string())
+        );
+        assert!(branches_containing(&path, "").is_err());
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== create_worktree_from_remote tests ====================
+
+    #[test]
+    fn test_derive_local_branch_name() {
+        let remotes = vec!["origin".to_string(), "upstream".to_string()];
+        assert_eq!(
+            derive_local_branch_name("origin/feature", &remotes),
+            Some("feature".to_string())
+        );
+        assert_eq!(
+            derive_local_branch_name("upstream/fix/login", &remotes),
+            Some("fix/login".to_string())
+        );
+        assert_eq!(
+            derive_local_branch_name("refs/remotes/origin/main", &remotes),
+            Some("main".to_string())
+        );
+    }
+
+    #[test]
+    fn test_derive_local_branch_name_remote_with_slash() {
+        let remotes = vec!["team/shared".to_string()];
+        assert_eq!(
+            derive_local_branch_name("team/shared/feature", &remotes),
+            Some("feature".to_string())
+        );
+    }
+
+    #[test]
+    fn test_derive_local_branch_name_invalid() {
+        let remotes = vec!["origin".to_string()];
+        assert_eq!(derive_local_branch_name("feature", &remotes), None);
+        assert_eq!(derive_local_branch_name("origin/", &remotes), None);
+        assert_eq!(derive_local_branch_name("origin/HEAD", &remotes), None);
+    }
+
+    #[test]
+    fn test_unique_branch_name() {
+        let existing = vec![
+            "main".to_string(),
+            "feature".to_string(),
+            "feature-2".to_string(),
+        ];
+        assert_eq!(unique_branch_name("bugfix", &existing), "bugfix");
+        assert_eq!(unique_branch_name("feature", &existing), "feature-3");
+        assert_eq!(unique_branch_name("main", &existing), "main-2");
+    }
+
+    #[test]
+    fn test_remote_worktree_args() {
+        let args = build_remote_worktree_args("feature", "/tmp/wt", "origin/feature");
+        assert_eq!(args[..4], ["worktree", "add", "--track", "-b"]);
+        assert_eq!(args[4..], ["feature", "/tmp/wt", "origin/feature"]);
+    }
+
+    // ==================== get_git_dirs tests ====================
+
+    #[test]
+    fn test_git_dirs_linked_worktree() {
+        let repo = init_test_repo("git-dirs");
+        let path = repo.to_string_lossy();
+        let linked = temp_path("git-dirs-wt");
+        let linked_str = linked.to_string_lossy();
+        run_git(&path, &["worktree", "add", "-q", &linked_str]).unwrap();
+
+        let (git_dir, common_dir) = get_git_dirs(&path).unwrap();
+        assert_eq!(git_dir, common_dir);
+
+        let (git_dir, common_dir) = get_git_dirs(&linked_str).unwrap();
+        assert_ne!(git_dir, common_dir);
+        assert!(git_dir.join("HEAD").exists());
+        assert!(git_dir.starts_with(common_dir.join("worktrees")));
+
+        let _ = std::fs::remove_dir_all(&linked);
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== create_worktree tests ====================
+
+    fn create_options(path: &str) -> CreateWorktreeOptions {
+        CreateWorktreeOptions {
+            path: path.to_string(),
+            new_branch: None,
+            commit_ish: None,
+            detach: false,
+            orphan: false,
+        }
+    }
+
+    #[test]
+    fn test_create_worktree_args_new_branch() {
+        let options = CreateWorktreeOptions {
+            new_branch: Some("feature".to_string()),
+            commit_ish: Some("main".to_string()),
+            ..create_options("/tmp/wt")
+        };
+        let args = build_create_worktree_args(&options, false);
+        assert_eq!(
+            args,
+            vec!["worktree", "add", "-b", "feature", "/tmp/wt", "main"]
+        );
+    }
+
+    #[test]
+    fn test_create_worktree_args_detach() {
+        let options = CreateWorktreeOptions {
+            detach: true,
+            commit_ish: Some("abc123".to_string()),
+            ..create_options("/tmp/wt")
+        };
+        let args = build_create_worktree_args(&options, false);
+        assert_eq!(
+            args,
+            vec!["worktree", "add", "--detach", "/tmp/wt", "abc123"]
+        );
+    }
+
+    #[test]
+    fn test_create_worktree_args_orphan_native() {
+        let options = CreateWorktreeOptions {
+            new_branch: Some("gh-pages".to_string()),
+            orphan: true,
+            ..create_options("/tmp/wt")
+        };
+        let args = build_create_worktree_args(&options, true);
+        assert_eq!(
+            args,
+            vec!["worktree", "add", "--orphan", "-b", "gh-pages", "/tmp/wt"]
+        );
+    }
+
+    #[test]
+    fn test_create_worktree_args_orphan_fallback() {
+        let options = CreateWorktreeOptions {
+            new_branch: Some("gh-pages".to_string()),
+            orphan: true,
+            ..create_options("/tmp/wt")
+        };
+        let args = build_create_worktree_args(&options, false);
+        assert_eq!(args, vec!["worktree", "add", "--detach", "/tmp/wt"]);
+    }
+
+    #[test]
+    fn test_validate_orphan_options() {
+        let orphan = CreateWorktreeOptions {
+            new_branch: Some("docs".to_string()),
+            orphan: true,
+            ..create_options("/tmp/wt")
+        };
+        assert!(validate_create_options(&orphan).is_ok());
+
+        let no_branch = CreateWorktreeOptions {
+            new_branch: None,
+            ..orphan.clone()
+        };
+        assert!(validate_create_options(&no_branch).is_err());
+
+        let with_commit = CreateWorktreeOptions {
+            commit_ish: Some("main".to_string()),
+            ..orphan.clone()
+        };
+        assert!(validate_create_options(&with_commit).is_err());
+
+        let detached = CreateWorktreeOptions {
+            detach: true,
+            ..orphan
+        };
+        assert!(validate_create_options(&detached).is_err());
+
+        // Non-orphan combinations are left to git
+        assert!(validate_create_options(&create_options("/tmp/wt")).is_ok());
+    }
+
+    #[test]
+    fn test_supports_worktree_orphan() {
+        assert!(supports_worktree_orphan("git version 2.42.0\n"));
+        assert!(supports_worktree_orphan("git version 2.50.1"));
+        assert!(supports_worktree_orphan("git version 3.0.0"));
+        assert!(!supports_worktree_orphan(
+            "git version 2.39.3 (Apple Git-146)"
+        ));
+        assert!(!supports_worktree_orphan("garbage"));
+    }
+
+    // ==================== parse_submodule_status tests ====================
+
+    #[test]
+    fn test_submodule_status_states() {
+        let output = " 1111111111111111111111111111111111111111 libs/clean (v1.0.0)\n\
+                      -2222222222222222222222222222222222222222 libs/uninit\n\
+                      +3333333333333333333333333333333333333333 libs/outdated (v1.0.0-3-g3333333)\n\
+                      U4444444444444444444444444444444444444444 libs/conflict\n";
+        let subs = parse_submodule_status(output);
+        assert_eq!(subs.len(), 4);
+
+        assert_eq!(subs[0].path, "libs/clean");
+        assert_eq!(subs[0].sha, "1111111111111111111111111111111111111111");
+        assert!(matches!(subs[0].state, SubmoduleState::Clean));
+
+        assert_eq!(subs[1].path, "libs/uninit");
+        assert!(matches!(subs[1].state, SubmoduleState::Uninitialized));
+
+        assert_eq!(subs[2].path, "libs/outdated");
+        assert!(matches!(subs[2].state, SubmoduleState::OutOfDate));
+
+        assert_eq!(subs[3].path, "libs/conflict");
+        assert!(matches!(subs[3].state, SubmoduleState::MergeConflict));
+    }
+
+    #[test]
+    fn test_submodule_status_path_with_spaces() {
+        let output = " abc123 vendor/my lib (heads/main)\n";
+        let subs = parse_submodule_status(output);
+        assert_eq!(subs.len(), 1);
+        assert_eq!(subs[0].path, "vendor/my lib");
+    }
+
+    #[test]
+    fn test_submodule_status_empty() {
+        assert!(parse_submodule_status("").is_empty());
+    }
+
+    // ==================== assign_graph_lanes tests ====================
+
+    fn graph_commit(hash: &str, parents: &[&str]) -> CommitInfo {
+        CommitInfo {
+            hash: hash.to_string(),
+            short_hash: hash.to_string(),
+            author_name: String::new(),
+            author_email: String::new(),
+            timestamp: 0,
+            author_timestamp: 0,
+            message: String::new(),
+            summary: String::new(),
+            parent_hashes: parents.iter().map(|p| p.to_string()).collect(),
+            trailers: Vec::new(),
+        }
+    }
+
+    fn edges(row: &GraphRow) -> Vec<(u32, u32)> {
+        row.edges.iter().map(|e| (e.from_lane, e.to_lane)).collect()
+    }
+
+    #[test]
+    fn test_graph_linear_history() {
+        let commits = vec![
+            graph_commit("c", &["b"]),
+            graph_commit("b", &["a"]),
+            graph_commit("a", &[]),
+        ];
+        let rows = assign_graph_lanes(&commits);
+        assert!(rows.iter().all(|r| r.lane == 0));
+        assert_eq!(edges(&rows[0]), vec![(0, 0)]);
+        assert_eq!(edges(&rows[1]), vec![(0, 0)]);
+        assert!(rows[2].edges.is_empty());
+    }
+
+    #[test]
+    fn test_graph_branch_and_merge() {
+        // m merges a and b, which both fork from root x
+        let commits = vec![
+            graph_commit("m", &["a", "b"]),
+            graph_commit("a", &["x"]),
+            graph_commit("b", &["x"]),
+            graph_commit("x", &[]),
+        ];
+        let rows = assign_graph_lanes(&commits);
+        let lanes: Vec<u32> = rows.iter().map(|r| r.lane).collect();
+        assert_eq!(lanes, vec![0, 0, 1, 0]);
+
+        assert_eq!(edges(&rows[0]), vec![(0, 0), (0, 1)]);
+        assert_eq!(edges(&rows[1]), vec![(1, 1), (0, 0)]);
+        // b's line joins x's existing line in lane 0
+        assert_eq!(edges(&rows[2]), vec![(0, 0), (1, 0)]);
+        assert!(rows[3].edges.is_empty());
+    }
+
+    #[test]
+    fn test_graph_octopus_merge() {
+        let commits = vec![
+            graph_commit("m", &["a", "b", "c"]),
+            graph_commit("c", &["x"]),
+            graph_commit("b", &["x"]),
+            graph_commit("a", &["x"]),
+            graph_commit("x", &[]),
+        ];
+        let rows = assign_graph_lanes(&commits);
+        assert_eq!(rows[0].lane, 0);
+        // Each parent gets its own lane; c is next so its edge lands on c's lane
+        assert_eq!(edges(&rows[0]), vec![(0, 0), (0, 1), (0, 2)]);
+        assert_eq!(rows[1].lane, 2);
+        assert_eq!(rows[2].lane, 1);
+        assert_eq!(rows[3].lane, 0);
+        // x inherited c's line, and every remaining line converges on it
+        assert_eq!(rows[4].lane, 2);
+        assert_eq!(edges(&rows[3]), vec![(2, 2), (0, 2)]);
+    }
+
+    #[test]
+    fn test_graph_disconnected_tip() {
+        // Second tip with no child in the window opens a new lane
+        let commits = vec![
+            graph_commit("a", &["x"]),
+            graph_commit("b", &["x"]),
+            graph_commit("x", &[]),
+        ];
+        let rows = assign_graph_lanes(&commits);
+        assert_eq!(rows[0].lane, 0);
+        assert_eq!(rows[1].lane, 1);
+        assert_eq!(edges(&rows[1]), vec![(0, 0), (1, 0)]);
+    }
+
+    // ==================== compare_branches tests ====================
+
+    #[test]
+    fn test_range_log_args() {
+        let args = build_range_log_args("main", "feature", 25);
+        assert_eq!(args[0], "log");
+        assert!(args[1].starts_with("--format="));
+        assert_eq!(args[2], "-n25");
+        assert_eq!(args[3], "main..feature");
+        assert_eq!(args[4], "--");
+    }
+
+    #[test]
+    fn test_compare_branches() {
+        let repo = init_test_repo("compare-branches");
+        let path = repo.to_string_lossy();
+        let base = run_git(&path, &["rev-parse", "HEAD"]).unwrap();
+
+        run_git(&path, &["checkout", "-q", "-b", "feature"]).unwrap();
+        run_git(&path, &["commit", "-q", "--allow-empty", "-m", "Feature 1"]).unwrap();
+        run_git(&path, &["commit", "-q", "--allow-empty", "-m", "Feature 2"]).unwrap();
+        run_git(&path, &["checkout", "-q", "main"]).unwrap();
+        run_git(&path, &["commit", "-q", "--allow-empty", "-m", "Main 1"]).unwrap();
+
+        let cmp = compare_branches(&path, "feature", "main", 1).unwrap();
+        assert_eq!(cmp.merge_base, base.trim());
+        assert_eq!(cmp.a_ahead, 2);
+        assert_eq!(cmp.b_ahead, 1);
+        // Lists are capped by limit, counts are not
+        assert_eq!(cmp.a_only.len(), 1);
+        assert_eq!(cmp.a_only[0].summary, "Feature 2");
+        assert_eq!(cmp.b_only[0].summary, "Main 1");
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== unborn HEAD tests ====================
+
+    #[test]
+    fn test_unborn_head_worktree() {
+        let dir = temp_path("unborn");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.to_string_lossy();
+        run_git(&path, &["init", "-q", "-b", "trunk"]).unwrap();
+
+        let worktrees = get_all_worktrees(&path, None).unwrap();
+        assert_eq!(worktrees.len(), 1);
+        let head = &worktrees[0].head;
+        assert!(head.unborn);
+        assert_eq!(head.branch.as_deref(), Some("trunk"));
+        assert!(head.commit_sha.is_empty());
+        assert!(head.commit_message.is_empty());
+        assert_eq!(worktrees[0].last_commit_timestamp, 0);
+
+        let history = get_commit_history(&path, 10, 0, false, false).unwrap();
+        assert!(history.is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_born_head_not_unborn() {
+        let repo = init_test_repo("born");
+        let head = get_head_info(&repo.to_string_lossy()).unwrap();
+        assert!(!head.unborn);
+        assert_eq!(head.branch.as_deref(), Some("main"));
+        assert_eq!(head.commit_message, "Initial commit");
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn test_create_orphan_worktree() {
+        let repo = init_test_repo("orphan");
+        let linked = temp_path("orphan-wt");
+        let options = CreateWorktreeOptions {
+            new_branch: Some("gh-pages".to_string()),
+            orphan: true,
+            ..create_options(&linked.to_string_lossy())
+        };
+
+        let worktree = create_worktree(&repo.to_string_lossy(), options)
+            .unwrap()
+            .worktree;
+        assert!(worktree.head.unborn);
+        assert_eq!(worktree.head.branch.as_deref(), Some("gh-pages"));
+        // The orphan tree starts empty
+        assert!(!linked.join("README.md").exists());
+
+        let _ = std::fs::remove_dir_all(&linked);
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn test_parse_worktree_add_warnings() {
+        let stderr = "Preparing worktree (new branch 'feature')\n\
+                      Updating files:  50% (1/2)\rUpdating files: 100% (2/2), done.\n\
+                      warning: refname 'feature' is ambiguous.\n\n";
+        assert_eq!(
+            parse_worktree_add_warnings(stderr),
+            vec!["warning: refname 'feature' is ambiguous."]
+        );
+        assert!(
+            parse_worktree_add_warnings("Preparing worktree (detached HEAD abc1234)\n").is_empty()
+        );
+    }
+
+    #[test]
+    fn test_parse_prepared_worktree() {
+        assert_eq!(
+            parse_prepared_worktree(
+                "Preparing worktree (new branch 'feature/x')\n\
+                 branch 'feature/x' set up to track 'origin/feature/x'.\n"
+            ),
+            Some(PreparedWorktree::NewBranch("feature/x".to_string()))
+        );
+        assert_eq!(
+            parse_prepared_worktree("Preparing worktree (detached HEAD abc1234)\n"),
+            Some(PreparedWorktree::Detached)
+        );
+        assert_eq!(
+            parse_prepared_worktree("Preparing worktree (checking out 'main')\n"),
+            Some(PreparedWorktree::CheckedOut("main".to_string()))
+        );
+        assert_eq!(
+            parse_prepared_worktree(
+                "Preparing worktree (resetting branch 'dev'; was at abc1234)\n"
+            ),
+            Some(PreparedWorktree::CheckedOut("dev".to_string()))
+        );
+        assert_eq!(
+            parse_prepared_worktree("fatal: invalid reference: nope\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_create_worktree_captures_warnings() {
+        let repo = init_test_repo("create-warnings");
+        let linked = temp_path("create-warnings-wt");
+
+        // A post-checkout hook simulates git printing advice on stderr
+        let hook = repo.join(".git").join("hooks").join("post-checkout");
+        std::fs::write(&hook, "#!/bin/sh\necho 'hint: remember to run setup' >&2\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let options = CreateWorktreeOptions {
+            new_branch: Some("feature".to_string()),
+            ..create_options(&linked.to_string_lossy())
+        };
+        let result = create_worktree(&repo.to_string_lossy(), options).unwrap();
+        assert_eq!(result.warnings, vec!["hint: remember to run setup"]);
+        assert_eq!(result.worktree.head.branch.as_deref(), Some("feature"));
+        assert_eq!(result.created_branch.as_deref(), Some("feature"));
+
+        let _ = std::fs::remove_dir_all(&linked);
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== commit_all tests ====================
+
+    #[test]
+    fn test_commit_all_args() {
+        let args = build_commit_all_args("Fix: handle -m in messages");
+        assert_eq!(
+            args,
+            vec!["commit", "-q", "-a", "-m", "Fix: handle -m in messages"]
+        );
+    }
+
+    #[test]
+    fn test_commit_all_excludes_untracked() {
+        let repo = init_test_repo("commit-all");
+        let path = repo.to_string_lossy();
+
+        std::fs::write(repo.join("README.md"), "changed\n").unwrap();
+        std::fs::write(repo.join("untracked.txt"), "new\n").unwrap();
+
+        let commit = commit_all(&path, "Update readme".to_string()).unwrap();
+        assert_eq!(commit.summary, "Update readme");
+        assert_eq!(commit.parent_hashes.len(), 1);
+
+        // Tracked change committed, untracked file left alone (-a semantics)
+        let status = run_git(&path, &["status", "--porcelain"]).unwrap();
+        assert_eq!(status.trim(), "?? untracked.txt");
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== fetch_pull_request tests ====================
+
+    #[test]
+    fn test_pr_refspec_default() {
+        let (refspec, branch) = build_pr_refspec(DEFAULT_PR_REF_PATTERN, 42);
+        assert_eq!(refspec, "+pull/42/head:pr-42");
+        assert_eq!(branch, "pr-42");
+    }
+
+    #[test]
+    fn test_pr_refspec_custom_pattern() {
+        let (refspec, branch) = build_pr_refspec("merge-requests/{number}/head", 7);
+        assert_eq!(refspec, "+merge-requests/7/head:pr-7");
+        assert_eq!(branch, "pr-7");
+    }
+
+    #[test]
+    fn test_fetch_pull_request_missing() {
+        let remote = init_test_repo("pr-remote");
+        let local = init_test_repo("pr-local");
+        let remote_str = remote.to_string_lossy();
+        let local_str = local.to_string_lossy();
+        run_git(&local_str, &["remote", "add", "origin", &remote_str]).unwrap();
+
+        let err = fetch_pull_request(&local_str, "origin", 99, None).unwrap_err();
+        assert!(err.contains("#99"));
+
+        // Option-like and unknown remotes are rejected before any fetch
+        assert_eq!(
+            fetch_pull_request(&local_str, "--upload-pack=touch pwned", 1, None).unwrap_err(),
+            "Invalid remote name: --upload-pack=touch pwned"
+        );
+        assert_eq!(
+            fetch_pull_request(&local_str, "upstream", 1, None).unwrap_err(),
+            "Remote 'upstream' does not exist"
+        );
+
+        // Simulate a PR ref on the remote and fetch it
+        run_git(&remote_str, &["update-ref", "refs/pull/5/head", "HEAD"]).unwrap();
+        let branch = fetch_pull_request(&local_str, "origin", 5, None).unwrap();
+        assert_eq!(branch, "pr-5");
+        assert!(run_git(&local_str, &["rev-parse", "--verify", "pr-5"]).is_ok());
+
+        let _ = std::fs::remove_dir_all(&remote);
+        let _ = std::fs::remove_dir_all(&local);
+    }
+
+    // ==================== mark_resolved tests ====================
+
+    #[test]
+    fn test_has_conflict_markers() {
+        let conflicted = "a\n<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> feature\nb\n";
+        assert!(has_conflict_markers(conflicted));
+        assert!(!has_conflict_markers("a\nb\n"));
+        // Setext heading underline is not a conflict
+        assert!(!has_conflict_markers("Title\n=======\n"));
+        // Markers must start the line
+        assert!(!has_conflict_markers("let s = \"<<<<<<< x\";\n"));
+    }
+
+    #[test]
+    fn test_mark_resolved_refreshes_status() {
+        let repo = init_test_repo("mark-resolved");
+        let path = repo.to_string_lossy();
+        run_git(&path, &["checkout", "-q", "-b", "feature"]).unwrap();
+        std::fs::write(repo.join("README.md"), "feature\n").unwrap();
+        run_git(&path, &["commit", "-q", "-am", "feature"]).unwrap();
+        run_git(&path, &["checkout", "-q", "main"]).unwrap();
+        std::fs::write(repo.join("README.md"), "main\n").unwrap();
+        run_git(&path, &["commit", "-q", "-am", "main"]).unwrap();
+        assert!(run_git(&path, &["merge", "-q", "feature"]).is_err());
+        assert_eq!(
+            get_worktree_status(&path, UntrackedMode::default(), None)
+                .unwrap()
+                .conflicted,
+            1
+        );
+
+        // Still has markers: staged anyway, with a warning
+        let result = mark_resolved(&path, vec!["README.md".to_string()]).unwrap();
+        assert_eq!(result.warnings.len(), 1);
+        assert!(result.warnings[0].contains("README.md"));
+        assert_eq!(result.status.conflicted, 0);
+        assert_eq!(result.status.staged, 1);
+
+        std::fs::write(repo.join("README.md"), "resolved\n").unwrap();
+        let result = mark_resolved(&path, vec!["README.md".to_string()]).unwrap();
+        assert!(result.warnings.is_empty());
+        assert_eq!(result.status.conflicted, 0);
+        assert_eq!(result.status.staged, 1);
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== mode change tests ====================
+
+    #[test]
+    fn test_diff_mode_exec_added() {
+        let diff = "diff --git a/run.sh b/run.sh
+old mode 100644
+new mode 100755
+";
+        let files = parse_git_diff_output(diff);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].old_mode.as_deref(), Some("100644"));
+        assert_eq!(files[0].new_mode.as_deref(), Some("100755"));
+        assert!(files[0].hunks.is_empty());
+        assert_eq!(
+            files[0].chmod,
+            Some(ChmodChange {
+                made_executable: true,
+                removed_executable: false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_diff_mode_exec_removed() {
+        let diff = "diff --git a/run.sh b/run.sh
+old mode 100755
+new mode 100644
+index abc123..def456
+--- a/run.sh
++++ b/run.sh
+@@ -1 +1 @@
+-echo old
++echo new
+";
+        let files = parse_git_diff_output(diff);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].hunks.len(), 1);
+        assert_eq!(
+            files[0].chmod,
+            Some(ChmodChange {
+                made_executable: false,
+                removed_executable: true,
+            })
+        );
+    }
+
+    #[test]
+    fn test_diff_mode_unrelated_change() {
+        let change = chmod_change("100644", "120000");
+        assert!(!change.made_executable);
+        assert!(!change.removed_executable);
+
+        // No mode headers at all
+        let diff = "diff --git a/file.rs b/file.rs
+index abc123..def456 100644
+--- a/file.rs
++++ b/file.rs
+@@ -1 +1 @@
+-a
++b
+";
+        let files = parse_git_diff_output(diff);
+        assert!(files[0].old_mode.is_none());
+        assert!(files[0].chmod.is_none());
+    }
+
+    // ==================== diff_name_only tests ====================
+
+    #[test]
+    fn test_parse_name_only() {
+        let output = "src/main.rs\ndocs/release notes.md\nREADME.md\n";
+        assert_eq!(
+            parse_name_only(output),
+            vec!["src/main.rs", "docs/release notes.md", "README.md"]
+        );
+        assert!(parse_name_only("").is_empty());
+    }
+
+    #[test]
+    fn test_diff_name_only_clean() {
+        let repo = init_test_repo("diff-name-only");
+        let path = repo.to_string_lossy();
+        assert!(diff_name_only(&path, "HEAD").unwrap().is_empty());
+
+        std::fs::write(repo.join("README.md"), "changed\n").unwrap();
+        assert_eq!(diff_name_only(&path, "HEAD").unwrap(), vec!["README.md"]);
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== file_count_delta tests ====================
+
+    #[test]
+    fn test_parse_name_status_counts() {
+        let output = "A\tsrc/new.rs\n\
+                      M\tsrc/main.rs\n\
+                      M\tREADME.md\n\
+                      D\told.txt\n\
+                      R100\tdocs/a.md\tdocs/b.md\n\
+                      R087\tlib.rs\tcore.rs\n\
+                      C075\tbase.rs\tcopy.rs\n\
+                      T\tlink\n";
+        let delta = parse_name_status_counts(output);
+        assert_eq!(delta.added, 2);
+        assert_eq!(delta.deleted, 1);
+        assert_eq!(delta.modified, 3);
+        assert_eq!(delta.renamed, 2);
+
+        let empty = parse_name_status_counts("");
+        assert_eq!(
+            (empty.added, empty.deleted, empty.modified, empty.renamed),
+            (0, 0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_file_count_delta() {
+        let repo = init_test_repo("file-count-delta");
+        let path = repo.to_string_lossy();
+        std::fs::write(repo.join("keep.txt"), "a\nb\nc\nd\n").unwrap();
+        run_git(&path, &["add", "keep.txt"]).unwrap();
+        run_git(&path, &["commit", "-q", "-m", "Add keep"]).unwrap();
+        run_git(&path, &["tag", "base"]).unwrap();
+
+        run_git(&path, &["mv", "keep.txt", "kept.txt"]).unwrap();
+        run_git(&path, &["rm", "-q", "README.md"]).unwrap();
+        std::fs::write(repo.join("new.txt"), "new\n").unwrap();
+        run_git(&path, &["add", "new.txt"]).unwrap();
+        run_git(&path, &["commit", "-q", "-m", "Shuffle"]).unwrap();
+
+        let delta = file_count_delta(&path, "base", "HEAD").unwrap();
+        assert_eq!(
+            (delta.added, delta.deleted, delta.modified, delta.renamed),
+            (1, 1, 0, 1)
+        );
+        assert!(file_count_delta(&path, "--cached", "HEAD").is_err());
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== get_working_diff scope tests ====================
+
+    /// Run collect_working_diff with a runner that records each invocation
+    fn recorded_invocations(scope: DiffScope) -> (WorkingDiff, Vec<String>) {
+        let mut calls = Vec::new();
+        let diff = collect_working_diff(scope, false, |args| {
+            calls.push(args.join(" "));
+            if args[0] == "ls-files" {
+                Ok("new.txt\n".to_string())
+            } else {
+                Ok(String::new())
+            }
+        })
+        .unwrap();
+        (diff, calls)
+    }
+
+    #[test]
+    fn test_working_diff_scope_all() {
+        let (diff, calls) = recorded_invocations(DiffScope::All);
+        assert_eq!(
+            calls,
+            vec![
+                "diff --cached -U3",
+                "diff -U3",
+                "ls-files --others --exclude-standard"
+            ]
+        );
+        assert_eq!(diff.unstaged_files.len(), 1);
+    }
+
+    #[test]
+    fn test_working_diff_scope_staged_only() {
+        let (diff, calls) = recorded_invocations(DiffScope::StagedOnly);
+        assert_eq!(calls, vec!["diff --cached -U3"]);
+        assert!(diff.unstaged_files.is_empty());
+    }
+
+    #[test]
+    fn test_working_diff_scope_unstaged_only() {
+        let (diff, calls) = recorded_invocations(DiffScope::UnstagedOnly);
+        assert_eq!(
+            calls,
+            vec!["diff -U3", "ls-files --others --exclude-standard"]
+        );
+        // Untracked files are still included
+        assert_eq!(diff.unstaged_files.len(), 1);
+        assert_eq!(diff.unstaged_files[0].path, "new.txt");
+    }
+
+    // ==================== probe_repo_scale tests ====================
+
+    #[test]
+    fn test_count_porcelain_worktrees() {
+        let output = "worktree /path/to/main
+HEAD abc1234
+branch refs/heads/main
+
+worktree /path/to/linked
+HEAD def5678
+detached
+
+worktree /path/to/bare
+bare
+";
+        assert_eq!(count_porcelain_worktrees(output), 3);
+        assert_eq!(count_porcelain_worktrees(""), 0);
+    }
+
+    // ==================== check_integrity tests ====================
+
+    #[test]
+    fn test_parse_fsck_output() {
+        let output = "Checking object directories\n\
+                      dangling commit 1111111111111111111111111111111111111111\n\
+                      dangling blob 2222222222222222222222222222222222222222\n\
+                      error: object file .git/objects/33/33 is empty\n\
+                      broken link from    tree 4444444444444444444444444444444444444444\n\
+                      \x20             to    blob 5555555555555555555555555555555555555555\n\
+                      missing blob 5555555555555555555555555555555555555555\n";
+        let report = parse_fsck_output(output);
+        assert!(!report.ok);
+        assert_eq!(
+            report.dangling,
+            vec![
+                "commit 1111111111111111111111111111111111111111",
+                "blob 2222222222222222222222222222222222222222"
+            ]
+        );
+        assert_eq!(report.errors.len(), 3);
+        assert_eq!(
+            report.errors[0],
+            "error: object file .git/objects/33/33 is empty"
+        );
+        assert!(report.errors[1].starts_with("broken link from"));
+        assert!(report.errors[1].ends_with("to    blob 5555555555555555555555555555555555555555"));
+        assert!(report.errors[2].starts_with("missing blob"));
+
+        // Dangling objects alone are harmless
+        let report =
+            parse_fsck_output("dangling commit 1111111111111111111111111111111111111111\n");
+        assert!(report.ok);
+        assert!(report.errors.is_empty());
+    }
+
+    #[test]
+    fn test_check_integrity_clean_repo() {
+        let repo = init_test_repo("check-integrity");
+        let report = check_integrity(&repo.to_string_lossy()).unwrap();
+        assert!(report.ok, "unexpected errors: {:?}", report.errors);
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn test_is_large_repo_threshold() {
+        assert!(!is_large_repo(1, Some(10)));
+        assert!(!is_large_repo(
+            LARGE_WORKTREE_COUNT,
+            Some(LARGE_COMMIT_COUNT)
+        ));
+        assert!(is_large_repo(LARGE_WORKTREE_COUNT + 1, Some(10)));
+        assert!(is_large_repo(1, Some(LARGE_COMMIT_COUNT + 1)));
+        // Unknown commit count alone is not large
+        assert!(!is_large_repo(1, None));
+    }
+
+    #[test]
+    fn test_probe_repo_scale() {
+        let repo = init_test_repo("probe-scale");
+        let scale = probe_repo_scale(&repo.to_string_lossy()).unwrap();
+        assert_eq!(scale.worktree_count, 1);
+        assert_eq!(scale.approx_commit_count, Some(1));
+        assert!(!scale.large);
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== set_upstream tests ====================
+
+    #[test]
+    fn test_set_upstream_args() {
+        assert_eq!(
+            build_set_upstream_args("origin/main"),
+            vec!["branch", "--set-upstream-to=origin/main"]
+        );
+    }
+
+    #[test]
+    fn test_set_and_unset_upstream() {
+        let repo = init_test_repo("set-upstream");
+        let path = repo.to_string_lossy();
+
+        let err = set_upstream(&path, "origin/main").unwrap_err();
+        assert!(err.contains("does not exist"));
+
+        // A local branch works as an upstream too
+        run_git(&path, &["branch", "base"]).unwrap();
+        run_git(&path, &["checkout", "-q", "-b", "topic"]).unwrap();
+        set_upstream(&path, "base").unwrap();
+        assert_eq!(get_upstream_info(&path).unwrap().remote_branch, "base");
+
+        unset_upstream(&path).unwrap();
+        assert!(get_upstream_info(&path).is_none());
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== get_outgoing_stat tests ====================
+
+    #[test]
+    fn test_parse_shortstat() {
+        let stats = parse_shortstat(" 3 files changed, 10 insertions(+), 2 deletions(-)\n");
+        assert_eq!(
+            (stats.files_changed, stats.insertions, stats.deletions),
+            (3, 10, 2)
+        );
+
+        let stats = parse_shortstat(" 1 file changed, 1 insertion(+)\n");
+        assert_eq!(
+            (stats.files_changed, stats.insertions, stats.deletions),
+            (1, 1, 0)
+        );
+
+        let stats = parse_shortstat(" 1 file changed, 4 deletions(-)\n");
+        assert_eq!(
+            (stats.files_changed, stats.insertions, stats.deletions),
+            (1, 0, 4)
+        );
+
+        let stats = parse_shortstat("");
+        assert_eq!(
+            (stats.files_changed, stats.insertions, stats.deletions),
+            (0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_get_outgoing_stat() {
+        let repo = init_test_repo("outgoing-stat");
+        let path = repo.to_string_lossy();
+
+        let err = get_outgoing_stat(&path).unwrap_err();
+        assert!(err.contains("no upstream"));
+
+        run_git(&path, &["checkout", "-q", "-b", "topic", "--track", "main"]).unwrap();
+        let stats = get_outgoing_stat(&path).unwrap();
+        assert_eq!(stats.files_changed, 0);
+
+        std::fs::write(repo.join("README.md"), "hello\nmore\n").unwrap();
+        run_git(&path, &["commit", "-q", "-am", "More"]).unwrap();
+        let stats = get_outgoing_stat(&path).unwrap();
+        assert_eq!(
+            (stats.files_changed, stats.insertions, stats.deletions),
+            (1, 1, 0)
+        );
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== get_push_target tests ====================
+
+    #[test]
+    fn test_resolve_push_remote_order() {
+        let some = |s: &str| Some(s.to_string());
+        assert_eq!(
+            resolve_push_remote(some("fork"), some("mirror"), some("upstream")),
+            "fork"
+        );
+        assert_eq!(
+            resolve_push_remote(None, some("mirror"), some("upstream")),
+            "mirror"
+        );
+        assert_eq!(
+            resolve_push_remote(None, None, some("upstream")),
+            "upstream"
+        );
+        assert_eq!(resolve_push_remote(None, None, None), "origin");
+    }
+
+    #[test]
+    fn test_get_push_target() {
+        let repo = init_test_repo("push-target");
+        let path = repo.to_string_lossy();
+
+        // No remotes configured
+        assert!(get_push_target(&path).unwrap().is_none());
+
+        run_git(
+            &path,
+            &[
+                "remote",
+                "add",
+                "origin",
+                "https://example.com/org/repo.git",
+            ],
+        )
+        .unwrap();
+        run_git(
+            &path,
+            &["remote", "add", "fork", "https://example.com/me/repo.git"],
+        )
+        .unwrap();
+        let target = get_push_target(&path).unwrap().unwrap();
+        assert_eq!(target.remote, "origin");
+        assert_eq!(target.url, "https://example.com/org/repo.git");
+        assert_eq!(target.branch, "main");
+
+        run_git(&path, &["config", "branch.main.pushRemote", "fork"]).unwrap();
+        let target = get_push_target(&path).unwrap().unwrap();
+        assert_eq!(target.remote, "fork");
+        assert_eq!(target.url, "https://example.com/me/repo.git");
+
+        // Detached HEAD has nothing to push
+        run_git(&path, &["checkout", "-q", "--detach"]).unwrap();
+        assert!(get_push_target(&path).unwrap().is_none());
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== bisect tests ====================
+
+    #[test]
+    fn test_parse_bisect_progress() {
+        let output = "Bisecting: 3 revisions left to test after this (roughly 2 steps)
+[60b68ff3f0ee3a58bccb452c10025480ddd17814] Add feature
+";
+        let state = parse_bisect_output(output);
+        assert_eq!(state.remaining_steps, Some(2));
+        assert_eq!(
+            state.current_sha.as_deref(),
+            Some("60b68ff3f0ee3a58bccb452c10025480ddd17814")
+        );
+        assert!(!state.finished);
+        assert!(state.first_bad.is_none());
+    }
+
+    #[test]
+    fn test_parse_bisect_waiting() {
+        let state = parse_bisect_output("status: waiting for good commit(s), bad commit known\n");
+        assert!(state.remaining_steps.is_none());
+        assert!(state.current_sha.is_none());
+        assert!(!state.finished);
+    }
+
+    #[test]
+    fn test_parse_bisect_first_bad() {
+        let output = "669db74028b139964091475fae21565e31198db4 is the first bad commit
+commit 669db74028b139964091475fae21565e31198db4
+Author: Test <test@example.com>
+Date:   Thu Oct 15 14:25:38 2026 +0000
+
+    Break things
+";
+        let state = parse_bisect_output(output);
+        assert!(state.finished);
+        assert_eq!(
+            state.first_bad.as_deref(),
+            Some("669db74028b139964091475fae21565e31198db4")
+        );
+        assert_eq!(state.remaining_steps, Some(0));
+    }
+
+    // ==================== get_worktree_config tests ====================
+
+    #[test]
+    fn test_parse_config_list() {
+        let output = "core.sparsecheckout=true\nuser.email=me@example.com\nremote.origin.fetch=+refs/heads/*:refs/remotes/origin/*\nfeature.flag\n";
+        let pairs = parse_config_list(output);
+        assert_eq!(pairs.len(), 4);
+        assert_eq!(
+            pairs[0],
+            ("core.sparsecheckout".to_string(), "true".to_string())
+        );
+        // Only the first "=" separates key and value
+        assert_eq!(pairs[2].1, "+refs/heads/*:refs/remotes/origin/*");
+        assert_eq!(pairs[3], ("feature.flag".to_string(), String::new()));
+    }
+
+    #[test]
+    fn test_worktree_config_disabled_error() {
+        let err = "fatal: --worktree cannot be used with multiple working trees unless the config
+extension worktreeConfig is enabled. Please read \"CONFIGURATION FILE\"";
+        assert!(is_worktree_config_unavailable(err));
+        assert!(is_worktree_config_unavailable(
+            "fatal: unable to read config file '.git/config.worktree': No such file or directory"
+        ));
+        assert!(!is_worktree_config_unavailable(
+            "fatal: not a git repository"
+        ));
+    }
+
+    #[test]
+    fn test_get_worktree_config() {
+        let repo = init_test_repo("worktree-config");
+        let path = repo.to_string_lossy();
+
+        // Extension not enabled: the repo's own config must not leak through
+        assert!(get_worktree_config(&path).unwrap().is_empty());
+
+        run_git(&path, &["config", "extensions.worktreeConfig", "true"]).unwrap();
+        assert!(get_worktree_config(&path).unwrap().is_empty());
+
+        run_git(
+            &path,
+            &["config", "--worktree", "core.sparseCheckout", "true"],
+        )
+        .unwrap();
+        assert_eq!(
+            get_worktree_config(&path).unwrap(),
+            vec![("core.sparsecheckout".to_string(), "true".to_string())]
+        );
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== to_split_diff tests ====================
+
+    fn split_hunk(kinds: &str) -> DiffHunk {
+        DiffHunk {
+            old_start: 1,
+            old_lines: 0,
+            new_start: 1,
+            new_lines: 0,
+            header: "@@ -1 +1 @@".to_string(),
+            additions: 0,
+            deletions: 0,
+            lines: kinds
+                .chars()
+                .enumerate()
+                .map(|(i, kind)| DiffLine {
+                    kind,
+                    content: format!("line{}", i),
+                    old_lineno: None,
+                    new_lineno: None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Render rows as "<left kind><right kind>" with "." for an empty side
+    fn split_shape(rows: &[SplitRow]) -> Vec<String> {
+        let kind = |line: &Option<DiffLine>| line.as_ref().map_or('.', |l| l.kind);
+        rows.iter()
+            .map(|row| format!("{}{}", kind(&row.left), kind(&row.right)))
+            .collect()
+    }
+
+    #[test]
+    fn test_split_diff_pure_addition() {
+        let rows = to_split_diff(&split_hunk(" ++ "));
+        assert_eq!(split_shape(&rows), vec!["  ", ".+", ".+", "  "]);
+    }
+
+    #[test]
+    fn test_split_diff_pure_deletion() {
+        let rows = to_split_diff(&split_hunk(" --"));
+        assert_eq!(split_shape(&rows), vec!["  ", "-.", "-."]);
+    }
+
+    #[test]
+    fn test_split_diff_mixed_replacement() {
+        // Three lines replaced by two, then one replaced by two
+        let rows = to_split_diff(&split_hunk("---++ -++"));
+        assert_eq!(split_shape(&rows), vec!["-+", "-+", "-.", "  ", "-+", ".+"]);
+        // Deletions pair with additions in order
+        assert_eq!(rows[0].left.as_ref().unwrap().content, "line0");
+        assert_eq!(rows[0].right.as_ref().unwrap().content, "line3");
+
+        // Interleaved -+-+ becomes two replacement blocks
+        let rows = to_split_diff(&split_hunk("-+-+"));
+        assert_eq!(split_shape(&rows), vec!["-+", "-+"]);
+        assert_eq!(rows[1].left.as_ref().unwrap().content, "line2");
+        assert_eq!(rows[1].right.as_ref().unwrap().content, "line3");
+    }
+
+    // ==================== main worktree detection tests ====================
+
+    #[test]
+    fn test_main_worktree_not_listed_first() {
+        let output = "worktree /repos/app-feature
+HEAD def5678
+branch refs/heads/feature
+
+worktree /repos/app
+HEAD abc1234
+branch refs/heads/main
+";
+        let paths = parse_worktree_list_paths(output);
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("/repos/app-feature"),
+                PathBuf::from("/repos/app")
+            ]
+        );
+
+        let common_dir = Path::new("/repos/app/.git");
+        let git_dir_of = |path: &Path| {
+            if path == Path::new("/repos/app") {
+                common_dir.to_path_buf()
+            } else {
+                common_dir.join("worktrees").join("app-feature")
+            }
+        };
+        let main: Vec<bool> = paths
+            .iter()
+            .map(|p| is_main_worktree(&git_dir_of(p), common_dir))
+            .collect();
+        assert_eq!(main, vec![false, true]);
+    }
+
+    #[test]
+    fn test_get_all_worktrees_flags_main() {
+        let repo = init_test_repo("main-detect");
+        let linked = temp_path("main-detect-wt");
+        let path = repo.to_string_lossy();
+        run_git(
+            &path,
+            &[
+                "worktree",
+                "add",
+                "-q",
+                "-b",
+                "side",
+                &linked.to_string_lossy(),
+            ],
+        )
+        .unwrap();
+
+        let worktrees = get_all_worktrees(&path, None).unwrap();
+        let main: Vec<&str> = worktrees
+            .iter()
+            .filter(|wt| wt.is_main)
+            .map(|wt| wt.head.branch.as_deref().unwrap_or(""))
+            .collect();
+        assert_eq!(main, vec!["main"]);
+
+        let _ = std::fs::remove_dir_all(&linked);
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== get_recent_worktrees tests ====================
+
+    #[test]
+    fn test_parse_last_reflog_timestamp() {
+        let content = "0000000000000000000000000000000000000000 abc123 Test User <test@example.com> 1700000000 +0000\tcommit (initial): Initial
+abc123 def456 Test User <test@example.com> 1700000500 -0800\tcheckout: moving from main to feature
+";
+        assert_eq!(parse_last_reflog_timestamp(content), Some(1700000500));
+        assert_eq!(parse_last_reflog_timestamp(""), None);
+    }
+
+    #[test]
+    fn test_order_by_activity() {
+        let items = vec![
+            ("a", Some(100)),
+            ("b", None),
+            ("c", Some(300)),
+            ("d", Some(200)),
+        ];
+        assert_eq!(
+            order_by_activity(items.clone(), 10),
+            vec!["c", "d", "a", "b"]
+        );
+        assert_eq!(order_by_activity(items, 2), vec!["c", "d"]);
+    }
+
+    // ==================== list_worktrees_by_attention tests ====================
+
+    fn attention_worktree(
+        name: &str,
+        last_commit_timestamp: i64,
+        status: Option<WorktreeStatus>,
+    ) -> Worktree {
+        Worktree {
+            path: PathBuf::from(format!("/tmp/{}", name)),
+            name: name.to_string(),
+            is_main: false,
+            head: HeadInfo {
+                branch: Some(name.to_string()),
+                commit_sha: String::new(),
+                commit_message: String::new(),
+                upstream: None,
+                unborn: false,
+            },
+            status,
+            last_commit_timestamp,
+            sparse: false,
+            label: None,
+        }
+    }
+
+    fn status_with(staged: u32, modified: u32, untracked: u32, conflicted: u32) -> WorktreeStatus {
+        WorktreeStatus {
+            is_clean: staged + modified + untracked + conflicted == 0,
+            modified,
+            staged,
+            untracked,
+            conflicted,
+            untracked_scanned: true,
+        }
+    }
+
+    #[test]
+    fn test_sort_by_attention() {
+        let mut worktrees = vec![
+            attention_worktree("clean-new", 400, Some(status_with(0, 0, 0, 0))),
+            attention_worktree("unknown", 500, None),
+            attention_worktree("untracked", 100, Some(status_with(0, 0, 3, 0))),
+            attention_worktree("conflicted", 50, Some(status_with(0, 1, 0, 2))),
+            attention_worktree("staged", 300, Some(status_with(1, 0, 0, 0))),
+            attention_worktree("clean-old", 200, Some(status_with(0, 0, 0, 0))),
+        ];
+        sort_by_attention(&mut worktrees);
+
+        let names: Vec<&str> = worktrees.iter().map(|wt| wt.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "conflicted",
+                "staged",
+                "untracked",
+                "clean-new",
+                "clean-old",
+                "unknown"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_summarize_worktrees() {
+        let mut ahead = attention_worktree("ahead", 100, Some(status_with(0, 0, 0, 0)));
+        ahead.head.upstream = Some(UpstreamInfo {
+            remote_branch: "origin/ahead".to_string(),
+            ahead: 2,
+            behind: 0,
+        });
+        let mut diverged = attention_worktree("diverged", 100, Some(status_with(0, 1, 0, 0)));
+        diverged.head.upstream = Some(UpstreamInfo {
+            remote_branch: "origin/diverged".to_string(),
+            ahead: 1,
+            behind: 3,
+        });
+        let worktrees = vec![
+            ahead,
+            diverged,
+            attention_worktree("conflicted", 100, Some(status_with(0, 0, 0, 1))),
+            attention_worktree("untracked", 100, Some(status_with(0, 0, 4, 0))),
+            attention_worktree("unknown", 100, None),
+        ];
+
+        let summary = summarize_worktrees(&worktrees);
+        assert_eq!(summary.total_worktrees, 5);
+        assert_eq!(summary.dirty, 3);
+        assert_eq!(summary.conflicted, 1);
+        assert_eq!(summary.ahead, 2);
+        assert_eq!(summary.behind, 1);
+    }
+
+    #[test]
+    fn test_get_recent_worktrees_uses_reflog() {
+        let repo = init_test_repo("recent-worktrees");
+        let path = repo.to_string_lossy();
+        let older = temp_path("recent-worktrees-older");
+        let newer = temp_path("recent-worktrees-newer");
+        for (dir, branch) in [(&older, "older"), (&newer, "newer")] {
+            run_git(
+                &path,
+                &[
+                    "worktree",
+                    "add",
+                    "-q",
+                    "-b",
+                    branch,
+                    &dir.to_string_lossy(),
+                ],
+            )
+            .unwrap();
+        }
+
+        // Fixture reflogs: "older" used most recently despite identical tips
+        let reflog = |ts: i64| {
+            format!(
+                "0000000000000000000000000000000000000000 abc123 Test <test@example.com> {} +0000\tcheckout\n",
+                ts
+            )
+        };
+        let git_dirs = [
+            (repo.clone(), 1_000),
+            (older.clone(), 3_000),
+            (newer.clone(), 2_000),
+        ];
+        for (dir, ts) in git_dirs {
+            let (git_dir, _) = get_git_dirs(&dir.to_string_lossy()).unwrap();
+            std::fs::write(git_dir.join("logs").join("HEAD"), reflog(ts)).unwrap();
+        }
+
+        let recent = get_recent_worktrees(&path, 2).unwrap();
+        let names: Vec<&str> = recent.iter().map(|wt| wt.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                older.file_name().unwrap().to_str().unwrap(),
+                newer.file_name().unwrap().to_str().unwrap()
+            ]
+        );
+
+        let _ = std::fs::remove_dir_all(&older);
+        let _ = std::fs::remove_dir_all(&newer);
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== rename_worktree_and_branch tests ====================
+
+    #[test]
+    fn test_rename_worktree_sequence() {
+        let mut calls = Vec::new();
+        rename_worktree_with("/repo", "/wt/old", "/wt/new", "feature-new", |dir, args| {
+            calls.push(format!("{}: {}", dir, args.join(" ")));
+            Ok(String::new())
+        })
+        .unwrap();
+
+        assert_eq!(
+            calls,
+            vec![
+                "/repo: worktree move /wt/old /wt/new",
+                "/wt/new: branch -m feature-new",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rename_worktree_rolls_back_move() {
+        let mut calls = Vec::new();
+        let err = rename_worktree_with("/repo", "/wt/old", "/wt/new", "taken", |dir, args| {
+            calls.push(format!("{}: {}", dir, args.join(" ")));
+            if args[0] == "branch" {
+                Err("fatal: a branch named 'taken' already exists".to_string())
+            } else {
+                Ok(String::new())
+            }
+        })
+        .unwrap_err();
+
+        assert!(err.contains("already exists"));
+        assert_eq!(
+            calls,
+            vec![
+                "/repo: worktree move /wt/old /wt/new",
+                "/wt/new: branch -m taken",
+                "/repo: worktree move /wt/new /wt/old",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rename_worktree_and_branch() {
+        let repo = init_test_repo("rename-wt");
+        let path = repo.to_string_lossy();
+        let old_dir = temp_path("rename-wt-old");
+        let new_dir = temp_path("rename-wt-new");
+        let old_str = old_dir.to_string_lossy();
+        let new_str = new_dir.to_string_lossy();
+        run_git(&path, &["worktree", "add", "-q", "-b", "feature", &old_str]).unwrap();
+
+        let wt = rename_worktree_and_branch(&path, &old_str, &new_str, "feature-renamed").unwrap();
+        assert_eq!(wt.head.branch.as_deref(), Some("feature-renamed"));
+        assert!(new_dir.exists());
+        assert!(!old_dir.exists());
+
+        // Renaming onto an existing branch fails and leaves the worktree where it was
+        let err = rename_worktree_and_branch(&path, &new_str, &old_str, "main").unwrap_err();
+        assert!(err.contains("Failed to rename branch"));
+        assert!(new_dir.exists());
+        assert!(!old_dir.exists());
+
+        let _ = std::fs::remove_dir_all(&new_dir);
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== is_branch_merged tests ====================
+
+    #[test]
+    fn test_is_ancestor_exit_codes() {
+        assert_eq!(interpret_is_ancestor_exit(Some(0), ""), Ok(true));
+        assert_eq!(interpret_is_ancestor_exit(Some(1), ""), Ok(false));
+
+        let err = interpret_is_ancestor_exit(Some(128), "fatal: Not a valid object name nope\n")
+            .unwrap_err();
+        assert!(err.contains("Not a valid object name"));
+        // Killed by a signal: no exit code
+        assert!(interpret_is_ancestor_exit(None, "").is_err());
+    }
+
+    #[test]
+    fn test_is_branch_merged() {
+        let repo = init_test_repo("branch-merged");
+        let path = repo.to_string_lossy();
+        run_git(&path, &["branch", "merged"]).unwrap();
+        run_git(&path, &["checkout", "-q", "-b", "unmerged"]).unwrap();
+        run_git(&path, &["commit", "-q", "--allow-empty", "-m", "Work"]).unwrap();
+        run_git(&path, &["checkout", "-q", "main"]).unwrap();
+
+        assert_eq!(is_branch_merged(&path, "merged", "main"), Ok(true));
+        assert_eq!(is_branch_merged(&path, "unmerged", "main"), Ok(false));
+        assert!(is_branch_merged(&path, "missing", "main").is_err());
+        assert_eq!(
+            is_branch_merged(&path, "--all", "main"),
+            Err("Invalid revision: --all".to_string())
+        );
+        assert_eq!(
+            is_branch_merged(&path, "merged", ""),
+            Err("Invalid revision: ".to_string())
+        );
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn test_can_fast_forward() {
+        let repo = init_test_repo("can-ff");
+        let path = repo.to_string_lossy();
+        run_git(&path, &["checkout", "-q", "-b", "ahead"]).unwrap();
+        run_git(&path, &["commit", "-q", "--allow-empty", "-m", "Ahead"]).unwrap();
+        run_git(&path, &["checkout", "-q", "-b", "diverged", "main"]).unwrap();
+        run_git(&path, &["commit", "-q", "--allow-empty", "-m", "Diverged"]).unwrap();
+        run_git(&path, &["checkout", "-q", "main"]).unwrap();
+
+        // Exit 0: main is an ancestor of "ahead"
+        assert_eq!(can_fast_forward(&path, "ahead"), Ok(true));
+
+        // Exit 1: from "diverged", "ahead" is on another line of history
+        run_git(&path, &["checkout", "-q", "diverged"]).unwrap();
+        assert_eq!(can_fast_forward(&path, "ahead"), Ok(false));
+
+        // Exit 128: unknown revision is an error, not "no"
+        assert!(can_fast_forward(&path, "missing").is_err());
+        assert!(can_fast_forward(&path, "--all").is_err());
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn test_merge_ff_args() {
+        assert_eq!(
+            build_merge_ff_args("origin/main"),
+            ["merge", "-q", "--ff-only", "origin/main"]
+        );
+    }
+
+    #[test]
+    fn test_ff_merge_error() {
+        let rejected =
+            "git merge -q --ff-only topic failed: fatal: Not possible to fast-forward, aborting.\n";
+        let message = ff_merge_error("topic", rejected);
+        assert!(message.contains("not fast-forwardable"));
+        assert!(message.contains("topic"));
+
+        let other =
+            "git merge -q --ff-only nope failed: merge: nope - not something we can merge\n";
+        assert_eq!(ff_merge_error("nope", other), other);
+    }
+
+    #[test]
+    fn test_merge_fast_forward() {
+        let repo = init_test_repo("merge-ff");
+        let path = repo.to_string_lossy();
+        run_git(&path, &["checkout", "-q", "-b", "topic"]).unwrap();
+        run_git(&path, &["commit", "-q", "--allow-empty", "-m", "Topic"]).unwrap();
+        let topic = run_git(&path, &["rev-parse", "--short", "topic"]).unwrap();
+        run_git(&path, &["checkout", "-q", "main"]).unwrap();
+
+        let head = merge_fast_forward(&path, "topic").unwrap();
+        assert_eq!(head.commit_sha, topic.trim());
+
+        run_git(&path, &["commit", "-q", "--allow-empty", "-m", "Main only"]).unwrap();
+        run_git(&path, &["checkout", "-q", "topic"]).unwrap();
+        run_git(
+            &path,
+            &["commit", "-q", "--allow-empty", "-m", "Topic only"],
+        )
+        .unwrap();
+        let err = merge_fast_forward(&path, "main").unwrap_err();
+        assert!(err.contains("not fast-forwardable"), "{}", err);
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== get_remote_head tests ====================
+
+    #[test]
+    fn test_remote_head_branch() {
+        assert_eq!(
+            remote_head_branch("refs/remotes/origin/main", "origin"),
+            Some("main".to_string())
+        );
+        assert_eq!(
+            remote_head_branch("refs/remotes/upstream/release/2.x", "upstream"),
+            Some("release/2.x".to_string())
+        );
+        // A target outside the remote's namespace isn't a branch of that remote
+        assert_eq!(remote_head_branch("refs/remotes/fork/main", "origin"), None);
+        assert_eq!(remote_head_branch("refs/remotes/origin/", "origin"), None);
+    }
+
+    #[test]
+    fn test_get_remote_head_set_and_unset() {
+        let repo = init_test_repo("remote-head");
+        let path = repo.to_string_lossy();
+        run_git(
+            &path,
+            &[
+                "remote",
+                "add",
+                "origin",
+                "https://example.com/org/repo.git",
+            ],
+        )
+        .unwrap();
+
+        // Unset: nothing has recorded origin/HEAD yet
+        assert_eq!(get_remote_head(&path, "origin").unwrap(), None);
+
+        // Set: fake a fetched branch and point origin/HEAD at it without touching the network
+        run_git(&path, &["update-ref", "refs/remotes/origin/trunk", "HEAD"]).unwrap();
+        run_git(&path, &["remote", "set-head", "origin", "trunk"]).unwrap();
+        assert_eq!(
+            get_remote_head(&path, "origin").unwrap(),
+            Some("trunk".to_string())
+        );
+
+        run_git(&path, &["remote", "set-head", "origin", "-d"]).unwrap();
+        assert_eq!(get_remote_head(&path, "origin").unwrap(), None);
+
+        assert!(get_remote_head(&path, "missing").is_err());
+        assert!(get_remote_head(&path, "--all").is_err());
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== commits_ahead_of_default tests ====================
+
+    #[test]
+    fn test_pick_default_branch() {
+        let locals = vec!["develop".to_string(), "master".to_string()];
+        assert_eq!(
+            pick_default_branch(Some("origin/develop"), &locals),
+            Some("develop".to_string())
+        );
+        // Default only exists on the remote
+        assert_eq!(
+            pick_default_branch(Some("origin/trunk"), &locals),
+            Some("origin/trunk".to_string())
+        );
+        assert_eq!(
+            pick_default_branch(None, &locals),
+            Some("master".to_string())
+        );
+        assert_eq!(pick_default_branch(None, &["feature".to_string()]), None);
+    }
+
+    #[test]
+    fn test_parse_rev_list_count() {
+        assert_eq!(parse_rev_list_count("3\n"), Ok(3));
+        assert_eq!(parse_rev_list_count("0"), Ok(0));
+        assert!(parse_rev_list_count("").is_err());
+    }
+
+    #[test]
+    fn test_commits_ahead_of_default() {
+        let repo = init_test_repo("ahead-default");
+        let path = repo.to_string_lossy();
+        let linked = temp_path("ahead-default-wt");
+        let linked_str = linked.to_string_lossy();
+        run_git(
+            &path,
+            &["worktree", "add", "-q", "-b", "feature", &linked_str],
+        )
+        .unwrap();
+        run_git(&linked_str, &["commit", "-q", "--allow-empty", "-m", "One"]).unwrap();
+        run_git(&linked_str, &["commit", "-q", "--allow-empty", "-m", "Two"]).unwrap();
+
+        assert_eq!(commits_ahead_of_default(&path, &linked_str), Ok(2));
+        // The default branch's own worktree is never ahead of itself
+        assert_eq!(commits_ahead_of_default(&path, &path), Ok(0));
+
+        let _ = std::fs::remove_dir_all(&linked);
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== get_all_in_flight_commits tests ====================
+
+    #[test]
+    fn test_get_all_in_flight_commits() {
+        let repo = init_test_repo("in-flight");
+        let path = repo.to_string_lossy();
+        let first = temp_path("in-flight-first");
+        let second = temp_path("in-flight-second");
+        let first_str = first.to_string_lossy().to_string();
+        let second_str = second.to_string_lossy().to_string();
+        run_git(&path, &["worktree", "add", "-q", "-b", "first", &first_str]).unwrap();
+        run_git(
+            &path,
+            &["worktree", "add", "-q", "-b", "second", &second_str],
+        )
+        .unwrap();
+        run_git(
+            &first_str,
+            &["commit", "-q", "--allow-empty", "-m", "First A"],
+        )
+        .unwrap();
+        run_git(
+            &first_str,
+            &["commit", "-q", "--allow-empty", "-m", "First B"],
+        )
+        .unwrap();
+        run_git(
+            &second_str,
+            &["commit", "-q", "--allow-empty", "-m", "Second A"],
+        )
+        .unwrap();
+
+        let all = get_all_in_flight_commits(&path, 10).unwrap();
+        let summary = |wt: &WorktreeCommits| -> (Option<String>, Vec<String>) {
+            let mut summaries: Vec<String> = wt.commits.iter().map(|c| c.summary.clone()).collect();
+            summaries.sort();
+            (wt.branch.clone(), summaries)
+        };
+        let summaries: Vec<_> = all.iter().map(summary).collect();
+        assert_eq!(
+            summaries,
+            vec![
+                (Some("main".to_string()), vec![]),
+                (
+                    Some("first".to_string()),
+                    vec!["First A".to_string(), "First B".to_string()]
+                ),
+                (Some("second".to_string()), vec!["Second A".to_string()]),
+            ]
+        );
+
+        let capped = get_all_in_flight_commits(&path, 1).unwrap();
+        assert_eq!(capped[1].commits.len(), 1);
+
+        let _ = std::fs::remove_dir_all(&first);
+        let _ = std::fs::remove_dir_all(&second);
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== get_branch_commits tests ====================
+
+    #[test]
+    fn test_branch_commits_args() {
+        let args = build_branch_commits_args("main", 50, false);
+        assert_eq!(args[0], "log");
+        assert!(args.contains(&"-n50".to_string()));
+        assert!(!args.contains(&"--no-merges".to_string()));
+        // The range comes last, fenced off from paths
+        assert_eq!(&args[args.len() - 2..], ["main..HEAD", "--"]);
+
+        let args = build_branch_commits_args("origin/main", 10, true);
+        assert!(args.contains(&"--no-merges".to_string()));
+        assert_eq!(&args[args.len() - 2..], ["origin/main..HEAD", "--"]);
+    }
+
+    #[test]
+    fn test_get_branch_commits_filters_merges() {
+        let repo = init_test_repo("branch-commits");
+        let path = repo.to_string_lossy();
+        run_git(&path, &["checkout", "-q", "-b", "side"]).unwrap();
+        run_git(&path, &["commit", "-q", "--allow-empty", "-m", "Side work"]).unwrap();
+        run_git(&path, &["checkout", "-q", "-b", "feature", "main"]).unwrap();
+        run_git(
+            &path,
+            &["commit", "-q", "--allow-empty", "-m", "Feature work"],
+        )
+        .unwrap();
+        run_git(
+            &path,
+            &["merge", "-q", "--no-ff", "-m", "Merge side", "side"],
+        )
+        .unwrap();
+
+        // Commits share a timestamp, so compare without relying on log order
+        let summaries = |commits: Vec<CommitInfo>| -> Vec<String> {
+            let mut summaries: Vec<String> = commits.into_iter().map(|c| c.summary).collect();
+            summaries.sort();
+            summaries
+        };
+        assert_eq!(
+            summaries(get_branch_commits(&path, "main", 50, false).unwrap()),
+            vec!["Feature work", "Merge side", "Side work"]
+        );
+        assert_eq!(
+            summaries(get_branch_commits(&path, "main", 50, true).unwrap()),
+            vec!["Feature work", "Side work"]
+        );
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== index.lock retry tests ====================
+
+    #[test]
+    fn test_is_index_lock_error() {
+        assert!(is_index_lock_error(
+            "fatal: Unable to create '/repo/.git/index.lock': File exists.\n\nAnother git process seems to be running"
+        ));
+        assert!(is_index_lock_error(
+            "fatal: Unable to create '/repo/.git/worktrees/feature/index.lock': File exists."
+        ));
+        assert!(!is_index_lock_error("fatal: not a git repository"));
+    }
+
+    #[test]
+    fn test_retry_when_locked_recovers() {
+        let mut calls = 0;
+        let result = retry_when_locked(3, Duration::from_millis(1), || {
+            calls += 1;
+            if calls < 3 {
+                Err(GitError::Locked("index.lock".to_string()))
+            } else {
+                Ok("clean")
+            }
+        });
+        assert_eq!(result, Ok("clean"));
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn test_retry_when_locked_gives_up() {
+        let mut calls = 0;
+        let result: Result<(), String> = retry_when_locked(3, Duration::from_millis(1), || {
+            calls += 1;
+            Err(GitError::Locked("still locked".to_string()))
+        });
+        assert_eq!(result, Err("still locked".to_string()));
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn test_retry_when_locked_ignores_other_errors() {
+        let mut calls = 0;
+        let result: Result<(), String> = retry_when_locked(3, Duration::from_millis(1), || {
+            calls += 1;
+            Err(GitError::Failed("bad revision".to_string()))
+        });
+        assert_eq!(result, Err("bad revision".to_string()));
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_is_transient_network_error() {
+        assert!(is_transient_network_error(
+            "fatal: unable to access 'https://github.com/o/r.git/': Could not resolve host: github.com\n"
+        ));
+        assert!(is_transient_network_error(
+            "error: RPC failed; curl 56 Recv failure: Connection reset by peer\nfatal: early EOF\n"
+        ));
+        assert!(is_transient_network_error(
+            "ssh: connect to host github.com port 22: Connection timed out\n"
+        ));
+
+        // Terminal failures
+        assert!(!is_transient_network_error(
+            "remote: Invalid username or password.\nfatal: Authentication failed for 'https://github.com/o/r.git/'\n"
+        ));
+        assert!(!is_transient_network_error(
+            "git@github.com: Permission denied (publickey).\nfatal: Could not read from remote repository.\n\
+             fatal: The remote end hung up unexpectedly\n"
+        ));
+        assert!(!is_transient_network_error(
+            " ! [rejected]        main -> main (non-fast-forward)\nerror: failed to push some refs\n"
+        ));
+        assert!(!is_transient_network_error(
+            "fatal: couldn't find remote ref pull/9/head\n"
+        ));
+    }
+
+    #[test]
+    fn test_retry_when_transient_recovers() {
+        let mut calls = 0;
+        let result = retry_when_transient(3, Duration::from_millis(1), || {
+            calls += 1;
+            if calls < 3 {
+                Err(GitError::Network("Could not resolve host".to_string()))
+            } else {
+                Ok("fetched")
+            }
+        });
+        assert_eq!(result, Ok("fetched"));
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn test_retry_when_transient_gives_up() {
+        let mut calls = 0;
+        let result: Result<(), String> = retry_when_transient(3, Duration::from_millis(1), || {
+            calls += 1;
+            Err(GitError::Network("Connection reset".to_string()))
+        });
+        assert_eq!(result, Err("Connection reset".to_string()));
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn test_retry_when_transient_fails_fast_on_terminal() {
+        let mut calls = 0;
+        let result: Result<(), String> = retry_when_transient(3, Duration::from_millis(1), || {
+            calls += 1;
+            Err(GitError::Failed("Authentication failed".to_string()))
+        });
+        assert_eq!(result, Err("Authentication failed".to_string()));
+        assert_eq!(calls, 1);
+
+        // Lock contention is not a network problem either
+        let mut calls = 0;
+        let _: Result<(), String> = retry_when_transient(3, Duration::from_millis(1), || {
+            calls += 1;
+            Err(GitError::Locked("index.lock".to_string()))
+        });
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_run_git_classifies_lock() {
+        let repo = init_test_repo("index-lock");
+        let path = repo.to_string_lossy();
+        std::fs::write(repo.join(".git").join("index.lock"), "").unwrap();
+        std::fs::write(repo.join("README.md"), "changed\n").unwrap();
+
+        let err = run_git_classified(&path, &["add", "README.md"]).unwrap_err();
+        assert!(matches!(err, GitError::Locked(_)));
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== branch_remote_presence tests ====================
+
+    #[test]
+    fn test_parse_remote_presence() {
+        let output = "refs/remotes/origin/feature/login\nrefs/remotes/upstream/feature/login\n";
+        assert_eq!(
+            parse_remote_presence(output, "feature/login"),
+            vec!["origin", "upstream"]
+        );
+
+        assert!(parse_remote_presence("", "local-only").is_empty());
+    }
+
+    // ==================== get_last_modified_file tests ====================
+
+    #[test]
+    fn test_newest_file_prefers_first_on_tie() {
+        let files = vec![
+            ("a".to_string(), 10),
+            ("b".to_string(), 30),
+            ("c".to_string(), 30),
+            ("d".to_string(), 20),
+        ];
+        let newest = newest_file(files.into_iter()).unwrap();
+        assert_eq!(newest.path, "b");
+        assert_eq!(newest.mtime, 30);
+
+        assert!(newest_file(std::iter::empty()).is_none());
+    }
+
+    #[test]
+    fn test_get_last_modified_file() {
+        let repo = init_test_repo("last-modified");
+        let path = repo.to_string_lossy();
+        std::fs::create_dir(repo.join("src")).unwrap();
+        std::fs::write(repo.join("src/lib.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(repo.join("notes.txt"), "todo\n").unwrap();
+        run_git(&path, &["add", "."]).unwrap();
+        run_git(&path, &["commit", "-q", "-m", "More files"]).unwrap();
+        // Untracked files are never considered, however recent
+        std::fs::write(repo.join("scratch.txt"), "tmp\n").unwrap();
+
+        let set_mtime = |file: &str, secs: u64| {
+            let time = std::time::UNIX_EPOCH + Duration::from_secs(secs);
+            std::fs::File::options()
+                .write(true)
+                .open(repo.join(file))
+                .unwrap()
+                .set_modified(time)
+                .unwrap();
+        };
+        set_mtime("README.md", 1_000_000);
+        set_mtime("notes.txt", 2_000_000);
+        set_mtime("src/lib.rs", 3_000_000);
+        set_mtime("scratch.txt", 1_000);
+
+        let newest = get_last_modified_file(&path).unwrap().unwrap();
+        assert_eq!(newest.path, "src/lib.rs");
+        assert_eq!(newest.mtime, 3_000_000);
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== get_revert_preview tests ====================
+
+    #[test]
+    fn test_revert_preview_args() {
+        assert_eq!(
+            build_revert_preview_args("abc123"),
+            vec!["show", "-R", "abc123", "--format=", "-U3", "-M"]
+        );
+    }
+
+    #[test]
+    fn test_revert_preview_swaps_stats() {
+        let repo = init_test_repo("revert-preview");
+        let path = repo.to_string_lossy();
+        std::fs::write(repo.join("README.md"), "first\nsecond\n").unwrap();
+        run_git(&path, &["commit", "-q", "-am", "Replace readme"]).unwrap();
+
+        let forward = get_commit_diff(&path, "HEAD").unwrap();
+        assert_eq!(forward.stats.insertions, 2);
+        assert_eq!(forward.stats.deletions, 1);
+
+        let preview = get_revert_preview(&path, "HEAD").unwrap();
+        assert_eq!(preview.commit.hash, forward.commit.hash);
+        assert_eq!(preview.stats.files_changed, 1);
+        assert_eq!(preview.stats.insertions, 1);
+        assert_eq!(preview.stats.deletions, 2);
+        let removed: Vec<&str> = preview.files[0].hunks[0]
+            .lines
+            .iter()
+            .filter(|l| l.kind == '-')
+            .map(|l| l.content.as_str())
+            .collect();
+        assert_eq!(removed, vec!["first", "second"]);
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== stream_commit_diff tests ====================
+
+    const TWO_FILE_DIFF: &str = "diff --git a/a.txt b/a.txt
+index 1111111..2222222 100644
+--- a/a.txt
++++ b/a.txt
+@@ -1 +1 @@
+-old
++new
+diff --git a/b.txt b/b.txt
+new file mode 100644
+index 0000000..3333333
+--- /dev/null
++++ b/b.txt
+@@ -0,0 +1,2 @@
++caf\u{e9}
++diff --git inside content is not a header
+";
+
+    #[test]
+    fn test_diff_splitter_yields_file_at_next_header() {
+        let mut splitter = DiffFileSplitter::default();
+        // Nothing is complete until the second header arrives
+        assert!(splitter
+            .push(b"diff --git a/a.txt b/a.txt\n--- a/a.txt\n")
+            .is_empty());
+        assert!(splitter
+            .push(b"+++ b/a.txt\n@@ -1 +1 @@\n-old\n+new\ndiff --git a/b")
+            .is_empty());
+
+        let files = splitter.push(b".txt b/b.txt\n");
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "a.txt");
+        assert_eq!(files[0].hunks[0].lines.len(), 2);
+    }
+
+    #[test]
+    fn test_diff_splitter_matches_whole_parse_for_any_chunking() {
+        let bytes = TWO_FILE_DIFF.as_bytes();
+        let expected = parse_git_diff_output(TWO_FILE_DIFF);
+
+        // Chunk sizes of 1 and 3 split lines, headers, and the two-byte "é"
+        for chunk_size in [1, 3, 7, bytes.len()] {
+            let mut splitter = DiffFileSplitter::default();
+            let mut files = Vec::new();
+            for chunk in bytes.chunks(chunk_size) {
+                files.extend(splitter.push(chunk));
+            }
+            files.extend(splitter.finish());
+
+            assert_eq!(files.len(), expected.len(), "chunk size {}", chunk_size);
+            for (file, want) in files.iter().zip(&expected) {
+                assert_eq!(file.path, want.path);
+                assert_eq!(file.hunks.len(), want.hunks.len());
+                let contents: Vec<&str> = file.hunks[0]
+                    .lines
+                    .iter()
+                    .map(|l| l.content.as_str())
+                    .collect();
+                let want_contents: Vec<&str> = want.hunks[0]
+                    .lines
+                    .iter()
+                    .map(|l| l.content.as_str())
+                    .collect();
+                assert_eq!(contents, want_contents);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stream_commit_diff() {
+        let repo = init_test_repo("stream-diff");
+        let path = repo.to_string_lossy();
+        std::fs::write(repo.join("README.md"), "changed\n").unwrap();
+        std::fs::write(repo.join("new.txt"), "one\ntwo\n").unwrap();
+        run_git(&path, &["add", "."]).unwrap();
+        run_git(&path, &["commit", "-q", "-m", "Two files"]).unwrap();
+
+        let mut paths = Vec::new();
+        let count = stream_commit_diff(&path, "HEAD", |file| paths.push(file.path)).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(paths, vec!["README.md", "new.txt"]);
+
+        // git's stderr is still collected for the error message
+        let err = stream_commit_diff(&path, "no-such-rev", |_| {}).unwrap_err();
+        assert!(err.contains("ambiguous argument 'no-such-rev'"));
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== get_commit_preview tests ====================
+
+    #[test]
+    fn test_parse_ident() {
+        assert_eq!(
+            parse_ident("Jane Doe <jane@example.com> 1700000000 +0100"),
+            (
+                "Jane Doe".to_string(),
+                "jane@example.com".to_string(),
+                1700000000
+            )
+        );
+        assert_eq!(parse_ident(""), (String::new(), String::new(), 0));
+    }
+
+    #[test]
+    fn test_commit_preview() {
+        let repo = init_test_repo("commit-preview");
+        let path = repo.to_string_lossy();
+        let head = run_git(&path, &["rev-parse", "HEAD"]).unwrap();
+        std::fs::write(repo.join("README.md"), "hello\nworld\n").unwrap();
+        std::fs::write(repo.join("new.txt"), "a\nb\nc\n").unwrap();
+        // Unstaged edits must not show up in the preview
+        std::fs::write(repo.join("unstaged.txt"), "x\n").unwrap();
+        run_git(&path, &["add", "README.md", "new.txt"]).unwrap();
+
+        let preview = get_commit_preview(&path).unwrap();
+        assert_eq!(preview.commit.hash, "");
+        assert_eq!(preview.commit.parent_hashes, vec![head.trim().to_string()]);
+        let paths: Vec<&str> = preview.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec!["README.md", "new.txt"]);
+        assert_eq!(preview.stats.files_changed, 2);
+        assert_eq!(preview.stats.insertions, 4);
+        assert_eq!(preview.stats.deletions, 0);
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== read_worktree_file tests ====================
+
+    #[test]
+    fn test_looks_binary() {
+        assert!(!looks_binary(b"plain text\n"));
+        assert!(looks_binary(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"));
+        // A NUL past the sniff window doesn't count, matching git
+        let mut late_nul = vec![b'a'; BINARY_SNIFF_LEN];
+        late_nul.push(0);
+        assert!(!looks_binary(&late_nul));
+    }
+
+    #[test]
+    fn test_read_worktree_file_truncates() {
+        let repo = init_test_repo("read-file");
+        let path = repo.to_string_lossy();
+        std::fs::write(repo.join("data.bin"), b"\0\x01\x02\x03\x04\x05").unwrap();
+
+        let whole = read_worktree_file(&path, "README.md", 1024).unwrap();
+        assert_eq!(whole.data_base64, BASE64_STANDARD.encode(b"hello\n"));
+        assert!(!whole.truncated);
+        assert_eq!(whole.total_size, 6);
+        assert!(!whole.is_binary);
+
+        let partial = read_worktree_file(&path, "data.bin", 4).unwrap();
+        assert_eq!(
+            partial.data_base64,
+            BASE64_STANDARD.encode(b"\0\x01\x02\x03")
+        );
+        assert!(partial.truncated);
+        assert_eq!(partial.total_size, 6);
+        assert!(partial.is_binary);
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn test_read_worktree_file_rejects_traversal() {
+        let repo = init_test_repo("read-file-traversal");
+        let path = repo.to_string_lossy();
+        let outside = temp_path("read-file-outside.txt");
+        std::fs::write(&outside, "secret\n").unwrap();
+
+        let relative = format!("../{}", outside.file_name().unwrap().to_string_lossy());
+        let err = read_worktree_file(&path, &relative, 1024).unwrap_err();
+        assert!(err.contains("outside the worktree"), "{}", err);
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&outside, repo.join("link.txt")).unwrap();
+            let err = read_worktree_file(&path, "link.txt", 1024).unwrap_err();
+            assert!(err.contains("outside the worktree"), "{}", err);
+        }
+
+        // ".." that stays inside the worktree is fine
+        std::fs::create_dir(repo.join("sub")).unwrap();
+        assert!(read_worktree_file(&path, "sub/../README.md", 1024).is_ok());
+
+        let _ = std::fs::remove_file(&outside);
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== get_working_diff_tree tests ====================
+
+    /// A modified file whose single hunk has the given counts
+    fn tree_file(path: &str, additions: u32, deletions: u32) -> FileDiff {
+        let mut file = parse_git_diff_output(&format!(
+            "diff --git a/{p} b/{p}\n--- a/{p}\n+++ b/{p}\n@@ -1 +1 @@\n",
+            p = path
+        ))
+        .remove(0);
+        file.hunks[0].additions = additions;
+        file.hunks[0].deletions = deletions;
+        file
+    }
+
+    #[test]
+    fn test_diff_tree_nesting_and_rollup() {
+        let files = [
+            tree_file("src/git/parse.rs", 5, 1),
+            tree_file("README.md", 2, 0),
+            tree_file("src/main.rs", 1, 1),
+            tree_file("src/git/run.rs", 3, 2),
+        ];
+        let root = build_diff_tree(files.iter());
+
+        assert_eq!(root.name, "");
+        assert_eq!(root.file_count, 4);
+        assert_eq!((root.additions, root.deletions), (11, 4));
+
+        // Directories before files
+        let names: Vec<&str> = root.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["src", "README.md"]);
+
+        let readme = &root.children[1];
+        assert!(readme.is_file);
+        assert_eq!(readme.path, "README.md");
+        assert_eq!(
+            (readme.additions, readme.deletions, readme.file_count),
+            (2, 0, 1)
+        );
+
+        let src = &root.children[0];
+        assert_eq!(src.file_count, 3);
+        assert_eq!((src.additions, src.deletions), (9, 4));
+        let git = &src.children[0];
+        assert_eq!(git.path, "src/git");
+        assert_eq!(git.file_count, 2);
+        assert_eq!((git.additions, git.deletions), (8, 3));
+        let leaves: Vec<&str> = git.children.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(leaves, vec!["src/git/parse.rs", "src/git/run.rs"]);
+    }
+
+    #[test]
+    fn test_diff_tree_merges_staged_and_unstaged() {
+        // The same file staged and further modified counts once, with both halves' lines
+        let files = [tree_file("a/b.txt", 1, 0), tree_file("a/b.txt", 2, 1)];
+        let root = build_diff_tree(files.iter());
+        assert_eq!(root.file_count, 1);
+        assert_eq!(root.children[0].file_count, 1);
+        let leaf = &root.children[0].children[0];
+        assert_eq!((leaf.additions, leaf.deletions, leaf.file_count), (3, 1, 1));
+
+        let empty = build_diff_tree(std::iter::empty());
+        assert_eq!(empty.file_count, 0);
+        assert!(empty.children.is_empty());
+    }
+
+    #[test]
+    fn test_get_working_diff_tree() {
+        let repo = init_test_repo("working-diff-tree");
+        let path = repo.to_string_lossy();
+        std::fs::create_dir(repo.join("docs")).unwrap();
+        std::fs::write(repo.join("docs/guide.md"), "one\ntwo\n").unwrap();
+        std::fs::write(repo.join("README.md"), "hello\nmore\n").unwrap();
+
+        let root = get_working_diff_tree(&path).unwrap();
+        assert_eq!(root.file_count, 2);
+        // Untracked files carry no hunks in the working diff, so only README's line counts
+        assert_eq!(root.additions, 1);
+        assert_eq!(root.children[0].path, "docs");
+        assert_eq!(root.children[1].path, "README.md");
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== stage_lines tests ====================
+
+    const STAGE_LINES_DIFF: &str = "diff --git a/f.txt b/f.txt
+index 1111111..2222222 100644
+--- a/f.txt
++++ b/f.txt
+@@ -1,4 +1,5 @@
+ one
+-two
++TWO
+ three
++three-and-a-half
+ four
+@@ -10,3 +11,4 @@ ctx
+ ten
+ eleven
++eleven-b
+ twelve
+";
+
+    #[test]
+    fn test_line_range_patch_single_range() {
+        let file = &parse_git_diff_output(STAGE_LINES_DIFF)[0];
+        // Line 2 is "TWO"; the "-two" deletion sits at the same position
+        let patch = build_line_range_patch(file, &[(2, 2)]).unwrap();
+        assert_eq!(
+            patch,
+            "diff --git a/f.txt b/f.txt
+--- a/f.txt
++++ b/f.txt
+@@ -1,4 +1,4 @@
+ one
+-two
++TWO
+ three
+ four
+"
+        );
+    }
+
+    #[test]
+    fn test_line_range_patch_disjoint_ranges() {
+        let file = &parse_git_diff_output(STAGE_LINES_DIFF)[0];
+        // "three-and-a-half" (line 4) and "eleven-b" (line 13), leaving the TWO edit unstaged
+        let patch = build_line_range_patch(file, &[(4, 4), (13, 13)]).unwrap();
+        assert_eq!(
+            patch,
+            "diff --git a/f.txt b/f.txt
+--- a/f.txt
++++ b/f.txt
+@@ -1,4 +1,5 @@
+ one
+ two
+ three
++three-and-a-half
+ four
+@@ -10,3 +11,4 @@
+ ten
+ eleven
++eleven-b
+ twelve
+"
+        );
+
+        assert_eq!(build_line_range_patch(file, &[(7, 9)]), None);
+    }
+
+    #[test]
+    fn test_stage_lines() {
+        let repo = init_test_repo("stage-lines");
+        let path = repo.to_string_lossy();
+        std::fs::write(repo.join("README.md"), "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n").unwrap();
+        run_git(&path, &["commit", "-q", "-am", "Letters"]).unwrap();
+        std::fs::write(repo.join("README.md"), "A\nb\nc\nd\ne\nf\ng\nh\ni\nJ\n").unwrap();
+
+        stage_lines(&path, "README.md", vec![(10, 10)]).unwrap();
+
+        let staged = run_git(&path, &["diff", "--cached"]).unwrap();
+        assert!(staged.contains("+J"));
+        assert!(!staged.contains("+A"));
+        let unstaged = run_git(&path, &["diff"]).unwrap();
+        assert!(unstaged.contains("+A"));
+        assert!(!unstaged.contains("+J"));
+
+        let err = stage_lines(&path, "README.md", vec![(5, 5)]).unwrap_err();
+        assert!(err.contains("No changes"));
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== export_working_patch tests ====================
+
+    #[test]
+    fn test_export_working_patch_untracked() {
+        let repo = init_test_repo("export-patch");
+        let path = repo.to_string_lossy();
+        std::fs::write(repo.join("README.md"), "hello\nstaged\n").unwrap();
+        run_git(&path, &["add", "README.md"]).unwrap();
+        std::fs::write(repo.join("README.md"), "hello\nstaged\nunstaged\n").unwrap();
+        std::fs::write(repo.join("new file.txt"), "brand new\n").unwrap();
+
+        let without = export_working_patch(&path, false).unwrap();
+        assert!(without.contains("+staged"));
+        assert!(without.contains("+unstaged"));
+        assert!(!without.contains("new file.txt"));
+
+        let status_before = run_git(&path, &["status", "--porcelain"]).unwrap();
+        let with = export_working_patch(&path, true).unwrap();
+        assert!(with.contains("+brand new"));
+        // Intent-to-add entries are gone: status is exactly as before
+        assert_eq!(
+            run_git(&path, &["status", "--porcelain"]).unwrap(),
+            status_before
+        );
+        assert!(status_before.contains("?? \"new file.txt\""));
+
+        // The patch applies cleanly to a pristine checkout
+        run_git(&path, &["stash", "-u", "-q"]).unwrap();
+        run_git_with_stdin(&path, &["apply", "--check", "-"], &with)
+            .map(|out| assert!(out.status.success()))
+            .unwrap();
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== transfer_changes tests ====================
+
+    #[test]
+    fn test_transfer_changes() {
+        let repo = init_test_repo("transfer");
+        let path = repo.to_string_lossy();
+        let dest = temp_path("transfer-dest");
+        let dest_str = dest.to_string_lossy();
+        run_git(&path, &["worktree", "add", "-q", "--detach", &dest_str]).unwrap();
+
+        std::fs::write(repo.join("README.md"), "hello\nmore\n").unwrap();
+        std::fs::write(repo.join("notes.txt"), "todo\n").unwrap();
+
+        transfer_changes(&path, &dest_str, true, true).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(dest.join("README.md")).unwrap(),
+            "hello\nmore\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dest.join("notes.txt")).unwrap(),
+            "todo\n"
+        );
+        // Source was discarded, untracked file included
+        assert!(run_git(&path, &["status", "--porcelain"])
+            .unwrap()
+            .is_empty());
+
+        let err = transfer_changes(&path, &dest_str, true, false).unwrap_err();
+        assert!(err.contains("No uncommitted changes"));
+
+        let _ = std::fs::remove_dir_all(&dest);
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn test_transfer_changes_conflict_guard() {
+        let repo = init_test_repo("transfer-conflict");
+        let path = repo.to_string_lossy();
+        let dest = temp_path("transfer-conflict-dest");
+        let dest_str = dest.to_string_lossy();
+        run_git(&path, &["worktree", "add", "-q", "--detach", &dest_str]).unwrap();
+
+        std::fs::write(repo.join("README.md"), "from source\n").unwrap();
+        std::fs::write(dest.join("README.md"), "already edited\n").unwrap();
+
+        let err = transfer_changes(&path, &dest_str, false, true).unwrap_err();
+        assert!(err.contains("conflict"), "{}", err);
+
+        // Neither side was modified
+        assert_eq!(
+            std::fs::read_to_string(dest.join("README.md")).unwrap(),
+            "already edited\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(repo.join("README.md")).unwrap(),
+            "from source\n"
+        );
+
+        let _ = std::fs::remove_dir_all(&dest);
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== get_range_numstat tests ====================
+
+    #[test]
+    fn test_parse_numstat() {
+        let output = "10\t2\tsrc/main.rs\n\
+                      -\t-\tassets/logo.png\n\
+                      3\t1\tsrc/{old => new}/lib.rs\n\
+                      0\t0\tREADME => README.md\n";
+        let stats = parse_numstat(output);
+        assert_eq!(stats.len(), 4);
+
+        assert_eq!(stats[0].path, "src/main.rs");
+        assert_eq!(stats[0].old_path, None);
+        assert_eq!(stats[0].additions, Some(10));
+        assert_eq!(stats[0].deletions, Some(2));
+
+        // Binary files have no line counts
+        assert_eq!(stats[1].path, "assets/logo.png");
+        assert_eq!(stats[1].additions, None);
+        assert_eq!(stats[1].deletions, None);
+
+        assert_eq!(stats[2].path, "src/new/lib.rs");
+        assert_eq!(stats[2].old_path.as_deref(), Some("src/old/lib.rs"));
+        assert_eq!(stats[3].path, "README.md");
+        assert_eq!(stats[3].old_path.as_deref(), Some("README"));
+    }
+
+    #[test]
+    fn test_split_numstat_rename_empty_side() {
+        assert_eq!(
+            split_numstat_rename("src/{ => nested}/file.rs"),
+            (
+                Some("src/file.rs".to_string()),
+                "src/nested/file.rs".to_string()
+            )
+        );
+        assert_eq!(
+            split_numstat_rename("{sub => }/file.rs"),
+            (Some("sub/file.rs".to_string()), "file.rs".to_string())
+        );
+        assert_eq!(
+            split_numstat_rename("dir/{a {x} => b}.rs"),
+            (Some("dir/a {x}.rs".to_string()), "dir/b.rs".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_range_numstat() {
+        let repo = init_test_repo("range-numstat");
+        let path = repo.to_string_lossy();
+        std::fs::write(repo.join("README.md"), "hello\nworld\n").unwrap();
+        run_git(&path, &["commit", "-q", "-am", "Extend"]).unwrap();
+
+        let stats = get_range_numstat(&path, "HEAD~1", "HEAD").unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].path, "README.md");
+
+        assert_eq!(
+            get_range_numstat(&path, "--output=x", "HEAD").unwrap_err(),
+            "Invalid revision: --output=x"
+        );
+        assert!(get_range_numstat(&path, "HEAD~1", "").is_err());
+        assert!(!repo.join("x").exists());
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== is_worktree_dirty tests ====================
+
+    #[test]
+    fn test_porcelain_has_changes() {
+        assert!(porcelain_has_changes(" M src/main.rs\n"));
+        assert!(porcelain_has_changes("M  staged.rs\n"));
+        assert!(!porcelain_has_changes(""));
+        assert!(!porcelain_has_changes("\n"));
+    }
+
+    #[test]
+    fn test_is_worktree_dirty() {
+        let repo = init_test_repo("quick-dirty");
+        let path = repo.to_string_lossy();
+        assert_eq!(is_worktree_dirty(&path), Ok(false));
+
+        // Untracked files don't count
+        std::fs::write(repo.join("scratch.txt"), "notes\n").unwrap();
+        assert_eq!(is_worktree_dirty(&path), Ok(false));
+
+        std::fs::write(repo.join("README.md"), "changed\n").unwrap();
+        assert_eq!(is_worktree_dirty(&path), Ok(true));
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn test_is_checkout_of() {
+        let repo = init_test_repo("checkout-of");
+        let path = repo.to_string_lossy();
+        run_git(&path, &["tag", "v1"]).unwrap();
+
+        // At the commit and clean (tag and branch name both resolve to it)
+        assert_eq!(is_checkout_of(&path, "v1"), Ok(true));
+        assert_eq!(is_checkout_of(&path, "main"), Ok(true));
+
+        // At the commit but dirty
+        std::fs::write(repo.join("README.md"), "changed\n").unwrap();
+        assert_eq!(is_checkout_of(&path, "v1"), Ok(false));
+        run_git(&path, &["checkout", "-q", "--", "README.md"]).unwrap();
+
+        // A different commit
+        run_git(&path, &["commit", "-q", "--allow-empty", "-m", "Next"]).unwrap();
+        assert_eq!(is_checkout_of(&path, "v1"), Ok(false));
+        assert_eq!(is_checkout_of(&path, "HEAD"), Ok(true));
+
+        assert!(is_checkout_of(&path, "missing").is_err());
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== working diff rename tests ====================
+
+    #[test]
+    fn test_content_similarity() {
+        let a = "fn main() {\n    println!(\"hi\");\n}\n";
+        let b = "fn main() {\n    println!(\"hello\");\n}\n";
+        // 2 of 3 lines shared on each side
+        assert!((content_similarity(a, b) - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(content_similarity(a, a), 1.0);
+        assert_eq!(content_similarity("x\n", "y\n"), 0.0);
+        assert_eq!(content_similarity("", ""), 0.0);
+    }
+
+    #[test]
+    fn test_pair_by_similarity() {
+        let body: String = (0..20).map(|i| format!("line {}\n", i)).collect();
+        let edited = body.replace("line 7\n", "line seven\n");
+        let deleted = vec![("old/name.rs".to_string(), body)];
+        let added = vec![
+            ("unrelated.txt".to_string(), "something else\n".to_string()),
+            ("new/name.rs".to_string(), edited),
+        ];
+
+        assert_eq!(
+            pair_by_similarity(&deleted, &added),
+            vec![("old/name.rs".to_string(), "new/name.rs".to_string())]
+        );
+        // Below the threshold nothing is paired
+        assert!(pair_by_similarity(&deleted, &added[..1]).is_empty());
+    }
+
+    #[test]
+    fn test_working_diff_detects_untracked_rename() {
+        let repo = init_test_repo("working-rename");
+        let path = repo.to_string_lossy();
+        let body: String = (0..20).map(|i| format!("line {}\n", i)).collect();
+        std::fs::write(repo.join("before.txt"), &body).unwrap();
+        run_git(&path, &["add", "before.txt"]).unwrap();
+        run_git(&path, &["commit", "-q", "-m", "Add file"]).unwrap();
+        std::fs::rename(repo.join("before.txt"), repo.join("after.txt")).unwrap();
+
+        let plain = get_working_diff(&path, DiffScope::All, false).unwrap();
+        assert_eq!(plain.unstaged_files.len(), 2);
+
+        let diff = get_working_diff(&path, DiffScope::All, true).unwrap();
+        assert_eq!(diff.unstaged_files.len(), 1);
+        assert_eq!(diff.unstaged_files[0].path, "after.txt");
+        assert!(matches!(diff.unstaged_files[0].status, FileStatus::Renamed));
+        assert_eq!(
+            diff.unstaged_files[0].old_path.as_deref(),
+            Some("before.txt")
+        );
+        assert_eq!(diff.stats.files_changed, 1);
+        assert_eq!(diff.stats.deletions, 0);
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== list_all_refs tests ====================
+
+    #[test]
+    fn test_parse_ref_entries() {
+        let output = "refs/heads/main\x1fmain\x1faaaa\x1f\n\
+                      refs/remotes/origin/HEAD\x1forigin\x1faaaa\x1frefs/remotes/origin/main\n\
+                      refs/remotes/origin/main\x1forigin/main\x1faaaa\x1f\n\
+                      refs/tags/v1.0\x1fv1.0\x1fbbbb\x1f\n\
+                      refs/notes/commits\x1fnotes/commits\x1fcccc\x1f\n\
+                      refs/stash\x1fstash\x1fdddd\x1f\n";
+        let refs = parse_ref_entries(output);
+        assert_eq!(refs.len(), 6);
+
+        assert_eq!(refs[0].kind, RefKind::LocalBranch);
+        assert_eq!(refs[0].short_name, "main");
+        assert_eq!(refs[0].target_sha, "aaaa");
+        assert!(!refs[0].is_symbolic);
+
+        assert_eq!(refs[1].kind, RefKind::RemoteBranch);
+        assert!(refs[1].is_symbolic);
+        assert_eq!(refs[2].kind, RefKind::RemoteBranch);
+        assert_eq!(refs[3].kind, RefKind::Tag);
+        assert_eq!(refs[4].kind, RefKind::Note);
+        assert_eq!(refs[5].kind, RefKind::Other);
+        assert_eq!(refs[5].full_name, "refs/stash");
+    }
+
+    // ==================== resolve_tag tests ====================
+
+    #[test]
+    fn test_resolve_tag_args() {
+        assert_eq!(
+            build_resolve_tag_args("v1.0"),
+            vec!["rev-parse", "--verify", "-q", "v1.0^{commit}"]
+        );
+    }
+
+    #[test]
+    fn test_resolve_tag() {
+        let repo = init_test_repo("resolve-tag");
+        let path = repo.to_string_lossy();
+        let head = run_git(&path, &["rev-parse", "HEAD"]).unwrap();
+
+        run_git(&path, &["tag", "light"]).unwrap();
+        run_git(&path, &["tag", "-a", "annotated", "-m", "Release"]).unwrap();
+        // The annotated tag ref names a tag object, not the commit
+        assert_ne!(run_git(&path, &["rev-parse", "annotated"]).unwrap(), head);
+
+        assert_eq!(resolve_tag(&path, "light").unwrap(), head.trim());
+        assert_eq!(resolve_tag(&path, "annotated").unwrap(), head.trim());
+        assert!(resolve_tag(&path, "missing").is_err());
+        assert!(resolve_tag(&path, "--all").is_err());
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== resolve_commit tests ====================
+
+    #[test]
+    fn test_parse_commit_candidates() {
+        let output = "1398aaaa commit\n1398bbbb blob\n1398cccc commit\n1398dddd tree\n";
+        assert_eq!(
+            parse_commit_candidates(output),
+            vec!["1398aaaa", "1398cccc"]
+        );
+    }
+
+    #[test]
+    fn test_resolve_commit() {
+        let repo = init_test_repo("resolve-commit");
+        let path = repo.to_string_lossy();
+        let head = run_git(&path, &["rev-parse", "HEAD"]).unwrap();
+        let head = head.trim();
+
+        assert_eq!(
+            resolve_commit(&path, &head[..7]).unwrap(),
+            ResolveResult::Resolved(head.to_string())
+        );
+        assert_eq!(
+            resolve_commit(&path, "HEAD").unwrap(),
+            ResolveResult::Resolved(head.to_string())
+        );
+        let err = resolve_commit(&path, "no-such-rev").unwrap_err();
+        assert!(err.contains("No commit matches"));
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn test_resolve_commit_ambiguous() {
+        let repo = init_test_repo("resolve-ambiguous");
+        let path = repo.to_string_lossy();
+
+        // Enough commits that some share a 4-character prefix (the shortest git accepts)
+        let stream: String = (0..1500)
+            .map(|i| {
+                let msg = format!("c{}\n", i);
+                format!(
+                    "commit refs/heads/many\ncommitter T <t@example.com> {} +0000\ndata {}\n{}\n",
+                    1_700_000_000 + i,
+                    msg.len(),
+                    msg
+                )
+            })
+            .collect();
+        let output = run_git_with_stdin(&path, &["fast-import", "--quiet"], &stream).unwrap();
+        assert!(output.status.success());
+
+        let shas = run_git(&path, &["rev-list", "many"]).unwrap();
+        let mut by_prefix: std::collections::HashMap<&str, Vec<&str>> =
+            std::collections::HashMap::new();
+        for sha in shas.lines() {
+            by_prefix.entry(&sha[..4]).or_default().push(sha);
+        }
+        let (prefix, expected) = by_prefix
+            .into_iter()
+            .find(|(_, shas)| shas.len() > 1)
+            .expect("no colliding prefix");
+
+        match resolve_commit(&path, prefix).unwrap() {
+            ResolveResult::Ambiguous(mut candidates) => {
+                let mut expected: Vec<String> = expected.iter().map(|s| s.to_string()).collect();
+                candidates.sort();
+                expected.sort();
+                assert_eq!(candidates, expected);
+            }
+            other => panic!("expected ambiguity, got {:?}", other),
+        }
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== get_stash_diff tests ====================
+
+    #[test]
+    fn test_stash_show_args() {
+        assert_eq!(
+            build_stash_show_args(0, false),
+            vec!["stash", "show", "-p", "-U3", "stash@{0}"]
+        );
+        assert_eq!(
+            build_stash_show_args(3, true),
+            vec![
+                "stash",
+                "show",
+                "-p",
+                "--include-untracked",
+                "-U3",
+                "stash@{3}"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_stash_diff() {
+        let repo = init_test_repo("stash-diff");
+        let path = repo.to_string_lossy();
+        std::fs::write(repo.join("README.md"), "hello\nstashed\n").unwrap();
+        std::fs::write(repo.join("extra.txt"), "untracked\n").unwrap();
+        run_git(&path, &["stash", "push", "-q", "-u", "-m", "Try something"]).unwrap();
+
+        let diff = get_stash_diff(&path, 0, false).unwrap();
+        assert!(diff.commit.summary.contains("Try something"));
+        assert_eq!(diff.files.len(), 1);
+        assert_eq!(diff.stats.insertions, 1);
+
+        let with_untracked = get_stash_diff(&path, 0, true).unwrap();
+        assert_eq!(with_untracked.files.len(), 2);
+
+        assert!(get_stash_diff(&path, 5, false).is_err());
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== branch_stash_count tests ====================
+
+    #[test]
+    fn test_count_branch_stashes() {
+        let output = "stash@{0} On main: try a thing\n\
+                      stash@{1} WIP on feature/login: abc1234 Add form\n\
+                      stash@{2} WIP on main: def5678 Initial commit\n\
+                      stash@{3} On main-old: stale experiment\n\
+                      stash@{4} WIP on (no branch): 9abcdef Detached work\n";
+        assert_eq!(count_branch_stashes(output, "main"), 2);
+        assert_eq!(count_branch_stashes(output, "feature/login"), 1);
+        assert_eq!(count_branch_stashes(output, "develop"), 0);
+        assert_eq!(count_branch_stashes("", "main"), 0);
+    }
+
+    #[test]
+    fn test_branch_stash_count() {
+        let repo = init_test_repo("branch-stash-count");
+        let path = repo.to_string_lossy();
+        std::fs::write(repo.join("README.md"), "one\n").unwrap();
+        run_git(&path, &["stash", "push", "-q", "-m", "On main"]).unwrap();
+        run_git(&path, &["checkout", "-q", "-b", "other"]).unwrap();
+        std::fs::write(repo.join("README.md"), "two\n").unwrap();
+        run_git(&path, &["stash", "-q"]).unwrap();
+
+        assert_eq!(branch_stash_count(&path), Ok(1));
+        run_git(&path, &["checkout", "-q", "main"]).unwrap();
+        assert_eq!(branch_stash_count(&path), Ok(1));
+        run_git(&path, &["checkout", "-q", "--detach"]).unwrap();
+        assert_eq!(branch_stash_count(&path), Ok(0));
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    // ==================== init_repository tests ====================
+
+    #[test]
+    fn test_init_args() {
+        assert_eq!(
+            build_init_args("/tmp/repo", None, false),
+            vec!["init", "-q", "--", "/tmp/repo"]
+        );
+        assert_eq!(
+            build_init_args("/tmp/repo", Some("trunk"), false),
+            vec!["init", "-q", "--initial-branch=trunk", "--", "/tmp/repo"]
+        );
+        assert_eq!(
+            build_init_args("/tmp/repo.git", None, true),
+            vec!["init", "-q", "--bare", "--", "/tmp/repo.git"]
+        );
+        assert_eq!(
+            build_init_args("/tmp/repo.git", Some("main"), true),
+            vec![
+                "init",
+                "-q",
+                "--initial-branch=main",
+                "--bare",
+                "--",
+                "/tmp/repo.git"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_init_repository() {
+        let repo = temp_path("init-repo");
+        let bare = temp_path("init-repo-bare");
+        let _ = std::fs::remove_dir_all(&repo);
+        let _ = std::fs::remove_dir_all(&bare);
+
+        let probe = init_repository(&repo.to_string_lossy(), Some("trunk"), false).unwrap();
+        assert!(probe.is_repo);
+        assert!(probe.is_worktree);
+        let head = get_head_info(&repo.to_string_lossy()).unwrap();
+        assert_eq!(head.branch.as_deref(), Some("trunk"));
+
+        let probe = init_repository(&bare.to_string_lossy(), None, true).unwrap();
+        assert!(probe.is_repo);
+        assert!(!probe.is_worktree);
+
+        let _ = std::fs::remove_dir_all(&repo);
+        let _ = std::fs::remove_dir_all(&bare);
+    }
+
+    // ==================== clone_repository tests ====================
+
+    #[test]
+    fn test_clone_args() {
+        assert_eq!(
+            build_clone_args("https://example.com/repo.git", "/tmp/repo", false),
+            vec![
+                "clone",
+                "--progress",
+                "--",
+                "https://example.com/repo.git",
+                "/tmp/repo"
+            ]
+        );
+        assert_eq!(
+            build_clone_args("git@example.com:repo.git", "/tmp/repo.git", true),
+            vec![
+                "clone",
+                "--progress",
+                "--bare",
+                "--",
+                "git@example.com:repo.git",
+                "/tmp/repo.git"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_progress_line() {
+        assert_eq!(
+            parse_progress_line("Receiving objects:  42% (420/1000), 1.20 MiB | 2.00 MiB/s"),
+            Some(("Receiving objects".to_string(), 42))
+        );
+        assert_eq!(
+            parse_progress_line("remote: Counting objects: 100% (5/5), done."),
+            Some(("Counting objects".to_string(), 100))
+        );
+        assert_eq!(parse_progress_line("Cloning into 'repo'..."), None);
+        assert_eq!(
+            parse_progress_line("remote: Enumerating objects: 5, done."),
+            None
+        );
+    }
+
+    #[test]
+    fn test_clone_repository() {
+        let source = init_test_repo("clone-source");
+        let dest = temp_path("clone-dest");
+        let _ = std::fs::remove_dir_all(&dest);
+
+        let cancel = AtomicBool::new(false);
+        let url = format!("file://{}", source.to_string_lossy());
+        let probe =
+            clone_repository(&url, &dest.to_string_lossy(), false, &cancel, |_, _| {}).unwrap();
+        assert!(probe.is_worktree);
+        assert!(dest.join("README.md").exists());
+        let _ = std::fs::remove_dir_all(&dest);
+
+        // Cancellation doesn't wait for git to print anything
+        let cancel = AtomicBool::new(true);
+        let err =
+            clone_repository(&url, &dest.to_string_lossy(), false, &cancel, |_, _| {}).unwrap_err();
+        assert_eq!(err, "Clone cancelled");
+        assert!(!dest.exists());
+
+        let _ = std::fs::remove_dir_all(&source);
+    }
+
+    // ==================== run_gc tests ====================
+
+    #[test]
+    fn test_build_gc_args() {
+        assert_eq!(build_gc_args(false), vec!["gc"]);
+        assert_eq!(build_gc_args(true), vec!["gc", "--aggressive"]);
+    }
+
+    #[test]
+    fn test_dir_size() {
+        let dir = temp_path("dir-size");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("nested/deeper")).unwrap();
+        std::fs::write(dir.join("a.bin"), vec![0u8; 100]).unwrap();
+        std::fs::write(dir.join("nested/b.bin"), vec![0u8; 20]).unwrap();
+        std::fs::write(dir.join("nested/deeper/c.bin"), vec![0u8; 3]).unwrap();
+
+        assert_eq!(dir_size(&dir), 123);
+        assert_eq!(dir_size(&dir.join("missing")), 0);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_run_gc() {
+        let repo = init_test_repo("run-gc");
+        let path = repo.to_string_lossy();
+
+        let mut phases = Vec::new();
+        let result = run_gc(&path, false, &AtomicBool::new(false), |phase, percent| {
+            phases.push((phase, percent))
+        })
+        .unwrap();
+        assert!(result.before_size > 0);
+        assert!(result.after_size > 0);
+        assert_eq!(phases.first(), Some(&(GC_PHASE.to_string(), 0)));
+        assert_eq!(phases.last(), Some(&(GC_PHASE.to_string(), 100)));
+
+        let err = run_gc(&path, false, &AtomicBool::new(true), |_, _| {}).unwrap_err();
+        assert!(err.contains("cancelled"));
+
+        let _ = std::fs::remove_dir_all(&repo);
     }
 
-    args.push(&options.path);
+    // ==================== detach/reattach tests ====================
 
-    if let Some(ref commit_ish) = options.commit_ish {
-        args.push(commit_ish);
+    #[test]
+    fn test_detach_args() {
+        assert_eq!(build_detach_args(), ["checkout", "-q", "--detach"]);
     }
 
-    run_git(repo_path, &args)?;
+    #[test]
+    fn test_reattach_args() {
+        assert_eq!(
+            build_reattach_args("feature/x"),
+            ["checkout", "-q", "feature/x", "--"]
+        );
+    }
 
-    // Build and return the new worktree info
-    let path = PathBuf::from(&options.path);
-    build_worktree_info(&path, false)
-}
+    #[test]
+    fn test_detach_and_reattach_head() {
+        let repo = init_test_repo("detach-head");
+        let path = repo.to_string_lossy();
 
-/// Delete a worktree
-pub fn delete_worktree(repo_path: &str, worktree_path: &str, force: bool) -> Result<(), String> {
-    let mut args = vec!["worktree", "remove"];
+        let head = detach_head(&path).unwrap();
+        assert_eq!(head.branch, None);
+        assert_eq!(detach_head(&path).unwrap_err(), "HEAD is already detached");
 
-    if force {
-        args.push("--force");
+        assert_eq!(
+            reattach_head(&path, "--force").unwrap_err(),
+            "Invalid branch name: --force"
+        );
+        run_git(&path, &["tag", "v1.0.0"]).unwrap();
+        assert_eq!(
+            reattach_head(&path, "v1.0.0").unwrap_err(),
+            "Branch v1.0.0 does not exist"
+        );
+        assert_eq!(get_current_branch(&path).unwrap(), None);
+
+        let head = reattach_head(&path, "main").unwrap();
+        assert_eq!(head.branch.as_deref(), Some("main"));
+
+        let _ = std::fs::remove_dir_all(&repo);
     }
 
-    args.push(worktree_path);
+    #[test]
+    fn test_rename_current_branch_args() {
+        assert_eq!(
+            build_rename_current_branch_args("feature/renamed"),
+            ["branch", "-m", "feature/renamed"]
+        );
+    }
 
-    run_git(repo_path, &args)?;
-    Ok(())
-}
+    #[test]
+    fn test_rename_current_branch() {
+        let repo = init_test_repo("rename-current");
+        let path = repo.to_string_lossy();
 
-/// Prune stale worktree references
-pub fn prune_worktrees(repo_path: &str) -> Result<PruneResult, String> {
-    // First, do a dry run to see what would be pruned
-    let dry_run_output = run_git(repo_path, &["worktree", "prune", "--dry-run"])?;
+        let head = rename_current_branch(&path, "trunk").unwrap();
+        assert_eq!(head.branch.as_deref(), Some("trunk"));
 
-    let messages: Vec<String> = dry_run_output
-        .lines()
-        .filter(|line| !line.is_empty())
-        .map(|line| line.to_string())
-        .collect();
+        run_git(&path, &["checkout", "-q", "--detach"]).unwrap();
+        let err = rename_current_branch(&path, "other").unwrap_err();
+        assert!(err.contains("detached"));
 
-    let pruned_count = messages.len() as u32;
+        let _ = std::fs::remove_dir_all(&repo);
+    }
 
-    // Actually prune
-    run_git(repo_path, &["worktree", "prune"])?;
+    // ==================== worktree label tests ====================
 
-    Ok(PruneResult {
-        pruned_count,
-        messages,
-    })
-}
+    #[test]
+    fn test_worktree_label_round_trip() {
+        let repo = init_test_repo("worktree-label");
+        let path = repo.to_string_lossy();
+        let linked = temp_path("worktree-label-linked");
+        let linked_str = linked.to_string_lossy();
+        run_git(&path, &["worktree", "add", "-q", "--detach", &linked_str]).unwrap();
+
+        assert_eq!(get_worktree_label(&path), Ok(None));
+
+        set_worktree_label(&path, "Main checkout").unwrap();
+        set_worktree_label(&linked_str, "Hotfix").unwrap();
+        // Labels are per worktree, even with several worktrees sharing one repo
+        assert_eq!(
+            get_worktree_label(&path),
+            Ok(Some("Main checkout".to_string()))
+        );
+        assert_eq!(
+            get_worktree_label(&linked_str),
+            Ok(Some("Hotfix".to_string()))
+        );
+        assert_eq!(
+            build_worktree_info(&linked, false).unwrap().label,
+            Some("Hotfix".to_string())
+        );
 
-/// List all branches (local and remote)
-pub fn list_branches(repo_path: &str) -> Result<Vec<BranchInfo>, String> {
-    // Get list of checked out branches from worktrees
-    let worktree_output = run_git(repo_path, &["worktree", "list", "--porcelain"])?;
-    let mut checked_out_branches: Vec<String> = Vec::new();
+        set_worktree_label(&path, "").unwrap();
+        assert_eq!(get_worktree_label(&path), Ok(None));
+        // Clearing an unset label is a no-op
+        set_worktree_label(&path, "").unwrap();
 
-    for line in worktree_output.lines() {
-        if let Some(branch) = line.strip_prefix("branch refs/heads/") {
-            checked_out_branches.push(branch.to_string());
-        }
+        let _ = std::fs::remove_dir_all(&linked);
+        let _ = std::fs::remove_dir_all(&repo);
     }
 
-    // Get all branches with format: refname, is_remote indicator
-    // Using for-each-ref for better control over output
-    let output = run_git(
-        repo_path,
-        &[
-            "for-each-ref",
-            "--format=%(refname:short)%09%(if)%(upstream)%(then)local%(else)%(if:equals=refs/remotes)%(refname:rstrip=-2)%(then)remote%(else)local%(end)%(end)",
-            "refs/heads",
-            "refs/remotes",
-        ],
-    )?;
+    // ==================== sparse-checkout tests ====================
 
-    let mut branches: Vec<BranchInfo> = Vec::new();
+    #[test]
+    fn test_detect_sparse() {
+        assert!(detect_sparse(Some("true"), false));
+        assert!(!detect_sparse(Some("false"), true));
+        assert!(detect_sparse(None, true));
+        assert!(!detect_sparse(None, false));
+    }
 
-    for line in output.lines() {
-        let line = line.trim();
-        if line.is_empty() {
-            continue;
-        }
+    #[test]
+    fn test_parse_sparse_patterns() {
+        let content = "# cone mode\n/*\n!/*/\n\n/src/\n";
+        assert_eq!(parse_sparse_patterns(content), vec!["/*", "!/*/", "/src/"]);
+        assert!(parse_sparse_patterns("").is_empty());
+    }
 
-        let parts: Vec<&str> = line.split('\t').collect();
-        let name = parts[0].to_string();
+    #[test]
+    fn test_sparse_worktree_detection() {
+        let repo = init_test_repo("sparse");
+        let path = repo.to_string_lossy();
+        assert!(!is_sparse_checkout(&path));
+        assert!(get_sparse_patterns(&path).unwrap().is_empty());
+
+        run_git(
+            &path,
+            &["sparse-checkout", "set", "--no-cone", "/README.md"],
+        )
+        .unwrap();
+        assert!(is_sparse_checkout(&path));
+        assert_eq!(get_sparse_patterns(&path).unwrap(), vec!["/README.md"]);
 
-        // Skip HEAD references from remotes
-        if name.ends_with("/HEAD") {
-            continue;
-        }
+        // "disable" leaves the pattern file behind but sets the config to false
+        run_git(&path, &["sparse-checkout", "disable"]).unwrap();
+        assert!(!is_sparse_checkout(&path));
 
-        let is_remote = name.contains('/');
-        let is_checked_out = if is_remote {
-            false
-        } else {
-            checked_out_branches.contains(&name)
-        };
+        let _ = std::fs::remove_dir_all(&repo);
+    }
 
-        branches.push(BranchInfo {
-            name,
-            is_remote,
-            is_checked_out,
-        });
+    // ==================== merge_base tests ====================
+
+    #[test]
+    fn test_merge_base_args() {
+        let refs = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            build_merge_base_args(&refs(&["a", "b"])),
+            vec!["merge-base", "a", "b"]
+        );
+        assert_eq!(
+            build_merge_base_args(&refs(&["a", "b", "c"])),
+            vec!["merge-base", "--octopus", "a", "b", "c"]
+        );
     }
 
-    // Sort: local branches first, then remote, alphabetically within each group
-    branches.sort_by(|a, b| {
-        match (a.is_remote, b.is_remote) {
-            (false, true) => std::cmp::Ordering::Less,
-            (true, false) => std::cmp::Ordering::Greater,
-            _ => a.name.cmp(&b.name),
-        }
-    });
+    #[test]
+    fn test_interpret_merge_base_output() {
+        assert_eq!(
+            interpret_merge_base_output(Some(0), "abc123\n", ""),
+            Ok(Some("abc123".to_string()))
+        );
+        assert_eq!(interpret_merge_base_output(Some(1), "", ""), Ok(None));
+        assert!(
+            interpret_merge_base_output(Some(128), "", "fatal: Not a valid object name x").is_err()
+        );
+    }
 
-    Ok(branches)
-}
+    #[test]
+    fn test_merge_base() {
+        let repo = init_test_repo("merge-base");
+        let path = repo.to_string_lossy();
+        let root = run_git(&path, &["rev-parse", "HEAD"])
+            .unwrap()
+            .trim()
+            .to_string();
+        run_git(&path, &["checkout", "-q", "-b", "feature"]).unwrap();
+        run_git(&path, &["commit", "-q", "--allow-empty", "-m", "Feature"]).unwrap();
+        run_git(&path, &["checkout", "-q", "main"]).unwrap();
+        run_git(&path, &["commit", "-q", "--allow-empty", "-m", "Main"]).unwrap();
+
+        let refs = vec!["main".to_string(), "feature".to_string()];
+        assert_eq!(merge_base(&path, &refs), Ok(Some(root)));
+
+        // An orphan branch shares no history with main
+        run_git(&path, &["checkout", "-q", "--orphan", "unrelated"]).unwrap();
+        run_git(&path, &["commit", "-q", "--allow-empty", "-m", "Unrelated"]).unwrap();
+        let refs = vec!["main".to_string(), "unrelated".to_string()];
+        assert_eq!(merge_base(&path, &refs), Ok(None));
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
 
-/// Parse git status --porcelain output into WorktreeStatus
-/// Extracted for testability
-fn parse_status_porcelain(output: &str) -> WorktreeStatus {
-    let mut modified = 0u32;
-    let mut staged = 0u32;
-    let mut untracked = 0u32;
-    let mut conflicted = 0u32;
+    // ==================== get_conflict_versions tests ====================
 
-    for line in output.lines() {
-        if line.len() < 2 {
-            continue;
-        }
+    #[test]
+    fn test_parse_unmerged_stages() {
+        let output =
+            "100644 aaaa 1\tsrc/lib.rs\x00100644 bbbb 2\tsrc/lib.rs\x00100644 cccc 3\tsrc/lib.rs\0";
+        assert_eq!(
+            parse_unmerged_stages(output),
+            [
+                Some("aaaa".to_string()),
+                Some("bbbb".to_string()),
+                Some("cccc".to_string())
+            ]
+        );
 
-        let index_status = line.chars().next().unwrap_or(' ');
-        let worktree_status = line.chars().nth(1).unwrap_or(' ');
+        // Modify/delete: theirs deleted the file
+        let output = "100644 aaaa 1\tgone.rs\x00100644 bbbb 2\tgone.rs\0";
+        assert_eq!(
+            parse_unmerged_stages(output),
+            [Some("aaaa".to_string()), Some("bbbb".to_string()), None]
+        );
+        assert_eq!(parse_unmerged_stages(""), [None, None, None]);
+    }
 
-        // Check for conflicts (UU, AA, DD, AU, UA, DU, UD)
-        if matches!(
-            (index_status, worktree_status),
-            ('U', 'U') | ('A', 'A') | ('D', 'D') | ('A', 'U') | ('U', 'A') | ('D', 'U') | ('U', 'D')
-        ) {
-            conflicted += 1;
-        } else if index_status == '?' && worktree_status == '?' {
-            // Untracked
-            untracked += 1;
-        } else {
-            // Check staged changes (index column)
-            if matches!(index_status, 'M' | 'A' | 'D' | 'R' | 'C') {
-                staged += 1;
-            }
-            // Check unstaged changes (worktree column) - only if not already counted as staged
-            else if matches!(worktree_status, 'M' | 'D') {
-                modified += 1;
-            }
-        }
+    #[test]
+    fn test_conflict_versions_add_add() {
+        let repo = init_test_repo("conflict-versions");
+        let path = repo.to_string_lossy();
+        run_git(&path, &["checkout", "-q", "-b", "feature"]).unwrap();
+        std::fs::write(repo.join("new.txt"), "theirs\n").unwrap();
+        run_git(&path, &["add", "new.txt"]).unwrap();
+        run_git(&path, &["commit", "-q", "-m", "Add on feature"]).unwrap();
+        run_git(&path, &["checkout", "-q", "main"]).unwrap();
+        std::fs::write(repo.join("new.txt"), "ours\n").unwrap();
+        run_git(&path, &["add", "new.txt"]).unwrap();
+        run_git(&path, &["commit", "-q", "-m", "Add on main"]).unwrap();
+        assert!(run_git(&path, &["merge", "-q", "feature"]).is_err());
+
+        let versions = get_conflict_versions(&path, "new.txt").unwrap();
+        assert_eq!(versions.base, None);
+        assert_eq!(versions.ours.as_deref(), Some("ours\n"));
+        assert_eq!(versions.theirs.as_deref(), Some("theirs\n"));
+
+        assert!(get_conflict_versions(&path, "README.md").is_err());
+
+        let _ = std::fs::remove_dir_all(&repo);
     }
 
-    WorktreeStatus {
-        is_clean: modified == 0 && staged == 0 && untracked == 0 && conflicted == 0,
-        modified,
-        staged,
-        untracked,
-        conflicted,
+    #[test]
+    fn test_parse_unmerged_paths() {
+        let output = "100644 aaaa 1\ta.txt\x00100644 bbbb 2\ta.txt\x00100644 cccc 3\ta.txt\x00\
+                      100644 dddd 2\tb c.txt\x00100644 eeee 3\tb c.txt\0";
+        assert_eq!(parse_unmerged_paths(output), vec!["a.txt", "b c.txt"]);
+        assert!(parse_unmerged_paths("").is_empty());
     }
-}
 
-/// Parse git log output with record/unit separators into Vec<CommitInfo>
-/// Extracted for testability
-fn parse_commit_log(output: &str) -> Vec<CommitInfo> {
-    let mut commits = Vec::new();
+    #[test]
+    fn test_has_diff3_base_section() {
+        let two_way = "<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> feature\n";
+        assert!(!has_diff3_base_section(two_way));
 
-    for record in output.split('\x1e') {
-        let record = record.trim();
-        if record.is_empty() {
-            continue;
-        }
+        let diff3 =
+            "<<<<<<< HEAD\nours\n||||||| base\noriginal\n=======\ntheirs\n>>>>>>> feature\n";
+        assert!(has_diff3_base_section(diff3));
+    }
 
-        let fields: Vec<&str> = record.split('\x1f').collect();
-        if fields.len() < 6 {
-            continue;
-        }
+    #[test]
+    fn test_list_conflicts() {
+        let repo = init_test_repo("list-conflicts");
+        let path = repo.to_string_lossy();
+        std::fs::write(repo.join("other.txt"), "base\n").unwrap();
+        run_git(&path, &["add", "other.txt"]).unwrap();
+        run_git(&path, &["commit", "-q", "-m", "Add other"]).unwrap();
+
+        run_git(&path, &["checkout", "-q", "-b", "feature"]).unwrap();
+        std::fs::write(repo.join("README.md"), "feature\n").unwrap();
+        std::fs::write(repo.join("other.txt"), "feature\n").unwrap();
+        run_git(&path, &["commit", "-q", "-am", "feature"]).unwrap();
+        run_git(&path, &["checkout", "-q", "main"]).unwrap();
+        std::fs::write(repo.join("README.md"), "main\n").unwrap();
+        std::fs::write(repo.join("other.txt"), "main\n").unwrap();
+        run_git(&path, &["commit", "-q", "-am", "main"]).unwrap();
+        assert!(run_git(
+            &path,
+            &["-c", "merge.conflictStyle=diff3", "merge", "-q", "feature"]
+        )
+        .is_err());
 
-        let hash = fields[0].to_string();
-        let short_hash = fields[1].to_string();
-        let author_name = fields[2].to_string();
-        let author_email = fields[3].to_string();
-        let timestamp = fields[4].parse::<i64>().unwrap_or(0);
-        let summary = fields[5].to_string();
-        let message = fields.get(6).unwrap_or(&"").trim().to_string();
+        // Rewrite one file with two-way markers, as a default-style merge would leave it
+        std::fs::write(
+            repo.join("other.txt"),
+            "<<<<<<< HEAD\nmain\n=======\nfeature\n>>>>>>> feature\n",
+        )
+        .unwrap();
 
-        commits.push(CommitInfo {
-            hash,
-            short_hash,
-            author_name,
-            author_email,
-            timestamp,
-            message,
-            summary,
-        });
+        let conflicts = list_conflicts(&path).unwrap();
+        let summary: Vec<(&str, bool)> = conflicts
+            .iter()
+            .map(|c| (c.path.as_str(), c.has_base_section))
+            .collect();
+        assert_eq!(summary, vec![("README.md", true), ("other.txt", false)]);
+
+        let _ = std::fs::remove_dir_all(&repo);
     }
 
-    commits
-}
+    // ==================== untracked_summary tests ====================
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_parse_untracked_summary() {
+        let output = " M src/main.rs\x00?? node_modules/\x00?? notes.txt\x00?? build output/\x00";
+        let summary = parse_untracked_summary(output);
+        assert_eq!(
+            summary.top_level_entries,
+            vec!["node_modules/", "notes.txt", "build output/"]
+        );
+        assert!(summary.approximate);
 
-    // ==================== parse_range tests ====================
+        let summary = parse_untracked_summary("?? notes.txt\x00");
+        assert_eq!(summary.top_level_entries, vec!["notes.txt"]);
+        assert!(!summary.approximate);
+    }
 
     #[test]
-    fn test_parse_range_with_comma() {
-        assert_eq!(parse_range("1,5"), Some((1, 5)));
-        assert_eq!(parse_range("10,20"), Some((10, 20)));
-        assert_eq!(parse_range("0,0"), Some((0, 0)));
+    fn test_untracked_summary_collapses_directories() {
+        let repo = init_test_repo("untracked-summary");
+        let path = repo.to_string_lossy();
+        std::fs::create_dir_all(repo.join("target").join("debug")).unwrap();
+        for i in 0..5 {
+            std::fs::write(
+                repo.join("target").join("debug").join(format!("{}.o", i)),
+                "",
+            )
+            .unwrap();
+        }
+
+        let summary = untracked_summary(&path).unwrap();
+        assert_eq!(summary.top_level_entries, vec!["target/"]);
+        assert!(summary.approximate);
+
+        let _ = std::fs::remove_dir_all(&repo);
     }
 
+    // ==================== uncommit_keep_changes tests ====================
+
     #[test]
-    fn test_parse_range_single_number() {
-        // Single number implies 1 line
-        assert_eq!(parse_range("1"), Some((1, 1)));
-        assert_eq!(parse_range("10"), Some((10, 1)));
-        assert_eq!(parse_range("0"), Some((0, 1)));
+    fn test_squash_reset_args() {
+        assert_eq!(
+            build_squash_reset_args(3),
+            vec!["reset", "--soft", "HEAD~3"]
+        );
     }
 
     #[test]
-    fn test_parse_range_invalid() {
-        assert_eq!(parse_range(""), None);
-        assert_eq!(parse_range("abc"), None);
-        assert_eq!(parse_range("1,abc"), None);
-        assert_eq!(parse_range("abc,1"), None);
-    }
+    fn test_squash_last_validates_count() {
+        let repo = init_test_repo("squash-validate");
+        let path = repo.to_string_lossy();
+        std::fs::write(repo.join("README.md"), "second\n").unwrap();
+        run_git(&path, &["commit", "-q", "-am", "Second"]).unwrap();
 
-    // ==================== parse_hunk_header tests ====================
+        let err = squash_last(&path, 1, "Squashed".to_string()).unwrap_err();
+        assert!(err.contains("at least 2"));
+
+        // Only two commits exist, so HEAD~2 is missing
+        let err = squash_last(&path, 2, "Squashed".to_string()).unwrap_err();
+        assert!(err.contains("does not exist"));
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
 
     #[test]
-    fn test_parse_hunk_header_basic() {
-        assert_eq!(
-            parse_hunk_header("@@ -1,5 +1,7 @@"),
-            Some((1, 5, 1, 7))
-        );
+    fn test_squash_last() {
+        let repo = init_test_repo("squash-last");
+        let path = repo.to_string_lossy();
+        for (file, summary) in [("a.txt", "WIP a"), ("b.txt", "WIP b"), ("c.txt", "WIP c")] {
+            std::fs::write(repo.join(file), "x\n").unwrap();
+            run_git(&path, &["add", file]).unwrap();
+            run_git(&path, &["commit", "-q", "-m", summary]).unwrap();
+        }
+        let initial = run_git(&path, &["rev-parse", "HEAD~3"]).unwrap();
+
+        let commit = squash_last(&path, 3, "-m Add a, b and c\n\nDetails".to_string()).unwrap();
+        assert_eq!(commit.summary, "-m Add a, b and c");
+        assert_eq!(commit.parent_hashes, vec![initial.trim().to_string()]);
+
+        let files = run_git(&path, &["show", "--name-only", "--format=", "HEAD"]).unwrap();
         assert_eq!(
-            parse_hunk_header("@@ -10,3 +12,5 @@"),
-            Some((10, 3, 12, 5))
+            files.lines().collect::<Vec<_>>(),
+            vec!["a.txt", "b.txt", "c.txt"]
         );
+        let status = run_git(&path, &["status", "--porcelain"]).unwrap();
+        assert!(status.is_empty());
+
+        let _ = std::fs::remove_dir_all(&repo);
     }
 
     #[test]
-    fn test_parse_hunk_header_single_line() {
-        // Single line changes (no comma means 1 line)
-        assert_eq!(
-            parse_hunk_header("@@ -1 +1 @@"),
-            Some((1, 1, 1, 1))
-        );
+    fn test_uncommit_args() {
+        assert_eq!(build_uncommit_args(), ["reset", "--soft", "HEAD~1"]);
     }
 
     #[test]
-    fn test_parse_hunk_header_with_context() {
-        // Hunk headers can have function context after @@
+    fn test_uncommit_keep_changes() {
+        let repo = init_test_repo("uncommit");
+        let path = repo.to_string_lossy();
+
+        // Only the initial commit exists
+        let err = uncommit_keep_changes(&path).unwrap_err();
+        assert!(err.contains("no parent"));
+
+        std::fs::write(repo.join("README.md"), "changed\n").unwrap();
+        run_git(&path, &["commit", "-q", "-am", "Change readme"]).unwrap();
+        let status = uncommit_keep_changes(&path).unwrap();
+        assert_eq!(status.staged, 1);
         assert_eq!(
-            parse_hunk_header("@@ -10,3 +12,5 @@ fn some_function()"),
-            Some((10, 3, 12, 5))
+            run_git(&path, &["log", "-1", "--format=%s"])
+                .unwrap()
+                .trim(),
+            "Initial commit"
         );
+
+        let _ = std::fs::remove_dir_all(&repo);
     }
 
+    // ==================== parse_stat_graphs tests ====================
+
     #[test]
-    fn test_parse_hunk_header_new_file() {
-        // New file: old side is 0,0
+    fn test_parse_stat_graphs() {
+        let output = " src/main.rs | 12 ++++++++----\n \
+                      image.png   | Bin 0 -> 1234 bytes\n \
+                      script.sh   |  0\n \
+                      a|b.txt     |  1 -\n \
+                      src/{a => b}/lib.rs | 2 ++\n \
+                      5 files changed, 8 insertions(+), 5 deletions(-)\n";
+        let graphs = parse_stat_graphs(output);
         assert_eq!(
-            parse_hunk_header("@@ -0,0 +1,10 @@"),
-            Some((0, 0, 1, 10))
+            graphs,
+            vec![
+                ("src/main.rs".to_string(), Some("++++++++----".to_string())),
+                ("image.png".to_string(), None),
+                ("script.sh".to_string(), None),
+                ("a|b.txt".to_string(), Some("-".to_string())),
+                ("src/b/lib.rs".to_string(), Some("++".to_string())),
+            ]
         );
     }
 
     #[test]
-    fn test_parse_hunk_header_deleted_file() {
-        // Deleted file: new side is 0,0
+    fn test_split_stat_and_patch() {
+        let output = " a.txt | 1 +\n 1 file changed, 1 insertion(+)\n\n\
+                      diff --git a/a.txt b/a.txt\n+x\n";
+        let (stat, patch) = split_stat_and_patch(output);
+        assert!(stat.contains("1 file changed"));
+        assert!(patch.starts_with("diff --git a/a.txt b/a.txt\n"));
+
+        assert_eq!(split_stat_and_patch(""), ("", ""));
         assert_eq!(
-            parse_hunk_header("@@ -1,10 +0,0 @@"),
-            Some((1, 10, 0, 0))
+            split_stat_and_patch("diff --git a/x b/x\n"),
+            ("", "diff --git a/x b/x\n")
         );
     }
 
     #[test]
-    fn test_parse_hunk_header_invalid() {
-        assert_eq!(parse_hunk_header("not a hunk header"), None);
-        assert_eq!(parse_hunk_header("@@ @@"), None);
-        assert_eq!(parse_hunk_header("@@ -1 @@"), None);
+    fn test_commit_diff_stat_graph() {
+        let repo = init_test_repo("stat-graph");
+        let path = repo.to_string_lossy();
+
+        std::fs::write(repo.join("README.md"), "one\ntwo\n").unwrap();
+        std::fs::write(repo.join("blob.bin"), [0u8, 1, 2]).unwrap();
+        run_git(&path, &["add", "-A"]).unwrap();
+        run_git(&path, &["commit", "-q", "-m", "Second"]).unwrap();
+
+        let diff = get_commit_diff(&path, "HEAD").unwrap();
+        let bin = diff.files.iter().find(|f| f.path == "blob.bin").unwrap();
+        assert_eq!(bin.stat_graph, None);
+        let readme = diff.files.iter().find(|f| f.path == "README.md").unwrap();
+        assert!(readme.stat_graph.as_deref().unwrap().contains('+'));
+
+        // Renamed files are matched to their graph by new path, not position
+        std::fs::create_dir_all(repo.join("docs")).unwrap();
+        run_git(&path, &["mv", "README.md", "docs/README.md"]).unwrap();
+        std::fs::write(repo.join("docs/README.md"), "one\ntwo\nthree\n").unwrap();
+        std::fs::write(repo.join("added.txt"), "a\nb\n").unwrap();
+        run_git(&path, &["add", "-A"]).unwrap();
+        run_git(&path, &["commit", "-q", "-m", "Third"]).unwrap();
+
+        let diff = get_commit_diff(&path, "HEAD").unwrap();
+        let moved = diff
+            .files
+            .iter()
+            .find(|f| f.path == "docs/README.md")
+            .unwrap();
+        assert_eq!(moved.stat_graph.as_deref(), Some("+"));
+        let added = diff.files.iter().find(|f| f.path == "added.txt").unwrap();
+        assert_eq!(added.stat_graph.as_deref(), Some("++"));
+
+        let _ = std::fs::remove_dir_all(&repo);
     }
 
-    // ==================== parse_git_diff_output tests ====================
+    // ==================== restore_file_from_commit tests ====================
 
     #[test]
-    fn test_parse_diff_empty() {
-        let files = parse_git_diff_output("");
-        assert!(files.is_empty());
+    fn test_restore_file_args() {
+        assert_eq!(
+            build_restore_file_args("abc123", "src/lib.rs"),
+            ["checkout", "abc123", "--", "src/lib.rs"]
+        );
     }
 
     #[test]
-    fn test_parse_diff_single_modified_file() {
-        let diff = r#"diff --git a/src/main.rs b/src/main.rs
-index abc1234..def5678 100644
---- a/src/main.rs
-+++ b/src/main.rs
-@@ -1,3 +1,4 @@
- fn main() {
-+    println!("Hello");
-     println!("World");
- }
-"#;
-        let files = parse_git_diff_output(diff);
-        assert_eq!(files.len(), 1);
-        assert_eq!(files[0].path, "src/main.rs");
-        assert!(matches!(files[0].status, FileStatus::Modified));
-        assert!(!files[0].binary);
-        assert_eq!(files[0].hunks.len(), 1);
+    fn test_restore_file_from_commit() {
+        let repo = init_test_repo("restore-file");
+        let path = repo.to_string_lossy();
 
-        let hunk = &files[0].hunks[0];
-        assert_eq!(hunk.old_start, 1);
-        assert_eq!(hunk.old_lines, 3);
-        assert_eq!(hunk.new_start, 1);
-        assert_eq!(hunk.new_lines, 4);
-        assert_eq!(hunk.lines.len(), 4);
+        run_git(&path, &["rm", "-q", "README.md"]).unwrap();
+        run_git(&path, &["commit", "-q", "-m", "Remove readme"]).unwrap();
 
-        // Check line types
-        assert_eq!(hunk.lines[0].kind, ' '); // context
-        assert_eq!(hunk.lines[1].kind, '+'); // addition
-        assert_eq!(hunk.lines[2].kind, ' '); // context
-        assert_eq!(hunk.lines[3].kind, ' '); // context
+        let err = restore_file_from_commit(&path, "HEAD", "README.md").unwrap_err();
+        assert!(err.contains("does not exist"));
+
+        restore_file_from_commit(&path, "HEAD~1", "README.md").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(repo.join("README.md")).unwrap(),
+            "hello\n"
+        );
+
+        let _ = std::fs::remove_dir_all(&repo);
     }
 
+    // ==================== assume-unchanged tests ====================
+
     #[test]
-    fn test_parse_diff_new_file() {
-        let diff = r#"diff --git a/new_file.txt b/new_file.txt
-new file mode 100644
-index 0000000..abc1234
---- /dev/null
-+++ b/new_file.txt
-@@ -0,0 +1,2 @@
-+line 1
-+line 2
-"#;
-        let files = parse_git_diff_output(diff);
-        assert_eq!(files.len(), 1);
-        assert_eq!(files[0].path, "new_file.txt");
-        assert!(matches!(files[0].status, FileStatus::Added));
+    fn test_parse_assume_unchanged() {
+        let output =
+            "H README.md\0h config/local.toml\0S sparse.txt\0s both.txt\0H with space.txt\0";
+        assert_eq!(
+            parse_assume_unchanged(output),
+            vec!["config/local.toml", "both.txt"]
+        );
+        assert!(parse_assume_unchanged("").is_empty());
     }
 
     #[test]
-    fn test_parse_diff_deleted_file() {
-        let diff = r#"diff --git a/old_file.txt b/old_file.txt
-deleted file mode 100644
-index abc1234..0000000
---- a/old_file.txt
-+++ /dev/null
-@@ -1,2 +0,0 @@
--line 1
--line 2
-"#;
-        let files = parse_git_diff_output(diff);
-        assert_eq!(files.len(), 1);
-        assert_eq!(files[0].path, "old_file.txt");
-        assert!(matches!(files[0].status, FileStatus::Deleted));
+    fn test_set_assume_unchanged() {
+        let repo = init_test_repo("assume-unchanged");
+        let path = repo.to_string_lossy();
+
+        set_assume_unchanged(&path, vec!["README.md".to_string()], true).unwrap();
+        assert_eq!(list_assume_unchanged(&path).unwrap(), vec!["README.md"]);
+
+        set_assume_unchanged(&path, vec!["README.md".to_string()], false).unwrap();
+        assert!(list_assume_unchanged(&path).unwrap().is_empty());
+
+        let _ = std::fs::remove_dir_all(&repo);
     }
 
+    // ==================== get_current_branch tests ====================
+
     #[test]
-    fn test_parse_diff_renamed_file() {
-        let diff = r#"diff --git a/old_name.rs b/new_name.rs
-similarity index 95%
-rename from old_name.rs
-rename to new_name.rs
-index abc1234..def5678 100644
---- a/old_name.rs
-+++ b/new_name.rs
-@@ -1,3 +1,3 @@
- fn main() {
--    old();
-+    new();
- }
-"#;
-        let files = parse_git_diff_output(diff);
-        assert_eq!(files.len(), 1);
-        assert_eq!(files[0].path, "new_name.rs");
-        assert!(matches!(files[0].status, FileStatus::Renamed));
-        assert_eq!(files[0].old_path, Some("old_name.rs".to_string()));
+    fn test_get_current_branch() {
+        let repo = init_test_repo("current-branch");
+        let path = repo.to_string_lossy();
+
+        assert_eq!(get_current_branch(&path).unwrap().as_deref(), Some("main"));
+
+        run_git(&path, &["checkout", "-q", "--detach"]).unwrap();
+        assert_eq!(get_current_branch(&path).unwrap(), None);
+
+        let _ = std::fs::remove_dir_all(&repo);
     }
 
     #[test]
-    fn test_parse_diff_binary_file() {
-        let diff = r#"diff --git a/image.png b/image.png
-new file mode 100644
-index 0000000..abc1234
-Binary files /dev/null and b/image.png differ
-"#;
-        let files = parse_git_diff_output(diff);
-        assert_eq!(files.len(), 1);
-        assert_eq!(files[0].path, "image.png");
-        assert!(files[0].binary);
-        assert!(files[0].hunks.is_empty());
+    fn test_get_current_branch_not_a_repo() {
+        let dir = temp_path("current-branch-not-repo");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        assert!(get_current_branch(&dir.to_string_lossy()).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
     }
 
+    // ==================== branch_checkout_conflict tests ====================
+
     #[test]
-    fn test_parse_diff_multiple_files() {
-        let diff = r#"diff --git a/file1.rs b/file1.rs
-index abc..def 100644
---- a/file1.rs
-+++ b/file1.rs
-@@ -1 +1 @@
--old
-+new
-diff --git a/file2.rs b/file2.rs
-index 123..456 100644
---- a/file2.rs
-+++ b/file2.rs
-@@ -1 +1 @@
--foo
-+bar
-"#;
-        let files = parse_git_diff_output(diff);
-        assert_eq!(files.len(), 2);
-        assert_eq!(files[0].path, "file1.rs");
-        assert_eq!(files[1].path, "file2.rs");
+    fn test_parse_worktree_branches() {
+        let output = "worktree /repos/app
+HEAD abc1234
+branch refs/heads/main
+
+worktree /repos/app-detached
+HEAD def5678
+detached
+
+worktree /repos/app-feature
+HEAD 0123456
+branch refs/heads/feature/x
+";
+        assert_eq!(
+            parse_worktree_branches(output),
+            vec![
+                (PathBuf::from("/repos/app"), "main".to_string()),
+                (PathBuf::from("/repos/app-feature"), "feature/x".to_string()),
+            ]
+        );
     }
 
     #[test]
-    fn test_parse_diff_multiple_hunks() {
-        let diff = r#"diff --git a/file.rs b/file.rs
-index abc..def 100644
---- a/file.rs
-+++ b/file.rs
-@@ -1,3 +1,3 @@
- fn foo() {
--    old1();
-+    new1();
- }
-@@ -10,3 +10,3 @@
- fn bar() {
--    old2();
-+    new2();
- }
-"#;
-        let files = parse_git_diff_output(diff);
-        assert_eq!(files.len(), 1);
-        assert_eq!(files[0].hunks.len(), 2);
-        assert_eq!(files[0].hunks[0].old_start, 1);
-        assert_eq!(files[0].hunks[1].old_start, 10);
-    }
+    fn test_branch_checkout_conflict() {
+        let repo = init_test_repo("checkout-conflict");
+        let linked = temp_path("checkout-conflict-wt");
+        let path = repo.to_string_lossy();
+        run_git(
+            &path,
+            &[
+                "worktree",
+                "add",
+                "-q",
+                "-b",
+                "side",
+                &linked.to_string_lossy(),
+            ],
+        )
+        .unwrap();
+        run_git(&path, &["branch", "free"]).unwrap();
 
-    // ==================== parse_status_porcelain tests ====================
+        let holder = branch_checkout_conflict(&path, "side").unwrap().unwrap();
+        assert_eq!(
+            std::fs::canonicalize(holder).unwrap(),
+            std::fs::canonicalize(&linked).unwrap()
+        );
+        assert!(branch_checkout_conflict(&path, "refs/heads/side")
+            .unwrap()
+            .is_some());
+        assert_eq!(branch_checkout_conflict(&path, "free").unwrap(), None);
 
-    #[test]
-    fn test_status_clean() {
-        let status = parse_status_porcelain("");
-        assert!(status.is_clean);
-        assert_eq!(status.modified, 0);
-        assert_eq!(status.staged, 0);
-        assert_eq!(status.untracked, 0);
-        assert_eq!(status.conflicted, 0);
+        let _ = std::fs::remove_dir_all(&linked);
+        let _ = std::fs::remove_dir_all(&repo);
     }
 
+    // ==================== check_ignore tests ====================
+
     #[test]
-    fn test_status_modified_unstaged() {
-        let status = parse_status_porcelain(" M src/main.rs\n");
-        assert!(!status.is_clean);
-        assert_eq!(status.modified, 1);
-        assert_eq!(status.staged, 0);
+    fn test_parse_check_ignore_output() {
+        let output = ".gitignore:2:*.log\tdebug.log\n\
+                      .gitignore:3:!keep.log\tkeep.log\n\
+                      /home/me/.config/git/ignore:1:a:b\tsrc/a:b\n\
+                      ::\tsrc/main.rs\n";
+        let results = parse_check_ignore_output(output);
+        assert_eq!(results.len(), 4);
+
+        assert_eq!(results[0].path, "debug.log");
+        assert!(results[0].ignored);
+        assert_eq!(results[0].source.as_deref(), Some(".gitignore"));
+        assert_eq!(results[0].pattern.as_deref(), Some("*.log"));
+
+        // Negated pattern matched: explicitly kept
+        assert!(!results[1].ignored);
+        assert_eq!(results[1].pattern.as_deref(), Some("!keep.log"));
+
+        assert!(results[2].ignored);
+        assert_eq!(
+            results[2].source.as_deref(),
+            Some("/home/me/.config/git/ignore")
+        );
+        assert_eq!(results[2].pattern.as_deref(), Some("a:b"));
+
+        assert_eq!(results[3].path, "src/main.rs");
+        assert!(!results[3].ignored);
+        assert_eq!(results[3].source, None);
+        assert_eq!(results[3].pattern, None);
     }
 
     #[test]
-    fn test_status_staged() {
-        let status = parse_status_porcelain("M  src/main.rs\n");
-        assert!(!status.is_clean);
-        assert_eq!(status.staged, 1);
-        assert_eq!(status.modified, 0);
+    fn test_check_ignore() {
+        let repo = init_test_repo("check-ignore");
+        std::fs::write(repo.join(".gitignore"), "*.log\n").unwrap();
+
+        let results = check_ignore(
+            &repo.to_string_lossy(),
+            vec!["build.log".to_string(), "src/main.rs".to_string()],
+        )
+        .unwrap();
+        let summary: Vec<(&str, bool)> = results
+            .iter()
+            .map(|r| (r.path.as_str(), r.ignored))
+            .collect();
+        assert_eq!(summary, vec![("build.log", true), ("src/main.rs", false)]);
+
+        let _ = std::fs::remove_dir_all(&repo);
     }
 
+    // ==================== parse_trailers tests ====================
+
     #[test]
-    fn test_status_added_staged() {
-        let status = parse_status_porcelain("A  new_file.rs\n");
-        assert!(!status.is_clean);
-        assert_eq!(status.staged, 1);
+    fn test_trailers_multiple() {
+        let message = "Add login form\n\nValidates input on submit.\n\n\
+                       Co-authored-by: Alice <alice@example.com>\n\
+                       Fixes: #42\n\
+                       Signed-off-by: Bob <bob@example.com>";
+        assert_eq!(
+            parse_trailers(message),
+            vec![
+                (
+                    "Co-authored-by".to_string(),
+                    "Alice <alice@example.com>".to_string()
+                ),
+                ("Fixes".to_string(), "#42".to_string()),
+                (
+                    "Signed-off-by".to_string(),
+                    "Bob <bob@example.com>".to_string()
+                ),
+            ]
+        );
     }
 
     #[test]
-    fn test_status_deleted_staged() {
-        let status = parse_status_porcelain("D  old_file.rs\n");
-        assert!(!status.is_clean);
-        assert_eq!(status.staged, 1);
+    fn test_trailers_none() {
+        assert!(parse_trailers("Fix: crash on startup").is_empty());
+        assert!(parse_trailers("Fix crash\n\nThe cache was read before init.").is_empty());
+        assert!(parse_trailers("").is_empty());
     }
 
     #[test]
-    fn test_status_untracked() {
-        let status = parse_status_porcelain("?? untracked.txt\n");
-        assert!(!status.is_clean);
-        assert_eq!(status.untracked, 1);
+    fn test_trailers_ignore_mid_message_colons() {
+        // "Note:" sits in a middle paragraph; the last paragraph mixes prose with "Key: value"
+        let message = "Refactor parser\n\nNote: behaviour is unchanged\n\n\
+                       Reviewed-by: Carol\nThanks to everyone who tested this";
+        assert!(parse_trailers(message).is_empty());
+
+        let message = "Refactor parser\n\nNote: behaviour is unchanged\n\n\
+                       Signed-off-by: Carol <carol@example.com>";
+        assert_eq!(
+            parse_trailers(message),
+            vec![(
+                "Signed-off-by".to_string(),
+                "Carol <carol@example.com>".to_string()
+            )]
+        );
     }
 
     #[test]
-    fn test_status_conflicts() {
-        // UU - both modified (merge conflict)
-        let status = parse_status_porcelain("UU conflicted.rs\n");
-        assert!(!status.is_clean);
-        assert_eq!(status.conflicted, 1);
-
-        // AA - both added
-        let status = parse_status_porcelain("AA both_added.rs\n");
-        assert_eq!(status.conflicted, 1);
-
-        // DD - both deleted
-        let status = parse_status_porcelain("DD both_deleted.rs\n");
-        assert_eq!(status.conflicted, 1);
+    fn test_trailers_continuation_line() {
+        let message = "Subject\n\nBug: first line\n  continued here";
+        assert_eq!(
+            parse_trailers(message),
+            vec![("Bug".to_string(), "first line continued here".to_string())]
+        );
     }
 
     #[test]
-    fn test_status_mixed() {
-        let output = "M  staged.rs\n M modified.rs\n?? untracked.txt\nUU conflict.rs\n";
-        let status = parse_status_porcelain(output);
-        assert!(!status.is_clean);
-        assert_eq!(status.staged, 1);
-        assert_eq!(status.modified, 1);
-        assert_eq!(status.untracked, 1);
-        assert_eq!(status.conflicted, 1);
+    fn test_commit_info_includes_trailers() {
+        let repo = init_test_repo("trailers");
+        let path = repo.to_string_lossy();
+        std::fs::write(repo.join("README.md"), "signed\n").unwrap();
+        run_git(&path, &["commit", "-q", "-s", "-am", "Sign readme"]).unwrap();
+
+        let commit = get_commit_info(&path, "HEAD").unwrap();
+        assert_eq!(commit.trailers.len(), 1);
+        assert_eq!(commit.trailers[0].0, "Signed-off-by");
+        assert!(commit.message.contains("Signed-off-by:"));
+
+        let _ = std::fs::remove_dir_all(&repo);
     }
 
     // ==================== parse_commit_log tests ====================
 
     #[test]
     fn test_commit_log_single() {
-        // Format: hash, short_hash, author_name, author_email, timestamp, summary, body
-        let output = "abc123def456\x1fabc123\x1fJohn Doe\x1fjohn@example.com\x1f1700000000\x1fFix bug\x1fDetailed description\x1e";
+        // Format: hash, short_hash, author_name, author_email, commit time, author time, summary, body
+        let output = "abc123def456\x1fabc123\x1fJohn Doe\x1fjohn@example.com\x1f1700000000\x1f1700000000\x1fFix bug\x1fDetailed description\x1e";
         let commits = parse_commit_log(output);
         assert_eq!(commits.len(), 1);
         assert_eq!(commits[0].hash, "abc123def456");
@@ -1015,14 +10410,15 @@ index abc..def 100644
         assert_eq!(commits[0].author_name, "John Doe");
         assert_eq!(commits[0].author_email, "john@example.com");
         assert_eq!(commits[0].timestamp, 1700000000);
+        assert_eq!(commits[0].author_timestamp, 1700000000);
         assert_eq!(commits[0].summary, "Fix bug");
         assert_eq!(commits[0].message, "Detailed description");
     }
 
     #[test]
     fn test_commit_log_multiple() {
-        let output = "hash1\x1fh1\x1fAlice\x1falice@test.com\x1f1700000000\x1fFirst\x1fBody1\x1e\
-                      hash2\x1fh2\x1fBob\x1fbob@test.com\x1f1700001000\x1fSecond\x1fBody2\x1e";
+        let output = "hash1\x1fh1\x1fAlice\x1falice@test.com\x1f1700000000\x1f1700000000\x1fFirst\x1fBody1\x1e\
+                      hash2\x1fh2\x1fBob\x1fbob@test.com\x1f1700001000\x1f1700001000\x1fSecond\x1fBody2\x1e";
         let commits = parse_commit_log(output);
         assert_eq!(commits.len(), 2);
         assert_eq!(commits[0].summary, "First");
@@ -1031,7 +10427,7 @@ index abc..def 100644
 
     #[test]
     fn test_commit_log_empty_body() {
-        let output = "hash\x1fh\x1fName\x1femail\x1f1700000000\x1fSummary\x1f\x1e";
+        let output = "hash\x1fh\x1fName\x1femail\x1f1700000000\x1f1700000000\x1fSummary\x1f\x1e";
         let commits = parse_commit_log(output);
         assert_eq!(commits.len(), 1);
         assert_eq!(commits[0].message, "");
@@ -1039,12 +10435,72 @@ index abc..def 100644
 
     #[test]
     fn test_commit_log_multiline_body() {
-        let output = "hash\x1fh\x1fName\x1femail\x1f1700000000\x1fSummary\x1fLine1\nLine2\nLine3\x1e";
+        let output = "hash\x1fh\x1fName\x1femail\x1f1700000000\x1f1700000000\x1fSummary\x1fLine1\nLine2\nLine3\x1e";
         let commits = parse_commit_log(output);
         assert_eq!(commits.len(), 1);
         assert_eq!(commits[0].message, "Line1\nLine2\nLine3");
     }
 
+    #[test]
+    fn test_commit_log_parents() {
+        let output = "hash\x1fh\x1fName\x1femail\x1f1700000000\x1f1700000000\x1fMerge\x1fBody\n\x1fp1 p2\x1e";
+        let commits = parse_commit_log(output);
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].message, "Body");
+        assert_eq!(commits[0].parent_hashes, vec!["p1", "p2"]);
+
+        // Root commit has no parents
+        let output = "hash\x1fh\x1fName\x1femail\x1f1700000000\x1f1700000000\x1fRoot\x1f\x1f\x1e";
+        let commits = parse_commit_log(output);
+        assert!(commits[0].parent_hashes.is_empty());
+    }
+
+    #[test]
+    fn test_commit_log_author_vs_commit_time() {
+        let output =
+            "hash\x1fh\x1fName\x1femail\x1f1700005000\x1f1700000000\x1fRebased\x1f\x1f\x1e";
+        let commits = parse_commit_log(output);
+        assert_eq!(commits[0].timestamp, 1700005000);
+        assert_eq!(commits[0].author_timestamp, 1700000000);
+        assert_eq!(commits[0].summary, "Rebased");
+    }
+
+    #[test]
+    fn test_rebased_commit_keeps_author_time() {
+        let repo = init_test_repo("author-time");
+        let path = repo.to_string_lossy();
+        run_git(&path, &["checkout", "-q", "-b", "feature"]).unwrap();
+        std::fs::write(repo.join("feature.txt"), "feature\n").unwrap();
+        run_git(&path, &["add", "feature.txt"]).unwrap();
+        run_git(
+            &path,
+            &[
+                "commit",
+                "-q",
+                "-m",
+                "Feature",
+                "--date=2023-11-14T12:00:00+00:00",
+            ],
+        )
+        .unwrap();
+        run_git(&path, &["checkout", "-q", "main"]).unwrap();
+        run_git(
+            &path,
+            &["commit", "-q", "--allow-empty", "-m", "Main moved"],
+        )
+        .unwrap();
+        run_git(&path, &["checkout", "-q", "feature"]).unwrap();
+        // Rebasing rewrites the committer date but keeps the author date
+        run_git(&path, &["rebase", "-q", "main"]).unwrap();
+
+        let commit = get_commit_info(&path, "HEAD").unwrap();
+        assert_eq!(commit.summary, "Feature");
+        assert_eq!(commit.author_timestamp, 1699963200);
+        assert!(commit.timestamp > commit.author_timestamp);
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
     #[test]
     fn test_commit_log_empty() {
         let commits = parse_commit_log("");
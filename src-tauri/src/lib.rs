@@ -1,6 +1,7 @@
 mod commands;
 mod git;
 mod menu;
+mod operations;
 mod types;
 mod watcher;
 
@@ -11,17 +12,107 @@ pub use types::*;
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
+        .manage(operations::OperationRegistry::default())
         .invoke_handler(tauri::generate_handler![
             commands::list_worktrees,
+            commands::get_recent_worktrees,
+            commands::list_worktrees_by_attention,
+            commands::get_repo_summary,
+            commands::is_git_repository,
+            commands::init_repository,
+            commands::clone_repository,
+            commands::run_gc,
+            commands::cancel_operation,
+            commands::probe_repo_scale,
+            commands::check_integrity,
             commands::start_watching,
             commands::get_commit_history,
+            commands::get_commit_oneline,
+            commands::get_commit_graph,
             commands::get_commit_diff,
+            commands::get_revert_preview,
+            commands::stream_commit_diff,
+            commands::get_commit_file_diff,
+            commands::get_commit_preview,
+            commands::get_staged_file_diff,
+            commands::diff_file_against_rev,
+            commands::blame_file,
+            commands::get_diff_context,
+            commands::get_split_diff,
+            commands::get_blob,
+            commands::read_worktree_file,
             commands::get_working_diff,
+            commands::get_working_diff_tree,
+            commands::get_stash_diff,
+            commands::branch_stash_count,
+            commands::diff_name_only,
+            commands::file_count_delta,
+            commands::get_remote_head,
+            commands::get_range_numstat,
             commands::get_worktree_status,
+            commands::is_worktree_dirty,
+            commands::is_checkout_of,
+            commands::untracked_summary,
+            commands::get_last_modified_file,
+            commands::check_ignore,
+            commands::get_worktree_label,
+            commands::set_worktree_label,
+            commands::get_head_info,
+            commands::get_current_branch,
+            commands::detach_head,
+            commands::reattach_head,
+            commands::rename_current_branch,
+            commands::get_push_target,
+            commands::set_upstream,
+            commands::unset_upstream,
+            commands::get_outgoing_stat,
+            commands::get_worktree_config,
+            commands::get_sparse_patterns,
+            commands::get_submodule_status,
+            commands::commit_all,
+            commands::squash_last,
+            commands::uncommit_keep_changes,
+            commands::restore_file_from_commit,
+            commands::list_conflicts,
+            commands::get_conflict_versions,
+            commands::mark_resolved,
+            commands::set_assume_unchanged,
+            commands::list_assume_unchanged,
             commands::create_worktree,
+            commands::create_worktree_from_remote,
+            commands::rename_worktree_and_branch,
             commands::delete_worktree,
+            commands::find_missing_worktrees,
             commands::prune_worktrees,
             commands::list_branches,
+            commands::branch_checkout_conflict,
+            commands::branch_remote_presence,
+            commands::list_all_refs,
+            commands::resolve_tag,
+            commands::resolve_commit,
+            commands::commits_ahead_of_default,
+            commands::get_branch_commits,
+            commands::get_all_in_flight_commits,
+            commands::merge_base,
+            commands::is_branch_merged,
+            commands::can_fast_forward,
+            commands::merge_fast_forward,
+            commands::branches_containing,
+            commands::compare_branches,
+            commands::fetch_pull_request,
+            commands::format_patch,
+            commands::stage_lines,
+            commands::export_working_patch,
+            commands::transfer_changes,
+            commands::apply_mailbox,
+            commands::am_abort,
+            commands::get_reflog,
+            commands::get_branch_reflog,
+            commands::recent_branches,
+            commands::bisect_start,
+            commands::bisect_good,
+            commands::bisect_bad,
+            commands::bisect_reset,
             commands::open_in_terminal,
             commands::open_claude_in_terminal,
             commands::set_theme_menu_state
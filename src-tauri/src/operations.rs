@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Cancellation flags for long-running operations (e.g. clone), keyed by an id the
+/// frontend picks when starting the operation and passes to cancel_operation
+#[derive(Default)]
+pub struct OperationRegistry {
+    flags: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl OperationRegistry {
+    /// Track a new operation and return the flag it should poll for cancellation
+    pub fn register(&self, operation_id: &str) -> Arc<AtomicBool> {
+        let flag = Arc::new(AtomicBool::new(false));
+        self.flags
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(operation_id.to_string(), flag.clone());
+        flag
+    }
+
+    /// Request cancellation; returns false if no such operation is running
+    pub fn cancel(&self, operation_id: &str) -> bool {
+        match self
+            .flags
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(operation_id)
+        {
+            Some(flag) => {
+                flag.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stop tracking a finished operation
+    pub fn finish(&self, operation_id: &str) {
+        self.flags
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(operation_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_registered_operation() {
+        let registry = OperationRegistry::default();
+        let flag = registry.register("clone-1");
+        assert!(!flag.load(Ordering::SeqCst));
+
+        assert!(registry.cancel("clone-1"));
+        assert!(flag.load(Ordering::SeqCst));
+
+        registry.finish("clone-1");
+        assert!(!registry.cancel("clone-1"));
+    }
+}
@@ -10,6 +10,10 @@ pub struct Worktree {
     /// Status is optional for lazy loading - initially None, fetched separately
     pub status: Option<WorktreeStatus>,
     pub last_commit_timestamp: i64,
+    /// Uses sparse-checkout, so files outside the patterns are intentionally absent
+    pub sparse: bool,
+    /// User-chosen display name, stored per worktree (see set_worktree_label)
+    pub label: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +22,16 @@ pub struct HeadInfo {
     pub commit_sha: String,
     pub commit_message: String,
     pub upstream: Option<UpstreamInfo>,
+    /// HEAD points at a branch with no commits yet; commit_sha and commit_message are empty
+    pub unborn: bool,
+}
+
+/// Where a push from the current branch would go
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PushTarget {
+    pub remote: String,
+    pub url: String,
+    pub branch: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,6 +48,70 @@ pub struct WorktreeStatus {
     pub staged: u32,
     pub untracked: u32,
     pub conflicted: u32,
+    /// False when untracked files weren't scanned (UntrackedMode::No); untracked is then 0
+    pub untracked_scanned: bool,
+}
+
+/// How "git status" looks for untracked files ("--untracked-files=...")
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UntrackedMode {
+    /// Skip untracked files entirely (fastest)
+    No,
+    /// Collapse wholly untracked directories to one entry (git's default)
+    #[default]
+    Normal,
+    /// List every untracked file
+    All,
+}
+
+/// Whether a path is ignored, and by which exclude rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IgnoreResult {
+    pub path: String,
+    pub ignored: bool,
+    /// File holding the matching rule (e.g. ".gitignore"); None when no rule matched
+    pub source: Option<String>,
+    pub pattern: Option<String>,
+}
+
+/// Untracked paths with untracked directories collapsed to a single "dir/" entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UntrackedSummary {
+    pub top_level_entries: Vec<String>,
+    /// Set when a collapsed directory hides how many files it contains
+    pub approximate: bool,
+}
+
+/// Raw (possibly truncated) content of a working-tree file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileBytes {
+    pub data_base64: String,
+    /// The file is larger than the bytes returned
+    pub truncated: bool,
+    pub total_size: u64,
+    /// A NUL byte was found in the returned data
+    pub is_binary: bool,
+}
+
+/// One line of "git blame" output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlameLine {
+    /// 1-based line number in the blamed revision of the file
+    pub line_number: u32,
+    /// Line number in the commit that introduced the line
+    pub original_line: u32,
+    pub commit_sha: String,
+    pub author_name: String,
+    pub author_time: i64,
+    pub summary: String,
+    pub content: String,
+}
+
+/// A tracked file and its modification time (unix seconds)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileMtime {
+    pub path: String,
+    pub mtime: i64,
 }
 
 // Commit history types
@@ -43,9 +121,53 @@ pub struct CommitInfo {
     pub short_hash: String,
     pub author_name: String,
     pub author_email: String,
+    /// Commit (committer) time; differs from author_timestamp after a rebase or amend
     pub timestamp: i64,
+    pub author_timestamp: i64,
     pub message: String,
     pub summary: String,
+    pub parent_hashes: Vec<String>,
+    /// Trailing "Key: value" lines of the message (sign-offs, co-authors); `message` keeps them
+    pub trailers: Vec<(String, String)>,
+}
+
+/// Minimal commit record for dense history views
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitOneline {
+    pub short_hash: String,
+    pub summary: String,
+    /// Decorations as git prints them, e.g. "HEAD -> main", "origin/main", "tag: v1.0"
+    pub refs: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitGraph {
+    pub commits: Vec<CommitInfo>,
+    /// One row per commit, in the same order as `commits`
+    pub rows: Vec<GraphRow>,
+    pub lane_count: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphRow {
+    pub lane: u32,
+    /// Segments drawn from this row to the next one
+    pub edges: Vec<GraphEdge>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphEdge {
+    pub from_lane: u32,
+    pub to_lane: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BranchComparison {
+    pub merge_base: String,
+    pub a_ahead: u32,
+    pub b_ahead: u32,
+    pub a_only: Vec<CommitInfo>,
+    pub b_only: Vec<CommitInfo>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -62,6 +184,24 @@ pub struct FileDiff {
     pub old_path: Option<String>,
     pub hunks: Vec<DiffHunk>,
     pub binary: bool,
+    /// File modes from "old mode"/"new mode" headers, set only when the mode changed
+    pub old_mode: Option<String>,
+    pub new_mode: Option<String>,
+    /// Executable bit change derived from old_mode/new_mode
+    pub chmod: Option<ChmodChange>,
+    /// Blob shas from the "index" line, set for binary files (None on an added/deleted side)
+    pub old_blob_sha: Option<String>,
+    pub new_blob_sha: Option<String>,
+    /// MIME type guessed from the extension, set for binary files
+    pub mime_guess: Option<String>,
+    /// "+++---" bar from git --stat, set for text files in commit diffs
+    pub stat_graph: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChmodChange {
+    pub made_executable: bool,
+    pub removed_executable: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -80,12 +220,34 @@ pub struct DiffHunk {
     pub new_lines: u32,
     pub header: String,
     pub lines: Vec<DiffLine>,
+    /// Count of '+' lines in this hunk
+    pub additions: u32,
+    /// Count of '-' lines in this hunk
+    pub deletions: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DiffLine {
     pub kind: char,
     pub content: String,
+    pub old_lineno: Option<u32>,
+    pub new_lineno: Option<u32>,
+}
+
+/// One row of a side-by-side diff; a missing side renders as blank
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitRow {
+    pub left: Option<DiffLine>,
+    pub right: Option<DiffLine>,
+}
+
+/// Files added, deleted, modified, and renamed between two revisions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileCountDelta {
+    pub added: u32,
+    pub deleted: u32,
+    pub modified: u32,
+    pub renamed: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -95,6 +257,17 @@ pub struct DiffStats {
     pub deletions: u32,
 }
 
+/// Per-file line counts from "git diff --numstat"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileNumstat {
+    pub path: String,
+    /// Previous path for renames
+    pub old_path: Option<String>,
+    /// None for binary files
+    pub additions: Option<u32>,
+    pub deletions: Option<u32>,
+}
+
 // Working directory (uncommitted) changes
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorkingDiff {
@@ -103,6 +276,32 @@ pub struct WorkingDiff {
     pub stats: DiffStats,
 }
 
+/// A node of the working diff grouped by directory; directory counts roll up everything
+/// below them. A file changed both in the index and the working tree is one node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffTree {
+    /// Last path component; empty for the root
+    pub name: String,
+    /// Path relative to the worktree root; empty for the root
+    pub path: String,
+    pub is_file: bool,
+    pub additions: u32,
+    pub deletions: u32,
+    pub file_count: u32,
+    /// Directories first, then files, each sorted by name
+    pub children: Vec<DiffTree>,
+}
+
+/// Which halves of the working diff to compute
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiffScope {
+    #[default]
+    All,
+    StagedOnly,
+    /// Unstaged changes plus untracked files
+    UnstagedOnly,
+}
+
 // Worktree management types
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateWorktreeOptions {
@@ -110,6 +309,18 @@ pub struct CreateWorktreeOptions {
     pub new_branch: Option<String>,
     pub commit_ish: Option<String>,
     pub detach: bool,
+    /// Start new_branch with no history (requires new_branch; excludes commit_ish/detach)
+    #[serde(default)]
+    pub orphan: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateWorktreeResult {
+    pub worktree: Worktree,
+    /// Branch created by the add (explicitly or via remote-branch DWIM), as git reported it
+    pub created_branch: Option<String>,
+    /// Non-fatal messages git printed while adding the worktree
+    pub warnings: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -124,3 +335,179 @@ pub struct BranchInfo {
     pub is_remote: bool,
     pub is_checked_out: bool,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefEntry {
+    pub full_name: String,
+    pub short_name: String,
+    pub kind: RefKind,
+    pub target_sha: String,
+    /// Symbolic refs (e.g. refs/remotes/origin/HEAD) point at another ref
+    pub is_symbolic: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RefKind {
+    LocalBranch,
+    RemoteBranch,
+    Tag,
+    Note,
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AmResult {
+    pub applied: u32,
+    pub conflicts: bool,
+    pub message: String,
+}
+
+/// Index stages of a conflicted file; a side is None when it doesn't have the file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConflictVersions {
+    pub base: Option<String>,
+    pub ours: Option<String>,
+    pub theirs: Option<String>,
+}
+
+/// A conflicted file in the working tree
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConflictEntry {
+    pub path: String,
+    /// Markers include a diff3-style "|||||||" base section
+    pub has_base_section: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarkResolvedResult {
+    pub status: WorktreeStatus,
+    /// Staged files that still contain conflict markers (may be intentional, e.g. docs)
+    pub warnings: Vec<String>,
+}
+
+/// Outcome of resolving a user-supplied revision to a commit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResolveResult {
+    Resolved(String),
+    /// An abbreviated sha matched several commits; full shas of the candidates
+    Ambiguous(Vec<String>),
+}
+
+/// Progress of a "git bisect" session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BisectState {
+    pub remaining_steps: Option<u32>,
+    /// Commit checked out for testing
+    pub current_sha: Option<String>,
+    pub finished: bool,
+    pub first_bad: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReflogEntry {
+    pub selector: String,
+    pub old_sha: String,
+    pub new_sha: String,
+    pub action: String,
+    pub message: String,
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepoProbe {
+    pub is_repo: bool,
+    pub toplevel: Option<String>,
+    pub is_worktree: bool,
+}
+
+/// A worktree's commits that aren't on the default branch yet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorktreeCommits {
+    pub worktree_path: String,
+    /// None when HEAD is detached
+    pub branch: Option<String>,
+    pub commits: Vec<CommitInfo>,
+}
+
+/// Worktree counts across a repository, for a summary badge
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepoSummary {
+    pub total_worktrees: u32,
+    /// Worktrees with any staged, modified, untracked, or conflicted files
+    pub dirty: u32,
+    pub conflicted: u32,
+    /// Worktrees whose branch is ahead of / behind its upstream
+    pub ahead: u32,
+    pub behind: u32,
+}
+
+/// Rough repo size, used to warn before an expensive worktree scan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepoScale {
+    pub worktree_count: u32,
+    /// Commits reachable from HEAD; None when HEAD is unborn
+    pub approx_commit_count: Option<u64>,
+    pub large: bool,
+}
+
+/// Result of "git fsck": dangling objects are harmless, errors mean corruption
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    pub ok: bool,
+    /// Unreferenced objects as "<type> <sha>"
+    pub dangling: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+/// Payload of the "commit-diff-file" event, one per file of a streamed commit diff
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamedFileDiff {
+    pub commit_sha: String,
+    pub file: FileDiff,
+}
+
+/// Outcome of "git gc": object directory size in bytes before and after
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GcResult {
+    pub before_size: u64,
+    pub after_size: u64,
+    /// Anything git printed to stderr (usually empty)
+    pub message: String,
+}
+
+/// Payload of the "operation-progress" event for long-running operations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperationProgress {
+    pub operation_id: String,
+    /// Git's phase label, e.g. "Receiving objects"
+    pub phase: String,
+    pub percent: u32,
+}
+
+/// Payload of the "worktree-changed" event so the frontend can pick a cheap refresh
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WatchKind {
+    /// Working files or the index changed - refresh status / working diff
+    FilesChanged,
+    /// HEAD or a ref moved (commit, checkout, reset) - refresh HeadInfo
+    HeadChanged,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmoduleStatus {
+    pub path: String,
+    pub sha: String,
+    pub state: SubmoduleState,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SubmoduleState {
+    /// Not initialized ("-")
+    Uninitialized,
+    /// Checked-out commit differs from the one recorded in the superproject ("+")
+    OutOfDate,
+    /// Merge conflicts ("U")
+    MergeConflict,
+    /// Checked out at the recorded commit (" ")
+    Clean,
+}
@@ -1,5 +1,7 @@
+use crate::git;
+use crate::types::WatchKind;
 use notify_debouncer_mini::{new_debouncer, DebouncedEventKind};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 use std::time::Duration;
 use tauri::{AppHandle, Emitter, Manager};
@@ -9,6 +11,9 @@ pub fn start_watching(app: AppHandle, paths: Vec<String>) -> Result<(), String>
 
     let mut debouncer = new_debouncer(Duration::from_millis(200), tx).map_err(|e| e.to_string())?;
 
+    // HEAD files and ref locations whose changes mean HEAD moved rather than files changed
+    let mut head_paths: Vec<PathBuf> = Vec::new();
+
     for path_str in &paths {
         let path = Path::new(path_str);
         // Watch the .git directory if it exists, otherwise the path itself
@@ -23,6 +28,33 @@ pub fn start_watching(app: AppHandle, paths: Vec<String>) -> Result<(), String>
             .watcher()
             .watch(&target, notify::RecursiveMode::Recursive)
             .map_err(|e| format!("Failed to watch {}: {}", target.display(), e))?;
+
+        // Linked worktrees keep HEAD in their private gitdir (.git/worktrees/<name>)
+        // and share refs through the common dir, neither of which is under `target`
+        let Ok((git_dir, common_dir)) = git::get_git_dirs(path_str) else {
+            continue;
+        };
+        let git_dir = git_dir.canonicalize().unwrap_or(git_dir);
+        let common_dir = common_dir.canonicalize().unwrap_or(common_dir);
+        let target = target.canonicalize().unwrap_or(target);
+
+        let extra_watches = [
+            (git_dir.clone(), notify::RecursiveMode::NonRecursive),
+            (common_dir.join("refs"), notify::RecursiveMode::Recursive),
+        ];
+        for (extra, mode) in extra_watches {
+            if extra.starts_with(&target) || !extra.exists() {
+                continue;
+            }
+            debouncer
+                .watcher()
+                .watch(&extra, mode)
+                .map_err(|e| format!("Failed to watch {}: {}", extra.display(), e))?;
+        }
+
+        head_paths.push(git_dir.join("HEAD"));
+        head_paths.push(common_dir.join("refs"));
+        head_paths.push(common_dir.join("packed-refs"));
     }
 
     // Store the debouncer in app state to keep it alive
@@ -34,12 +66,19 @@ pub fn start_watching(app: AppHandle, paths: Vec<String>) -> Result<(), String>
         while let Ok(result) = rx.recv() {
             match result {
                 Ok(events) => {
-                    // Only emit if there are actual changes
-                    let has_changes = events
+                    // Only emit if there are actual changes, once per kind
+                    let mut kinds: Vec<WatchKind> = Vec::new();
+                    for event in events
                         .iter()
-                        .any(|e| matches!(e.kind, DebouncedEventKind::Any));
-                    if has_changes {
-                        let _ = app_handle.emit("worktree-changed", ());
+                        .filter(|e| matches!(e.kind, DebouncedEventKind::Any))
+                    {
+                        let kind = classify_event(&event.path, &head_paths);
+                        if !kinds.contains(&kind) {
+                            kinds.push(kind);
+                        }
+                    }
+                    for kind in kinds {
+                        let _ = app_handle.emit("worktree-changed", kind);
                     }
                 }
                 Err(e) => eprintln!("Watch error: {:?}", e),
@@ -50,7 +89,70 @@ pub fn start_watching(app: AppHandle, paths: Vec<String>) -> Result<(), String>
     Ok(())
 }
 
+/// Map a changed path to the kind of refresh it needs
+/// Git writes "<file>.lock" and renames it into place, so the suffix is ignored
+fn classify_event(path: &Path, head_paths: &[PathBuf]) -> WatchKind {
+    let path_str = path.to_string_lossy();
+    let path = Path::new(path_str.strip_suffix(".lock").unwrap_or(&path_str));
+
+    if head_paths.iter().any(|head| path.starts_with(head)) {
+        WatchKind::HeadChanged
+    } else {
+        WatchKind::FilesChanged
+    }
+}
+
 // State to keep the debouncer alive
 struct WatcherState {
     _debouncer: notify_debouncer_mini::Debouncer<notify::RecommendedWatcher>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn head_paths() -> Vec<PathBuf> {
+        vec![
+            PathBuf::from("/repo/.git/worktrees/feature/HEAD"),
+            PathBuf::from("/repo/.git/refs"),
+            PathBuf::from("/repo/.git/packed-refs"),
+        ]
+    }
+
+    #[test]
+    fn test_classify_head_modification() {
+        let kind = classify_event(
+            Path::new("/repo/.git/worktrees/feature/HEAD"),
+            &head_paths(),
+        );
+        assert_eq!(kind, WatchKind::HeadChanged);
+
+        let kind = classify_event(
+            Path::new("/repo/.git/worktrees/feature/HEAD.lock"),
+            &head_paths(),
+        );
+        assert_eq!(kind, WatchKind::HeadChanged);
+    }
+
+    #[test]
+    fn test_classify_ref_update() {
+        let kind = classify_event(Path::new("/repo/.git/refs/heads/main"), &head_paths());
+        assert_eq!(kind, WatchKind::HeadChanged);
+
+        let kind = classify_event(Path::new("/repo/.git/packed-refs"), &head_paths());
+        assert_eq!(kind, WatchKind::HeadChanged);
+    }
+
+    #[test]
+    fn test_classify_file_change() {
+        let kind = classify_event(
+            Path::new("/repo/.git/worktrees/feature/index"),
+            &head_paths(),
+        );
+        assert_eq!(kind, WatchKind::FilesChanged);
+
+        // ORIG_HEAD / FETCH_HEAD are not HEAD
+        let kind = classify_event(Path::new("/repo/.git/ORIG_HEAD"), &head_paths());
+        assert_eq!(kind, WatchKind::FilesChanged);
+    }
+}
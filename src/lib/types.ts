@@ -6,6 +6,8 @@ export interface Worktree {
   /** Status is optional for lazy loading - initially null, fetched separately */
   status: WorktreeStatus | null;
   last_commit_timestamp: number;
+  sparse: boolean;
+  label: string | null;
 }
 
 export interface HeadInfo {
@@ -13,6 +15,14 @@ export interface HeadInfo {
   commit_sha: string;
   commit_message: string;
   upstream: UpstreamInfo | null;
+  /** HEAD points at a branch with no commits yet; commit_sha and commit_message are empty */
+  unborn: boolean;
+}
+
+export interface PushTarget {
+  remote: string;
+  url: string;
+  branch: string;
 }
 
 export interface UpstreamInfo {
@@ -27,6 +37,43 @@ export interface WorktreeStatus {
   staged: number;
   untracked: number;
   conflicted: number;
+  untracked_scanned: boolean;
+}
+
+export type UntrackedMode = "No" | "Normal" | "All";
+
+export interface IgnoreResult {
+  path: string;
+  ignored: boolean;
+  source: string | null;
+  pattern: string | null;
+}
+
+export interface UntrackedSummary {
+  top_level_entries: string[];
+  approximate: boolean;
+}
+
+export interface BlameLine {
+  line_number: number;
+  original_line: number;
+  commit_sha: string;
+  author_name: string;
+  author_time: number;
+  summary: string;
+  content: string;
+}
+
+export interface FileBytes {
+  data_base64: string;
+  truncated: boolean;
+  total_size: number;
+  is_binary: boolean;
+}
+
+export interface FileMtime {
+  path: string;
+  mtime: number;
 }
 
 // Commit history types
@@ -36,8 +83,43 @@ export interface CommitInfo {
   author_name: string;
   author_email: string;
   timestamp: number;
+  author_timestamp: number;
   message: string;
   summary: string;
+  parent_hashes: string[];
+  trailers: [string, string][];
+}
+
+export interface CommitOneline {
+  short_hash: string;
+  summary: string;
+  refs: string[];
+}
+
+export interface CommitGraph {
+  commits: CommitInfo[];
+  /** One row per commit, in the same order as commits */
+  rows: GraphRow[];
+  lane_count: number;
+}
+
+export interface GraphRow {
+  lane: number;
+  /** Segments drawn from this row to the next one */
+  edges: GraphEdge[];
+}
+
+export interface GraphEdge {
+  from_lane: number;
+  to_lane: number;
+}
+
+export interface BranchComparison {
+  merge_base: string;
+  a_ahead: number;
+  b_ahead: number;
+  a_only: CommitInfo[];
+  b_only: CommitInfo[];
 }
 
 export interface CommitDiff {
@@ -52,6 +134,18 @@ export interface FileDiff {
   old_path: string | null;
   hunks: DiffHunk[];
   binary: boolean;
+  old_mode: string | null;
+  new_mode: string | null;
+  chmod: ChmodChange | null;
+  old_blob_sha: string | null;
+  new_blob_sha: string | null;
+  mime_guess: string | null;
+  stat_graph: string | null;
+}
+
+export interface ChmodChange {
+  made_executable: boolean;
+  removed_executable: boolean;
 }
 
 export type FileStatus = "Added" | "Modified" | "Deleted" | "Renamed";
@@ -63,11 +157,27 @@ export interface DiffHunk {
   new_lines: number;
   header: string;
   lines: DiffLine[];
+  additions: number;
+  deletions: number;
 }
 
 export interface DiffLine {
   kind: string;
   content: string;
+  old_lineno: number | null;
+  new_lineno: number | null;
+}
+
+export interface SplitRow {
+  left: DiffLine | null;
+  right: DiffLine | null;
+}
+
+export interface FileCountDelta {
+  added: number;
+  deleted: number;
+  modified: number;
+  renamed: number;
 }
 
 export interface DiffStats {
@@ -76,6 +186,13 @@ export interface DiffStats {
   deletions: number;
 }
 
+export interface FileNumstat {
+  path: string;
+  old_path: string | null;
+  additions: number | null;
+  deletions: number | null;
+}
+
 // Working directory (uncommitted) changes
 export interface WorkingDiff {
   staged_files: FileDiff[];
@@ -83,12 +200,32 @@ export interface WorkingDiff {
   stats: DiffStats;
 }
 
+export interface DiffTree {
+  name: string;
+  path: string;
+  is_file: boolean;
+  additions: number;
+  deletions: number;
+  file_count: number;
+  children: DiffTree[];
+}
+
+export type DiffScope = "All" | "StagedOnly" | "UnstagedOnly";
+
 // Worktree management types
 export interface CreateWorktreeOptions {
   path: string;
   new_branch: string | null;
   commit_ish: string | null;
   detach: boolean;
+  /** Start new_branch with no history (requires new_branch) */
+  orphan: boolean;
+}
+
+export interface CreateWorktreeResult {
+  worktree: Worktree;
+  created_branch: string | null;
+  warnings: string[];
 }
 
 export interface PruneResult {
@@ -101,3 +238,114 @@ export interface BranchInfo {
   is_remote: boolean;
   is_checked_out: boolean;
 }
+
+export interface RefEntry {
+  full_name: string;
+  short_name: string;
+  kind: RefKind;
+  target_sha: string;
+  is_symbolic: boolean;
+}
+
+export type RefKind = "LocalBranch" | "RemoteBranch" | "Tag" | "Note" | "Other";
+
+export interface AmResult {
+  applied: number;
+  conflicts: boolean;
+  message: string;
+}
+
+export type ResolveResult = { Resolved: string } | { Ambiguous: string[] };
+
+export interface ConflictEntry {
+  path: string;
+  has_base_section: boolean;
+}
+
+export interface MarkResolvedResult {
+  status: WorktreeStatus;
+  warnings: string[];
+}
+
+export interface ConflictVersions {
+  base: string | null;
+  ours: string | null;
+  theirs: string | null;
+}
+
+export interface BisectState {
+  remaining_steps: number | null;
+  current_sha: string | null;
+  finished: boolean;
+  first_bad: string | null;
+}
+
+export interface ReflogEntry {
+  selector: string;
+  old_sha: string;
+  new_sha: string;
+  action: string;
+  message: string;
+  timestamp: number;
+}
+
+export interface RepoProbe {
+  is_repo: boolean;
+  toplevel: string | null;
+  is_worktree: boolean;
+}
+
+export interface RepoSummary {
+  total_worktrees: number;
+  dirty: number;
+  conflicted: number;
+  ahead: number;
+  behind: number;
+}
+
+export interface WorktreeCommits {
+  worktree_path: string;
+  branch: string | null;
+  commits: CommitInfo[];
+}
+
+export interface RepoScale {
+  worktree_count: number;
+  approx_commit_count: number | null;
+  large: boolean;
+}
+
+export interface IntegrityReport {
+  ok: boolean;
+  dangling: string[];
+  errors: string[];
+}
+
+export interface GcResult {
+  before_size: number;
+  after_size: number;
+  message: string;
+}
+
+/** Payload of the "commit-diff-file" event */
+export interface StreamedFileDiff {
+  commit_sha: string;
+  file: FileDiff;
+}
+
+export interface OperationProgress {
+  operation_id: string;
+  phase: string;
+  percent: number;
+}
+
+/** Payload of the "worktree-changed" event */
+export type WatchKind = "FilesChanged" | "HeadChanged";
+
+export interface SubmoduleStatus {
+  path: string;
+  sha: string;
+  state: SubmoduleState;
+}
+
+export type SubmoduleState = "Uninitialized" | "OutOfDate" | "MergeConflict" | "Clean";